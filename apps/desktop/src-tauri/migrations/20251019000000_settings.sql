@@ -0,0 +1,7 @@
+-- migrations/20251019000000_settings.sql
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+INSERT OR IGNORE INTO settings (key, value) VALUES ('timezone', 'UTC');
@@ -0,0 +1,27 @@
+-- migrations/20251020000000_calendar.sql
+-- Weekly opening hours, one row per weekday (0 = Monday).
+CREATE TABLE IF NOT EXISTS calendar_hours (
+    weekday INTEGER PRIMARY KEY CHECK (weekday BETWEEN 0 AND 6),
+    is_open INTEGER NOT NULL DEFAULT 1,
+    opens_at TEXT,
+    closes_at TEXT
+);
+
+INSERT OR IGNORE INTO calendar_hours (weekday, is_open, opens_at, closes_at) VALUES
+    (0, 1, '08:00', '17:00'),
+    (1, 1, '08:00', '17:00'),
+    (2, 1, '08:00', '17:00'),
+    (3, 1, '08:00', '17:00'),
+    (4, 1, '08:00', '17:00'),
+    (5, 0, NULL, NULL),
+    (6, 0, NULL, NULL);
+
+-- Holidays and other closures. Both dates are inclusive local dates.
+CREATE TABLE IF NOT EXISTS calendar (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    start_date TEXT NOT NULL,
+    end_date TEXT NOT NULL,
+    source TEXT NOT NULL DEFAULT 'manual',
+    created_at TEXT NOT NULL
+);
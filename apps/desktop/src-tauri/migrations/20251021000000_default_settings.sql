@@ -0,0 +1,5 @@
+-- migrations/20251021000000_default_settings.sql
+INSERT OR IGNORE INTO settings (key, value) VALUES
+    ('library_name', 'Tiza Library'),
+    ('loan_period_days', '14'),
+    ('max_loans_per_student', '1');
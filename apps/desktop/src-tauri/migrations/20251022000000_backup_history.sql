@@ -0,0 +1,14 @@
+-- migrations/20251022000000_backup_history.sql
+CREATE TABLE IF NOT EXISTS backup_history (
+    id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    path TEXT NOT NULL,
+    size_bytes INTEGER,
+    success INTEGER NOT NULL,
+    message TEXT,
+    created_at TEXT NOT NULL
+);
+
+INSERT OR IGNORE INTO settings (key, value) VALUES
+    ('auto_backup', 'off'),
+    ('backup_directory', '');
@@ -0,0 +1,6 @@
+-- migrations/20251023000000_api_settings.sql
+INSERT OR IGNORE INTO settings (key, value) VALUES
+    ('api_enabled', 'false'),
+    ('api_bind', 'localhost'),
+    ('api_port', '8787'),
+    ('api_token', '');
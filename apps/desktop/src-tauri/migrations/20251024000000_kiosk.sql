@@ -0,0 +1,6 @@
+-- migrations/20251024000000_kiosk.sql
+ALTER TABLE lent ADD COLUMN renewal_count INTEGER NOT NULL DEFAULT 0;
+
+INSERT OR IGNORE INTO settings (key, value) VALUES
+    ('max_renewals', '1'),
+    ('kiosk_timeout_seconds', '90');
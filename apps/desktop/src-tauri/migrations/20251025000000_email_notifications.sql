@@ -0,0 +1,32 @@
+-- migrations/20251025000000_email_notifications.sql
+ALTER TABLE students ADD COLUMN email TEXT;
+ALTER TABLE students ADD COLUMN guardian_email TEXT;
+
+-- Every reminder sent (or attempted) about a loan. A reminder counts as sent
+-- once per loan, kind, recipient and due date, so renewing a loan makes it
+-- eligible for a new due-soon reminder.
+CREATE TABLE IF NOT EXISTS notification_log (
+    id TEXT PRIMARY KEY,
+    lending_id TEXT NOT NULL,
+    channel TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    due_date TEXT NOT NULL,
+    success INTEGER NOT NULL,
+    message TEXT,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (lending_id) REFERENCES lent(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_notification_log_lending
+    ON notification_log (lending_id, channel, kind);
+
+INSERT OR IGNORE INTO settings (key, value) VALUES
+    ('email_notifications', 'false'),
+    ('due_soon_days', '2'),
+    ('smtp_host', ''),
+    ('smtp_port', '587'),
+    ('smtp_security', 'starttls'),
+    ('smtp_username', ''),
+    ('smtp_password', ''),
+    ('smtp_from', '');
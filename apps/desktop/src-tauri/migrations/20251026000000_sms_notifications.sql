@@ -0,0 +1,11 @@
+-- migrations/20251026000000_sms_notifications.sql
+ALTER TABLE students ADD COLUMN sms_opt_out INTEGER NOT NULL DEFAULT 0;
+
+INSERT OR IGNORE INTO settings (key, value) VALUES
+    ('sms_notifications', 'false'),
+    ('sms_gateway', 'file'),
+    ('sms_country_code', '250'),
+    ('sms_http_url', ''),
+    ('sms_http_body', ''),
+    ('sms_http_authorization', ''),
+    ('sms_outbox_path', '');
@@ -0,0 +1,2 @@
+INSERT OR IGNORE INTO settings (key, value) VALUES
+    ('daily_digest', 'true');
@@ -0,0 +1,10 @@
+-- migrations/20251028000000_orphaned_loans.sql
+-- Foreign keys are enforced now. Loans left behind by books and students
+-- deleted before that point to nothing and would fail `PRAGMA
+-- foreign_key_check`, so drop them along with their reminder log.
+DELETE FROM lent
+WHERE book_id NOT IN (SELECT id FROM books)
+   OR student_id NOT IN (SELECT id FROM students);
+
+DELETE FROM notification_log
+WHERE lending_id NOT IN (SELECT id FROM lent);
@@ -0,0 +1,579 @@
+//! Optional local HTTP/JSON API over the same services the app uses, for the
+//! school intranet and kiosks. Off by default; see the `api_*` settings.
+
+use crate::db::Database;
+use crate::services;
+use crate::services::lendings::LendingError;
+use crate::services::settings::{ApiBind, Secret, Settings};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::{Pool, Sqlite};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+use tokio::sync::oneshot;
+
+/// Handle to the running server, managed by Tauri.
+#[derive(Default)]
+pub struct ApiServer {
+    // An async mutex, so two settings saves can't restart the server at once.
+    running: tokio::sync::Mutex<Option<RunningServer>>,
+}
+
+struct RunningServer {
+    config: ListenConfig,
+    addr: SocketAddr,
+    shutdown: oneshot::Sender<()>,
+    task: tauri::async_runtime::JoinHandle<()>,
+}
+
+impl RunningServer {
+    /// Signals the server and waits until it has let go of its port.
+    async fn stop(self) {
+        let _ = self.shutdown.send(());
+        let _ = self.task.await;
+    }
+}
+
+/// The settings the server is started with; any other setting can change
+/// without a restart.
+#[derive(PartialEq)]
+struct ListenConfig {
+    bind: ApiBind,
+    port: i64,
+    token: String,
+}
+
+/// Starts, restarts or stops the server to match the settings. Leaves a
+/// running server alone unless its address or token changed. Returns the
+/// bound address.
+pub async fn apply_settings(
+    app_handle: &AppHandle,
+    settings: &Settings,
+) -> Result<Option<SocketAddr>, String> {
+    let server = app_handle.state::<ApiServer>();
+    let mut running = server.running.lock().await;
+
+    let pool = app_handle.state::<Database>().pool();
+    let token = services::settings::get_secret(&pool, Secret::ApiToken)
+        .await
+        .map_err(|e| format!("could not load the API token: {}", e))?;
+    let config = settings.api_enabled.then(|| ListenConfig {
+        bind: settings.api_bind,
+        port: settings.api_port,
+        token,
+    });
+    if let (Some(current), Some(config)) = (running.as_ref(), config.as_ref()) {
+        if current.config == *config {
+            return Ok(Some(current.addr));
+        }
+    }
+    if let Some(current) = running.take() {
+        current.stop().await;
+    }
+    let Some(config) = config else {
+        return Ok(None);
+    };
+
+    let ip = match config.bind {
+        ApiBind::Localhost => Ipv4Addr::LOCALHOST,
+        ApiBind::Lan => Ipv4Addr::UNSPECIFIED,
+    };
+    let addr = SocketAddr::from((ip, config.port as u16));
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("could not start the API server on {}: {}", addr, e))?;
+
+    let pool_handle = app_handle.clone();
+    let state = ApiState {
+        pool: Arc::new(move || pool_handle.state::<Database>().pool()),
+        token: config.token.clone().into(),
+    };
+
+    let (shutdown, receiver) = oneshot::channel();
+    let task = tauri::async_runtime::spawn(async move {
+        let shutdown = async {
+            let _ = receiver.await;
+        };
+        if let Err(e) = axum::serve(listener, router(state))
+            .with_graceful_shutdown(shutdown)
+            .await
+        {
+            eprintln!("API server stopped: {}", e);
+        }
+    });
+    *running = Some(RunningServer {
+        config,
+        addr,
+        shutdown,
+        task,
+    });
+
+    println!("API server listening on http://{}", addr);
+    Ok(Some(addr))
+}
+
+/// Applies the API settings of the database that is open now: at start-up,
+/// and after a restore or archive import brought in another library's
+/// settings and token. Failures are logged, the app works without the API.
+pub async fn reload(app_handle: &AppHandle) {
+    let pool = app_handle.state::<Database>().pool();
+    match services::settings::get_settings(&pool).await {
+        Ok(settings) => {
+            if let Err(e) = apply_settings(app_handle, &settings).await {
+                eprintln!("{}", e);
+            }
+        }
+        Err(e) => eprintln!("Failed to load settings for the API server: {}", e),
+    }
+}
+
+/// Shared by all handlers. The pool is looked up per request because a
+/// restore swaps it while the app runs.
+#[derive(Clone)]
+pub struct ApiState {
+    pub pool: Arc<dyn Fn() -> Pool<Sqlite> + Send + Sync>,
+    pub token: Arc<str>,
+}
+
+impl ApiState {
+    fn pool(&self) -> Pool<Sqlite> {
+        (self.pool)()
+    }
+}
+
+pub fn router(state: ApiState) -> Router {
+    let api = Router::new()
+        .route("/books", get(list_books).post(create_book))
+        .route("/books/:id", get(get_book))
+        .route("/students", get(list_students).post(create_student))
+        .route("/students/:id", get(get_student))
+        .route("/lendings", get(list_lendings).post(create_lending))
+        .route("/lendings/:id", get(get_lending))
+        .route("/lendings/:id/return", post(return_lending))
+        .route("/stats/dashboard", get(dashboard_stats))
+        .route("/stats/overdue", get(overdue_books))
+        .route("/stats/popular", get(popular_books))
+        .route("/stats/recent", get(recent_activity))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_token))
+        // Registered after the auth layer, so it stays public.
+        .route("/openapi.json", get(openapi));
+
+    Router::new().nest("/api", api).with_state(state)
+}
+
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn not_found() -> Self {
+        Self::new(StatusCode::NOT_FOUND, "Not found")
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::RowNotFound => Self::not_found(),
+            e => Self::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        }
+    }
+}
+
+impl From<LendingError> for ApiError {
+    fn from(e: LendingError) -> Self {
+        match e {
+            LendingError::Database(e) => e.into(),
+            e @ (LendingError::Unavailable | LendingError::LoanLimit(_) | LendingError::NotLent) => {
+                Self::new(StatusCode::CONFLICT, e.to_string())
+            }
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({ "error": self.message }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn require_token(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(token) if !state.token.is_empty()
+            && constant_time_eq(token.as_bytes(), state.token.as_bytes()) =>
+        {
+            next.run(request).await
+        }
+        _ => ApiError::new(StatusCode::UNAUTHORIZED, "Missing or invalid API token")
+            .into_response(),
+    }
+}
+
+async fn list_books(State(state): State<ApiState>) -> ApiResult<Vec<crate::models::Book>> {
+    Ok(Json(services::books::get_all_books(&state.pool()).await?))
+}
+
+async fn get_book(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+) -> ApiResult<crate::models::Book> {
+    services::books::get_book_by_id(&state.pool(), &id)
+        .await?
+        .map(Json)
+        .ok_or_else(ApiError::not_found)
+}
+
+#[derive(Deserialize)]
+struct NewBook {
+    title: String,
+    author: String,
+    quantity: i32,
+    isbn: String,
+    category: String,
+}
+
+async fn create_book(
+    State(state): State<ApiState>,
+    Json(book): Json<NewBook>,
+) -> Result<StatusCode, ApiError> {
+    services::books::create_book(
+        &state.pool(),
+        &book.title,
+        &book.author,
+        book.quantity,
+        &book.isbn,
+        &book.category,
+    )
+    .await?;
+    Ok(StatusCode::CREATED)
+}
+
+async fn list_students(
+    State(state): State<ApiState>,
+) -> ApiResult<Vec<crate::models::Student>> {
+    Ok(Json(services::students::get_all_students(&state.pool()).await?))
+}
+
+async fn get_student(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+) -> ApiResult<crate::models::Student> {
+    services::students::get_student_by_id(&state.pool(), &id)
+        .await?
+        .map(Json)
+        .ok_or_else(ApiError::not_found)
+}
+
+#[derive(Deserialize)]
+struct NewStudent {
+    name: String,
+    grade: String,
+    phone_number: Option<String>,
+    email: Option<String>,
+    guardian_email: Option<String>,
+    student_id: String,
+}
+
+async fn create_student(
+    State(state): State<ApiState>,
+    Json(student): Json<NewStudent>,
+) -> Result<StatusCode, ApiError> {
+    services::students::create_student(
+        &state.pool(),
+        &student.name,
+        &student.grade,
+        student.phone_number.as_deref(),
+        student.email.as_deref(),
+        student.guardian_email.as_deref(),
+        &student.student_id,
+    )
+    .await?;
+    Ok(StatusCode::CREATED)
+}
+
+async fn list_lendings(
+    State(state): State<ApiState>,
+) -> ApiResult<Vec<crate::models::LendingWithDetails>> {
+    Ok(Json(services::lendings::get_all_lendings(&state.pool()).await?))
+}
+
+async fn get_lending(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+) -> ApiResult<crate::models::LendingWithDetails> {
+    services::lendings::get_lending_by_id(&state.pool(), &id)
+        .await?
+        .map(Json)
+        .ok_or_else(ApiError::not_found)
+}
+
+#[derive(Deserialize)]
+struct NewLending {
+    book_id: String,
+    student_id: String,
+    due_date: Option<DateTime<Utc>>,
+}
+
+async fn create_lending(
+    State(state): State<ApiState>,
+    Json(lending): Json<NewLending>,
+) -> Result<StatusCode, ApiError> {
+    services::lendings::create_lending(
+        &state.pool(),
+        &lending.book_id,
+        &lending.student_id,
+        lending.due_date,
+    )
+    .await?;
+    Ok(StatusCode::CREATED)
+}
+
+async fn return_lending(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    services::lendings::return_lending(&state.pool(), &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn dashboard_stats(
+    State(state): State<ApiState>,
+    Query(filter): Query<services::statistics::StatsFilter>,
+) -> ApiResult<services::statistics::DashboardStats> {
+    Ok(Json(
+        services::statistics::get_dashboard_stats(&state.pool(), &filter).await?,
+    ))
+}
+
+async fn overdue_books(
+    State(state): State<ApiState>,
+    Query(filter): Query<services::statistics::StatsFilter>,
+) -> ApiResult<Vec<services::statistics::OverdueBook>> {
+    Ok(Json(
+        services::statistics::get_overdue_books(&state.pool(), &filter).await?,
+    ))
+}
+
+async fn popular_books(
+    State(state): State<ApiState>,
+    Query(filter): Query<services::statistics::StatsFilter>,
+) -> ApiResult<Vec<services::statistics::PopularBook>> {
+    Ok(Json(
+        services::statistics::get_popular_books(&state.pool(), &filter).await?,
+    ))
+}
+
+async fn recent_activity(
+    State(state): State<ApiState>,
+    Query(filter): Query<services::statistics::StatsFilter>,
+) -> ApiResult<Vec<services::statistics::RecentActivity>> {
+    Ok(Json(
+        services::statistics::get_recent_activity(&state.pool(), &filter).await?,
+    ))
+}
+
+async fn openapi() -> Json<serde_json::Value> {
+    Json(openapi_document())
+}
+
+/// OpenAPI 3.0 description of the routes in [`router`].
+fn openapi_document() -> serde_json::Value {
+    let id_param = json!([{
+        "name": "id", "in": "path", "required": true, "schema": { "type": "string" }
+    }]);
+    let filter_params = json!([
+        { "name": "from", "in": "query", "schema": { "type": "string", "format": "date-time" } },
+        { "name": "to", "in": "query", "schema": { "type": "string", "format": "date-time" } },
+        { "name": "limit", "in": "query", "schema": { "type": "integer" } },
+        { "name": "grade", "in": "query", "schema": { "type": "string" } },
+        { "name": "category", "in": "query", "schema": { "type": "string" } }
+    ]);
+    let ok = |schema: serde_json::Value| {
+        json!({ "200": { "description": "OK", "content": { "application/json": { "schema": schema } } } })
+    };
+    let list = |name: &str| json!({ "type": "array", "items": { "$ref": format!("#/components/schemas/{}", name) } });
+    let one = |name: &str| json!({ "$ref": format!("#/components/schemas/{}", name) });
+    let body = |name: &str| {
+        json!({ "required": true, "content": { "application/json": { "schema": { "$ref": format!("#/components/schemas/{}", name) } } } })
+    };
+    let object = json!({ "type": "object" });
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Tiza Library API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Send the token from the app settings as `Authorization: Bearer <token>`."
+        },
+        "servers": [{ "url": "/api" }],
+        "security": [{ "bearerAuth": [] }],
+        "paths": {
+            "/books": {
+                "get": { "summary": "List books", "responses": ok(list("Book")) },
+                "post": { "summary": "Add a book", "requestBody": body("NewBook"), "responses": { "201": { "description": "Created" } } }
+            },
+            "/books/{id}": {
+                "get": { "summary": "Get a book", "parameters": id_param, "responses": ok(one("Book")) }
+            },
+            "/students": {
+                "get": { "summary": "List students", "responses": ok(list("Student")) },
+                "post": { "summary": "Register a student", "requestBody": body("NewStudent"), "responses": { "201": { "description": "Created" } } }
+            },
+            "/students/{id}": {
+                "get": { "summary": "Get a student", "parameters": id_param, "responses": ok(one("Student")) }
+            },
+            "/lendings": {
+                "get": { "summary": "List loans", "responses": ok(list("Lending")) },
+                "post": {
+                    "summary": "Lend a book",
+                    "requestBody": body("NewLending"),
+                    "responses": {
+                        "201": { "description": "Created" },
+                        "404": { "description": "No such book" },
+                        "409": { "description": "No copy is available or the student has reached the loan limit" }
+                    }
+                }
+            },
+            "/lendings/{id}": {
+                "get": { "summary": "Get a loan", "parameters": id_param, "responses": ok(one("Lending")) }
+            },
+            "/lendings/{id}/return": {
+                "post": {
+                    "summary": "Return a loan",
+                    "parameters": id_param,
+                    "responses": {
+                        "204": { "description": "Returned" },
+                        "404": { "description": "No such loan" },
+                        "409": { "description": "The loan was already returned" }
+                    }
+                }
+            },
+            "/stats/dashboard": {
+                "get": { "summary": "Dashboard statistics", "parameters": filter_params, "responses": ok(object.clone()) }
+            },
+            "/stats/overdue": {
+                "get": { "summary": "Overdue loans", "parameters": filter_params, "responses": ok(json!({ "type": "array", "items": object })) }
+            },
+            "/stats/popular": {
+                "get": { "summary": "Most borrowed books", "parameters": filter_params, "responses": ok(json!({ "type": "array", "items": object })) }
+            },
+            "/stats/recent": {
+                "get": { "summary": "Recent loans and returns", "parameters": filter_params, "responses": ok(json!({ "type": "array", "items": object })) }
+            },
+            "/openapi.json": {
+                "get": { "summary": "This document", "security": [], "responses": ok(object) }
+            }
+        },
+        "components": {
+            "securitySchemes": {
+                "bearerAuth": { "type": "http", "scheme": "bearer" }
+            },
+            "schemas": {
+                "Book": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string" },
+                        "title": { "type": "string" },
+                        "author": { "type": "string" },
+                        "quantity": { "type": "integer" },
+                        "isbn": { "type": "string" },
+                        "category": { "type": "string" },
+                        "status": { "type": "string", "enum": ["Available", "Unavailable"] },
+                        "created_at": { "type": "string", "format": "date-time" }
+                    }
+                },
+                "NewBook": {
+                    "type": "object",
+                    "required": ["title", "author", "quantity", "isbn", "category"],
+                    "properties": {
+                        "title": { "type": "string" },
+                        "author": { "type": "string" },
+                        "quantity": { "type": "integer" },
+                        "isbn": { "type": "string" },
+                        "category": { "type": "string" }
+                    }
+                },
+                "Student": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string" },
+                        "name": { "type": "string" },
+                        "grade": { "type": "string" },
+                        "phone_number": { "type": "string", "nullable": true },
+                        "email": { "type": "string", "nullable": true },
+                        "guardian_email": { "type": "string", "nullable": true },
+                        "sms_opt_out": { "type": "boolean" },
+                        "student_id": { "type": "string" },
+                        "status": { "type": "string", "enum": ["Active", "Inactive"] },
+                        "created_at": { "type": "string", "format": "date-time" }
+                    }
+                },
+                "NewStudent": {
+                    "type": "object",
+                    "required": ["name", "grade", "student_id"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "grade": { "type": "string" },
+                        "phone_number": { "type": "string", "nullable": true },
+                        "email": { "type": "string", "nullable": true },
+                        "guardian_email": { "type": "string", "nullable": true },
+                        "student_id": { "type": "string" }
+                    }
+                },
+                "Lending": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string" },
+                        "book_id": { "type": "string" },
+                        "student_id": { "type": "string" },
+                        "lent_at": { "type": "string", "format": "date-time" },
+                        "returned_at": { "type": "string", "format": "date-time", "nullable": true },
+                        "status": { "type": "string", "enum": ["Lent", "Returned"] },
+                        "due_date": { "type": "string", "format": "date-time" },
+                        "book_title": { "type": "string" },
+                        "book_author": { "type": "string" },
+                        "student_name": { "type": "string" },
+                        "student_number": { "type": "string" }
+                    }
+                },
+                "NewLending": {
+                    "type": "object",
+                    "required": ["book_id", "student_id"],
+                    "properties": {
+                        "book_id": { "type": "string" },
+                        "student_id": { "type": "string" },
+                        "due_date": { "type": "string", "format": "date-time", "nullable": true }
+                    }
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,320 @@
+//! Headless administration of a Tiza library database, for scripting and
+//! scheduled jobs:
+//!
+//! ```text
+//! tiza-cli --db ~/.local/share/tiza.com/library.db backup /srv/backups/library.db
+//! ```
+//!
+//! Encrypted backups and restores read the passphrase from the
+//! `TIZA_BACKUP_PASSPHRASE` environment variable.
+
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use tiza_lib::db::{self, Database, DbConfig};
+use tiza_lib::services;
+
+const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+const PASSPHRASE_VAR: &str = "TIZA_BACKUP_PASSPHRASE";
+
+#[derive(Parser)]
+#[command(name = "tiza-cli", version, about = "Administer a Tiza library without the app")]
+struct Cli {
+    /// Path to the library database (library.db).
+    #[arg(long, value_name = "PATH")]
+    db: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Apply pending migrations and print the schema version.
+    Migrate,
+    /// Run SQLite's integrity check without modifying the database.
+    Check,
+    /// Write a verified backup of the database.
+    Backup {
+        destination: PathBuf,
+        /// Encrypt with the passphrase in TIZA_BACKUP_PASSPHRASE.
+        #[arg(long)]
+        encrypt: bool,
+    },
+    /// Replace the database with a backup. The current database is kept as
+    /// library_pre_restore_<timestamp>.db next to it.
+    Restore { backup: PathBuf },
+    /// Export the whole library as a .tiza archive.
+    ExportArchive { destination: PathBuf },
+    /// Replace the library with the contents of a .tiza archive.
+    ImportArchive { archive: PathBuf },
+    /// Export books, students and loans as an Excel workbook.
+    ExportXlsx { destination: PathBuf },
+    /// Create and update students from a CSV or XLSX roster.
+    ImportRoster {
+        file: PathBuf,
+        #[arg(long, default_value = "Student ID")]
+        student_id_column: String,
+        #[arg(long, default_value = "Name")]
+        name_column: String,
+        #[arg(long, default_value = "Grade")]
+        grade_column: String,
+        #[arg(long)]
+        phone_column: Option<String>,
+        /// Deactivate active students that are not in the roster.
+        #[arg(long)]
+        deactivate_missing: bool,
+        /// Print the changes without applying them.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// List overdue loans.
+    Overdue {
+        #[arg(long)]
+        grade: Option<String>,
+        /// Print JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Move students to their next grade, e.g. --promote "Grade 6=Grade 7".
+    /// Leave the target empty ("Grade 12=") to graduate a grade.
+    Rollover {
+        #[arg(long = "promote", value_name = "FROM=TO", required = true)]
+        promotions: Vec<String>,
+        /// Print the changes without applying them.
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn passphrase() -> Result<String, String> {
+    std::env::var(PASSPHRASE_VAR)
+        .ok()
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| format!("Set {} to the backup passphrase", PASSPHRASE_VAR))
+}
+
+fn data_dir(db_path: &Path) -> &Path {
+    db_path.parent().unwrap_or_else(|| Path::new("."))
+}
+
+fn parse_progression(
+    promotions: &[String],
+) -> Result<services::rollover::GradeProgression, String> {
+    promotions
+        .iter()
+        .map(|promotion| {
+            let (from, to) = promotion
+                .split_once('=')
+                .ok_or_else(|| format!("Expected FROM=TO, got '{}'", promotion))?;
+            let to = Some(to.trim().to_string()).filter(|to| !to.is_empty());
+            Ok((from.trim().to_string(), to))
+        })
+        .collect()
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    let db_path = cli.db.as_path();
+
+    // Checking must not touch the file, everything else works on a migrated pool.
+    if let Command::Check = cli.command {
+        let (report, version) = services::backup::check_integrity(db_path)
+            .await
+            .map_err(|e| e.to_string())?;
+        println!("integrity: {}", report);
+        println!("schema version: {}", version.unwrap_or(0));
+        return if report == "ok" {
+            Ok(())
+        } else {
+            Err("Integrity check failed".to_string())
+        };
+    }
+
+    // Only these may start a new library. Anything else pointed at a
+    // mistyped --db must fail rather than create and use an empty one.
+    let may_create = matches!(cli.command, Command::Migrate | Command::ImportArchive { .. });
+    if !may_create && !db_path.is_file() {
+        return Err(format!("No library database at {}", db_path.display()));
+    }
+    let config = DbConfig {
+        create_if_missing: may_create,
+        ..DbConfig::file(db_path)
+    };
+    let pool = db::open(&config).await.map_err(|e| e.to_string())?;
+
+    match cli.command {
+        Command::Check => unreachable!("handled above"),
+        Command::Migrate => {
+            println!("schema version: {}", db::latest_migration_version());
+        }
+        Command::Backup {
+            destination,
+            encrypt,
+        } => {
+            let metadata = if encrypt {
+                services::backup::create_encrypted_backup(
+                    &pool,
+                    &destination,
+                    db_path.parent().unwrap_or(Path::new(".")),
+                    APP_VERSION,
+                    &passphrase()?,
+                )
+                .await
+            } else {
+                services::backup::create_backup(&pool, &destination, APP_VERSION).await
+            };
+            if let Err(e) =
+                services::backup::record_backup(&pool, "manual", &destination, &metadata).await
+            {
+                // Only the history entry is missing; the backup itself is fine.
+                eprintln!("warning: failed to record the backup in the history: {}", e);
+            }
+            let metadata = metadata.map_err(|e| e.to_string())?;
+            println!(
+                "Backed up {} bytes to {}",
+                metadata.size_bytes,
+                destination.display()
+            );
+        }
+        Command::Restore { backup } => {
+            let passphrase = std::env::var(PASSPHRASE_VAR).ok();
+            let database = Database::new(pool);
+            let pre_restore = services::backup::restore_backup(
+                &database,
+                &backup,
+                db_path,
+                APP_VERSION,
+                passphrase.as_deref(),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+            database.pool().close().await;
+            println!(
+                "Restored {}; previous database kept at {}",
+                backup.display(),
+                pre_restore.display()
+            );
+        }
+        Command::ExportArchive { destination } => {
+            let manifest = services::archive::export_archive(
+                &pool,
+                data_dir(db_path),
+                &destination,
+                APP_VERSION,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+            println!(
+                "Exported {} files to {}",
+                manifest.files.len(),
+                destination.display()
+            );
+        }
+        Command::ImportArchive { archive } => {
+            let database = Database::new(pool);
+            let manifest =
+                services::archive::import_archive(&database, &archive, db_path, APP_VERSION)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            database.pool().close().await;
+            println!(
+                "Imported {} (exported {})",
+                manifest.library_name,
+                manifest.created_at.format("%Y-%m-%d")
+            );
+        }
+        Command::ExportXlsx { destination } => {
+            services::xlsx::export_workbook(&pool, &destination)
+                .await
+                .map_err(|e| e.to_string())?;
+            println!("Exported workbook to {}", destination.display());
+        }
+        Command::ImportRoster {
+            file,
+            student_id_column,
+            name_column,
+            grade_column,
+            phone_column,
+            deactivate_missing,
+            dry_run,
+        } => {
+            let mapping = services::roster::ColumnMapping {
+                student_id: student_id_column,
+                name: name_column,
+                grade: grade_column,
+                phone_number: phone_column,
+            };
+            let diff = if dry_run {
+                services::roster::preview_roster_import(&pool, &file, &mapping, deactivate_missing)
+                    .await
+            } else {
+                services::roster::apply_roster_import(&pool, &file, &mapping, deactivate_missing)
+                    .await
+            };
+            print_json(&diff.map_err(|e| e.to_string())?)?;
+        }
+        Command::Overdue { grade, json } => {
+            let filter = services::statistics::StatsFilter {
+                grade,
+                limit: Some(i64::MAX),
+                ..Default::default()
+            };
+            let overdue = services::statistics::get_overdue_books(&pool, &filter)
+                .await
+                .map_err(|e| e.to_string())?;
+            if json {
+                print_json(&overdue)?;
+            } else {
+                for loan in &overdue {
+                    println!(
+                        "{}\t{}\t{}\t{}\t{} days\t{}",
+                        loan.due_date.format("%Y-%m-%d"),
+                        loan.student_name,
+                        loan.grade,
+                        loan.book_title,
+                        loan.days_overdue,
+                        loan.student_id
+                    );
+                }
+                eprintln!("{} overdue loans", overdue.len());
+            }
+        }
+        Command::Rollover {
+            promotions,
+            dry_run,
+        } => {
+            let progression = parse_progression(&promotions)?;
+            if dry_run {
+                let preview = services::rollover::preview_grade_rollover(&pool, &progression)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                print_json(&preview)?;
+            } else {
+                let result = services::rollover::apply_grade_rollover(&pool, &progression)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                print_json(&result)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,6 +1,10 @@
 use chrono::{DateTime, Utc};
+use tauri::Emitter;
+use crate::db::Database;
 use crate::models;
 use crate::services;
+use crate::services::settings::Secret;
+use sqlx::{Pool, Sqlite};
 
 #[tauri::command]
 pub fn greet(name: &str) -> String {
@@ -20,40 +24,40 @@ pub fn test_command() -> String {
 
 #[tauri::command]
 pub async fn get_all_books(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
 ) -> Result<Vec<models::Book>, String> {
-    services::books::get_all_books(&state)
+    services::books::get_all_books(&state.pool())
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_book_by_id(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
     id: String,
 ) -> Result<Option<models::Book>, String> {
-    services::books::get_book_by_id(&state, &id)
+    services::books::get_book_by_id(&state.pool(), &id)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn create_book(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
     title: String,
     author: String,
     quantity: i32,
     isbn: String,
     category: String,
 ) -> Result<(), String> {
-    services::books::create_book(&state, &title, &author, quantity, &isbn, &category)
+    services::books::create_book(&state.pool(), &title, &author, quantity, &isbn, &category)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn update_book(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
     id: String,
     title: String,
     author: String,
@@ -63,7 +67,7 @@ pub async fn update_book(
     status: models::BookStatus,
 ) -> Result<(), String> {
     services::books::update_book(
-        &state, &id, &title, &author, quantity, &isbn, &category, status,
+        &state.pool(), &id, &title, &author, quantity, &isbn, &category, status,
     )
     .await
     .map_err(|e| e.to_string())
@@ -71,63 +75,78 @@ pub async fn update_book(
 
 #[tauri::command]
 pub async fn delete_book(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
     id: String,
 ) -> Result<(), String> {
-    services::books::delete_book(&state, &id)
+    services::books::delete_book(&state.pool(), &id)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_all_students(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
 ) -> Result<Vec<models::Student>, String> {
-    services::students::get_all_students(&state)
+    services::students::get_all_students(&state.pool())
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_student_by_id(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
     id: String,
 ) -> Result<Option<models::Student>, String> {
-    services::students::get_student_by_id(&state, &id)
+    services::students::get_student_by_id(&state.pool(), &id)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn create_student(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
     name: String,
     grade: String,
     phone_number: Option<String>,
+    email: Option<String>,
+    guardian_email: Option<String>,
     student_id: String,
 ) -> Result<(), String> {
     println!("Creating student with Student ID: {}", student_id);
-    services::students::create_student(&state, &name, &grade, phone_number.as_deref(), &student_id)
-        .await
-        .map_err(|e| e.to_string())
+    services::students::create_student(
+        &state.pool(),
+        &name,
+        &grade,
+        phone_number.as_deref(),
+        email.as_deref(),
+        guardian_email.as_deref(),
+        &student_id,
+    )
+    .await
+    .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn update_student(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
     id: String,
     name: String,
     grade: String,
     phone_number: Option<String>,
+    email: Option<String>,
+    guardian_email: Option<String>,
     student_id: String,
     status: models::StudentStatus,
 ) -> Result<(), String> {
     services::students::update_student(
-        &state,
+        &state.pool(),
         &id,
         &name,
         &grade,
         phone_number.as_deref(),
+        email.as_deref(),
+        guardian_email.as_deref(),
         &student_id,
         status,
     )
@@ -135,73 +154,88 @@ pub async fn update_student(
     .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn set_sms_opt_out(
+    state: tauri::State<'_, Database>,
+    id: String,
+    opt_out: bool,
+) -> Result<(), String> {
+    services::students::set_sms_opt_out(&state.pool(), &id, opt_out)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn delete_student(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
     id: String,
 ) -> Result<(), String> {
-    services::students::delete_student(&state, &id)
+    services::students::delete_student(&state.pool(), &id)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_all_lendings(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
 ) -> Result<Vec<models::LendingWithDetails>, String> {
-    services::lendings::get_all_lendings(&state)
+    services::lendings::get_all_lendings(&state.pool())
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_lending_by_id(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
     id: String,
 ) -> Result<Option<models::LendingWithDetails>, String> {
-    services::lendings::get_lending_by_id(&state, &id)
+    services::lendings::get_lending_by_id(&state.pool(), &id)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_lending_records_by_book_id(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
     id: String,
 ) -> Result<Vec<models::LendingWithDetails>, String> {
-    services::lendings::get_lending_records_by_book_id(&state, &id)
+    services::lendings::get_lending_records_by_book_id(&state.pool(), &id)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_lending_records_by_student_id(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
     id: String,
 ) -> Result<Vec<models::LendingWithDetails>, String> {
-    services::lendings::get_lending_records_by_student_id(&state, &id)
+    services::lendings::get_lending_records_by_student_id(&state.pool(), &id)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn create_lending(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
     book_id: String,
     student_id: String,
-    due_date: String,
+    due_date: Option<String>,
 ) -> Result<(), String> {
-    let due_date = chrono::DateTime::parse_from_rfc3339(&due_date)
-        .map_err(|e| e.to_string())?
-        .with_timezone(&chrono::Utc);
-    services::lendings::create_lending(&state, &book_id, &student_id, due_date)
+    let due_date = due_date
+        .map(|due_date| {
+            chrono::DateTime::parse_from_rfc3339(&due_date)
+                .map(|d| d.with_timezone(&chrono::Utc))
+                .map_err(|e| e.to_string())
+        })
+        .transpose()?;
+    services::lendings::create_lending(&state.pool(), &book_id, &student_id, due_date)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn update_lending(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
     id: String,
     book_id: String,
     student_id: String,
@@ -211,63 +245,428 @@ pub async fn update_lending(
     let due_date = chrono::DateTime::parse_from_rfc3339(&due_date)
         .map_err(|e| e.to_string())?
         .with_timezone(&chrono::Utc);
-    services::lendings::update_lending(&state, &id, &book_id, &student_id, due_date, returned_at)
+    services::lendings::update_lending(&state.pool(), &id, &book_id, &student_id, due_date, returned_at)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn return_lending(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
     id: String,
 ) -> Result<(), String> {
-    services::lendings::return_lending(&state, &id)
+    services::lendings::return_lending(&state.pool(), &id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn renew_lending(
+    state: tauri::State<'_, Database>,
+    id: String,
+) -> Result<DateTime<Utc>, String> {
+    services::lendings::renew_lending(&state.pool(), &id)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn delete_lending(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
     id: String,
 ) -> Result<(), String> {
-    services::lendings::delete_lending(&state, &id)
+    services::lendings::delete_lending(&state.pool(), &id)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_dashboard_stats(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
+    filter: Option<services::statistics::StatsFilter>,
 ) -> Result<services::statistics::DashboardStats, String> {
-    services::statistics::get_dashboard_stats(&state)
+    services::statistics::get_dashboard_stats(&state.pool(), &filter.unwrap_or_default())
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_popular_books(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
+    filter: Option<services::statistics::StatsFilter>,
 ) -> Result<Vec<services::statistics::PopularBook>, String> {
-    services::statistics::get_popular_books(&state)
+    services::statistics::get_popular_books(&state.pool(), &filter.unwrap_or_default())
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_overdue_books(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
+    filter: Option<services::statistics::StatsFilter>,
 ) -> Result<Vec<services::statistics::OverdueBook>, String> {
-    services::statistics::get_overdue_books(&state)
+    services::statistics::get_overdue_books(&state.pool(), &filter.unwrap_or_default())
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_recent_activity(
-    state: tauri::State<'_, sqlx::Pool<sqlx::Sqlite>>,
+    state: tauri::State<'_, Database>,
+    filter: Option<services::statistics::StatsFilter>,
 ) -> Result<Vec<services::statistics::RecentActivity>, String> {
-    services::statistics::get_recent_activity(&state)
+    services::statistics::get_recent_activity(&state.pool(), &filter.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn preview_grade_rollover(
+    state: tauri::State<'_, Database>,
+    progression: services::rollover::GradeProgression,
+) -> Result<services::rollover::RolloverPreview, String> {
+    services::rollover::preview_grade_rollover(&state.pool(), &progression)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn apply_grade_rollover(
+    state: tauri::State<'_, Database>,
+    progression: services::rollover::GradeProgression,
+) -> Result<services::rollover::RolloverResult, String> {
+    services::rollover::apply_grade_rollover(&state.pool(), &progression)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn preview_roster_import(
+    state: tauri::State<'_, Database>,
+    path: String,
+    mapping: services::roster::ColumnMapping,
+    deactivate_missing: bool,
+) -> Result<services::roster::RosterDiff, String> {
+    services::roster::preview_roster_import(
+        &state.pool(),
+        std::path::Path::new(&path),
+        &mapping,
+        deactivate_missing,
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn apply_roster_import(
+    state: tauri::State<'_, Database>,
+    path: String,
+    mapping: services::roster::ColumnMapping,
+    deactivate_missing: bool,
+) -> Result<services::roster::RosterDiff, String> {
+    services::roster::apply_roster_import(
+        &state.pool(),
+        std::path::Path::new(&path),
+        &mapping,
+        deactivate_missing,
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_circulation_series(
+    state: tauri::State<'_, Database>,
+    request: services::trends::SeriesRequest,
+) -> Result<services::trends::CirculationSeries, String> {
+    services::trends::get_circulation_series(&state.pool(), &request)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_reading_profile(
+    state: tauri::State<'_, Database>,
+    id: String,
+    terms: Option<Vec<services::reading::Term>>,
+) -> Result<services::reading::ReadingProfile, String> {
+    services::reading::get_reading_profile(&state.pool(), &id, &terms.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_grade_leaderboard(
+    state: tauri::State<'_, Database>,
+    grade: String,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    limit: Option<i64>,
+) -> Result<Vec<services::reading::LeaderboardEntry>, String> {
+    services::reading::get_grade_leaderboard(&state.pool(), &grade, from, to, limit.unwrap_or(10))
         .await
         .map_err(|e| e.to_string())
-}
\ No newline at end of file
+}
+
+#[tauri::command]
+pub async fn get_collection_analysis(
+    state: tauri::State<'_, Database>,
+    params: Option<services::collection::CollectionParams>,
+) -> Result<services::collection::CollectionAnalysis, String> {
+    services::collection::get_collection_analysis(&state.pool(), &params.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_settings(
+    state: tauri::State<'_, Database>,
+) -> Result<services::settings::Settings, String> {
+    services::settings::get_settings(&state.pool())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_settings(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, Database>,
+    settings: services::settings::Settings,
+) -> Result<services::settings::Settings, String> {
+    let settings = services::settings::update_settings(&state.pool(), &settings)
+        .await
+        .map_err(|e| e.to_string())?;
+    app_handle
+        .emit("settings_changed", &settings)
+        .map_err(|e| e.to_string())?;
+    crate::api::apply_settings(&app_handle, &settings)
+        .await
+        .map_err(|e| format!("Settings saved, but {}", e))?;
+    Ok(settings)
+}
+
+/// Replaces a password or token. There is no command to read one back.
+#[tauri::command]
+pub async fn set_secret(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, Database>,
+    secret: Secret,
+    value: String,
+) -> Result<services::settings::Settings, String> {
+    let pool = state.pool();
+    services::settings::set_secret(&pool, secret, &value)
+        .await
+        .map_err(|e| e.to_string())?;
+    secret_changed(&app_handle, &pool, secret).await
+}
+
+/// Replaces the API token with a random one and returns it, once, to copy
+/// into API clients.
+#[tauri::command]
+pub async fn generate_api_token(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, Database>,
+) -> Result<String, String> {
+    let pool = state.pool();
+    let token = services::settings::generate_api_token(&pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    secret_changed(&app_handle, &pool, Secret::ApiToken).await?;
+    Ok(token)
+}
+
+/// Tells the window the "is set" flags changed and restarts the API server
+/// for a new token.
+async fn secret_changed(
+    app_handle: &tauri::AppHandle,
+    pool: &Pool<Sqlite>,
+    secret: Secret,
+) -> Result<services::settings::Settings, String> {
+    let settings = services::settings::get_settings(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    app_handle
+        .emit("settings_changed", &settings)
+        .map_err(|e| e.to_string())?;
+    if secret == Secret::ApiToken {
+        crate::api::apply_settings(app_handle, &settings)
+            .await
+            .map_err(|e| format!("Token saved, but {}", e))?;
+    }
+    Ok(settings)
+}
+
+#[tauri::command]
+pub async fn get_backup_history(
+    state: tauri::State<'_, Database>,
+    limit: Option<i64>,
+) -> Result<Vec<services::backup::BackupRecord>, String> {
+    services::backup::get_backup_history(&state.pool(), limit.unwrap_or(50))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn is_new_library(state: tauri::State<'_, Database>) -> Result<bool, String> {
+    services::archive::is_new_library(&state.pool())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_calendar(
+    state: tauri::State<'_, Database>,
+) -> Result<services::calendar::Calendar, String> {
+    services::calendar::get_calendar(&state.pool())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_opening_hours(
+    state: tauri::State<'_, Database>,
+    hours: Vec<services::calendar::OpeningHours>,
+) -> Result<(), String> {
+    services::calendar::set_opening_hours(&state.pool(), &hours)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_closure(
+    state: tauri::State<'_, Database>,
+    name: String,
+    start_date: chrono::NaiveDate,
+    end_date: chrono::NaiveDate,
+) -> Result<(), String> {
+    services::calendar::add_closure(&state.pool(), &name, start_date, end_date)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_closure(
+    state: tauri::State<'_, Database>,
+    id: String,
+) -> Result<(), String> {
+    services::calendar::delete_closure(&state.pool(), &id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn import_calendar_ics(
+    state: tauri::State<'_, Database>,
+    path: String,
+) -> Result<usize, String> {
+    services::calendar::import_ics(&state.pool(), std::path::Path::new(&path))
+        .await
+        .map_err(|e| e.to_string())
+}
+#[tauri::command]
+pub async fn send_test_email(
+    state: tauri::State<'_, Database>,
+    to: String,
+) -> Result<(), String> {
+    let pool = state.pool();
+    let settings = services::settings::get_settings(&pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let password = services::settings::get_secret(&pool, Secret::SmtpPassword)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mailer = services::email::Mailer::from_settings(&settings, &password)
+        .map_err(|e| e.to_string())?;
+    mailer
+        .send(
+            &to,
+            &format!("Test email from {}", settings.library_name),
+            "Email reminders are set up correctly.",
+        )
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn send_email_reminders(
+    state: tauri::State<'_, Database>,
+) -> Result<services::notifications::NotificationRun, String> {
+    let pool = state.pool();
+    let settings = services::settings::get_settings(&pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let password = services::settings::get_secret(&pool, Secret::SmtpPassword)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mailer = services::email::Mailer::from_settings(&settings, &password)
+        .map_err(|e| e.to_string())?;
+    services::notifications::send_email_notices(&pool, &mailer)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn send_test_sms(
+    state: tauri::State<'_, Database>,
+    to: String,
+) -> Result<String, String> {
+    use services::sms::SmsGateway;
+
+    let pool = state.pool();
+    let settings = services::settings::get_settings(&pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let number = services::sms::normalize_e164(&to, &settings.sms_country_code)
+        .map_err(|e| e.to_string())?;
+    let authorization = services::settings::get_secret(&pool, Secret::SmsHttpAuthorization)
+        .await
+        .map_err(|e| e.to_string())?;
+    let gateway = services::sms::ConfiguredGateway::from_settings(&settings, &authorization)
+        .map_err(|e| e.to_string())?;
+    gateway
+        .send(
+            &number,
+            &format!("{}: SMS reminders are set up correctly.", settings.library_name),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(number)
+}
+
+#[tauri::command]
+pub async fn send_sms_reminders(
+    state: tauri::State<'_, Database>,
+) -> Result<services::notifications::NotificationRun, String> {
+    let pool = state.pool();
+    let settings = services::settings::get_settings(&pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let authorization = services::settings::get_secret(&pool, Secret::SmsHttpAuthorization)
+        .await
+        .map_err(|e| e.to_string())?;
+    let gateway = services::sms::ConfiguredGateway::from_settings(&settings, &authorization)
+        .map_err(|e| e.to_string())?;
+    services::sms::send_sms_notices(&pool, &gateway)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_daily_digest(
+    state: tauri::State<'_, Database>,
+) -> Result<services::digest::DailyDigest, String> {
+    services::digest::build_digest(&state.pool())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_notification_log(
+    state: tauri::State<'_, Database>,
+    limit: Option<i64>,
+) -> Result<Vec<services::notifications::NotificationRecord>, String> {
+    services::notifications::get_notification_log(&state.pool(), limit.unwrap_or(100))
+        .await
+        .map_err(|e| e.to_string())
+}
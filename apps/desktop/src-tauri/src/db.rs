@@ -1,37 +1,156 @@
-use sqlx::{Pool, Sqlite, SqlitePool};
-use tauri::Manager;
-use std::path::PathBuf;
+use sqlx::migrate::{MigrateError, Migrator};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use sqlx::{Pool, Sqlite};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::Duration;
 
-pub async fn init_db(app_handle: &tauri::AppHandle) -> Result<Pool<Sqlite>, sqlx::Error> {
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .expect("Could not get app data dir");
+/// File name of the SQLite database inside the app data directory.
+pub const DB_FILE_NAME: &str = "library.db";
 
-    println!("Resolved app data dir: {}", app_data_dir.display());
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
 
-    std::fs::create_dir_all(&app_data_dir)
-        .expect("Failed to create app data directory");
+#[derive(Debug, thiserror::Error)]
+pub enum DbError {
+    #[error("Failed to create database directory {path}: {source}")]
+    CreateDir {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Failed to open database: {0}")]
+    Connect(#[from] sqlx::Error),
+    #[error("Failed to migrate database: {0}")]
+    Migrate(#[from] MigrateError),
+}
+
+#[derive(Debug, Clone)]
+pub enum DbLocation {
+    File(PathBuf),
+    /// A private in-memory database, gone when its connection closes. The
+    /// pool never retires that connection, so it lasts as long as the pool.
+    Memory,
+}
+
+/// How to open the library database. Every connection gets WAL journaling
+/// (for files), enforced foreign keys and a busy timeout, so a backup or a
+/// CLI job running next to the app waits instead of failing with "database is
+/// locked".
+#[derive(Debug, Clone)]
+pub struct DbConfig {
+    pub location: DbLocation,
+    pub create_if_missing: bool,
+    pub run_migrations: bool,
+    pub busy_timeout: Duration,
+    pub max_connections: u32,
+}
+
+impl DbConfig {
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        Self {
+            location: DbLocation::File(path.into()),
+            create_if_missing: true,
+            run_migrations: true,
+            busy_timeout: Duration::from_secs(5),
+            max_connections: 5,
+        }
+    }
+
+    /// Each SQLite connection to `:memory:` is its own database, so the pool
+    /// is limited to a single connection.
+    pub fn in_memory() -> Self {
+        Self {
+            location: DbLocation::Memory,
+            max_connections: 1,
+            ..Self::file(PathBuf::new())
+        }
+    }
+
+    fn connect_options(&self) -> SqliteConnectOptions {
+        let options = match &self.location {
+            DbLocation::File(path) => SqliteConnectOptions::new()
+                .filename(path)
+                .create_if_missing(self.create_if_missing)
+                .journal_mode(SqliteJournalMode::Wal),
+            DbLocation::Memory => SqliteConnectOptions::new()
+                .in_memory(true)
+                .journal_mode(SqliteJournalMode::Memory),
+        };
+
+        options
+            .foreign_keys(true)
+            .busy_timeout(self.busy_timeout)
+    }
+}
+
+/// The pool managed by Tauri. Restoring a backup swaps the pool while the app
+/// is running, so commands take a fresh handle with [`Database::pool`] on
+/// every call instead of holding on to one.
+pub struct Database {
+    pool: RwLock<Pool<Sqlite>>,
+}
 
-    let db_path: PathBuf = app_data_dir.join("library.db");
-    println!("Resolved database path: {}", db_path.display());
+impl Database {
+    pub fn new(pool: Pool<Sqlite>) -> Self {
+        Self {
+            pool: RwLock::new(pool),
+        }
+    }
 
-    let db_exists = db_path.exists();
+    pub fn pool(&self) -> Pool<Sqlite> {
+        self.pool
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
 
-    let uri = format!("sqlite:file:{}?mode=rwc", db_path.to_str().unwrap());
+    /// Installs a new pool and hands back the previous one for closing.
+    pub fn replace(&self, pool: Pool<Sqlite>) -> Pool<Sqlite> {
+        let mut current = self
+            .pool
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        std::mem::replace(&mut *current, pool)
+    }
+}
 
-    println!("Connecting with URI: {}", uri);
+/// Latest migration version this build knows about.
+pub fn latest_migration_version() -> i64 {
+    MIGRATOR
+        .iter()
+        .map(|migration| migration.version)
+        .max()
+        .unwrap_or(0)
+}
 
-    let pool = SqlitePool::connect(&uri).await?;
+/// Opens a pool as described by `config`, creating the database's directory
+/// if needed, and applies pending migrations.
+pub async fn open(config: &DbConfig) -> Result<Pool<Sqlite>, DbError> {
+    if let DbLocation::File(path) = &config.location {
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+        if let Some(dir) = dir.filter(|_| config.create_if_missing) {
+            std::fs::create_dir_all(dir).map_err(|source| DbError::CreateDir {
+                path: dir.to_path_buf(),
+                source,
+            })?;
+        }
+    }
 
-    if db_exists {
-        println!("Opened existing database");
-    } else {
-        println!("Created new database");
+    let mut options = SqlitePoolOptions::new().max_connections(config.max_connections);
+    if let DbLocation::Memory = config.location {
+        // Recycling the connection would silently swap in an empty database.
+        options = options.idle_timeout(None).max_lifetime(None);
     }
+    let pool = options.connect_with(config.connect_options()).await?;
 
-    // Run migrations (if any)
-    sqlx::migrate!("./migrations").run(&pool).await?;
+    if config.run_migrations {
+        MIGRATOR.run(&pool).await?;
+    }
 
     Ok(pool)
 }
+
+/// Opens (creating if needed) the database at `db_path` with the default
+/// settings and applies pending migrations.
+pub async fn connect(db_path: &Path) -> Result<Pool<Sqlite>, DbError> {
+    open(&DbConfig::file(db_path)).await
+}
@@ -0,0 +1,270 @@
+//! Student self-service kiosk. While kiosk mode is on, the webview may only
+//! call the commands in [`KIOSK_COMMANDS`]; everything else is rejected in
+//! [`scoped`] before it reaches a handler, so hiding librarian screens in the
+//! UI is not what keeps students out of them. Leaving kiosk mode needs the
+//! PIN set when it was entered, and survives a restart of the app.
+
+use crate::db::Database;
+use crate::services::kiosk::{self as service, KioskLoan, KioskStudent};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+/// Everything the webview can call in kiosk mode.
+const KIOSK_COMMANDS: &[&str] = &[
+    "kiosk_status",
+    "kiosk_login",
+    "kiosk_logout",
+    "kiosk_loans",
+    "kiosk_checkout",
+    "kiosk_return",
+    "kiosk_renew",
+    "exit_kiosk_mode",
+];
+
+/// How often idle sessions are looked for.
+const IDLE_CHECK: Duration = Duration::from_secs(5);
+
+struct Session {
+    student: KioskStudent,
+    timeout: Duration,
+    last_activity: Instant,
+}
+
+#[derive(Default)]
+struct KioskState {
+    active: bool,
+    session: Option<Session>,
+}
+
+/// Kiosk mode and the signed-in student, managed by Tauri.
+pub struct Kiosk {
+    state: Mutex<KioskState>,
+}
+
+impl Kiosk {
+    pub fn new(active: bool) -> Self {
+        Self {
+            state: Mutex::new(KioskState {
+                active,
+                session: None,
+            }),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, KioskState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.lock().active
+    }
+
+    /// Whether the webview may call `command` right now.
+    pub fn allows(&self, command: &str) -> bool {
+        !self.is_active() || KIOSK_COMMANDS.contains(&command)
+    }
+
+    fn set_active(&self, active: bool) {
+        let mut state = self.lock();
+        state.active = active;
+        state.session = None;
+    }
+
+    /// The signed-in student, if the session hasn't timed out. Counts as
+    /// activity and restarts the timeout.
+    fn student(&self) -> Result<KioskStudent, String> {
+        let mut state = self.lock();
+        match state.session.as_mut() {
+            Some(session) if session.last_activity.elapsed() < session.timeout => {
+                session.last_activity = Instant::now();
+                Ok(session.student.clone())
+            }
+            _ => {
+                state.session = None;
+                Err("Your session has ended, please scan your card again".to_string())
+            }
+        }
+    }
+
+    /// Ends the session if it has been idle too long. Returns true if it did.
+    fn expire_idle(&self) -> bool {
+        let mut state = self.lock();
+        let idle = state
+            .session
+            .as_ref()
+            .is_some_and(|session| session.last_activity.elapsed() >= session.timeout);
+        if idle {
+            state.session = None;
+        }
+        idle
+    }
+}
+
+/// Wraps the app's invoke handler so commands outside the kiosk scope are
+/// rejected while kiosk mode is on.
+pub fn scoped<R, F>(handler: F) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static
+where
+    R: Runtime,
+    F: Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+{
+    move |invoke| {
+        let command = invoke.message.command().to_string();
+        let allowed = invoke
+            .message
+            .webview()
+            .try_state::<Kiosk>()
+            .is_some_and(|kiosk| kiosk.allows(&command));
+
+        if allowed {
+            handler(invoke)
+        } else {
+            invoke
+                .resolver
+                .reject(format!("'{}' is not available in kiosk mode", command));
+            true
+        }
+    }
+}
+
+/// Signs idle students out and tells the kiosk screen to go back to the
+/// card prompt.
+pub fn spawn_idle_logout(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(IDLE_CHECK);
+        loop {
+            interval.tick().await;
+            if app_handle.state::<Kiosk>().expire_idle() {
+                let _ = app_handle.emit("kiosk_logged_out", ());
+            }
+        }
+    });
+}
+
+#[derive(Serialize)]
+pub struct KioskStatus {
+    pub active: bool,
+    pub student: Option<KioskStudent>,
+}
+
+#[tauri::command]
+pub fn kiosk_status(kiosk: tauri::State<'_, Kiosk>) -> KioskStatus {
+    kiosk.expire_idle();
+    let state = kiosk.lock();
+    KioskStatus {
+        active: state.active,
+        student: state.session.as_ref().map(|session| session.student.clone()),
+    }
+}
+
+#[tauri::command]
+pub async fn enter_kiosk_mode(
+    app_handle: AppHandle,
+    state: tauri::State<'_, Database>,
+    kiosk: tauri::State<'_, Kiosk>,
+    pin: String,
+) -> Result<(), String> {
+    service::lock(&state.pool(), &pin)
+        .await
+        .map_err(|e| e.to_string())?;
+    kiosk.set_active(true);
+    app_handle
+        .emit("kiosk_mode_changed", true)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn exit_kiosk_mode(
+    app_handle: AppHandle,
+    state: tauri::State<'_, Database>,
+    kiosk: tauri::State<'_, Kiosk>,
+    pin: String,
+) -> Result<(), String> {
+    service::unlock(&state.pool(), &pin)
+        .await
+        .map_err(|e| e.to_string())?;
+    kiosk.set_active(false);
+    app_handle
+        .emit("kiosk_mode_changed", false)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn kiosk_login(
+    state: tauri::State<'_, Database>,
+    kiosk: tauri::State<'_, Kiosk>,
+    card: String,
+) -> Result<KioskStudent, String> {
+    let pool = state.pool();
+    let student = service::find_student_by_card(&pool, &card)
+        .await
+        .map_err(|e| e.to_string())?;
+    let timeout = crate::services::settings::get_settings(&pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .kiosk_timeout_seconds;
+
+    kiosk.lock().session = Some(Session {
+        student: student.clone(),
+        timeout: Duration::from_secs(timeout.max(1) as u64),
+        last_activity: Instant::now(),
+    });
+    Ok(student)
+}
+
+#[tauri::command]
+pub fn kiosk_logout(kiosk: tauri::State<'_, Kiosk>) {
+    kiosk.lock().session = None;
+}
+
+#[tauri::command]
+pub async fn kiosk_loans(
+    state: tauri::State<'_, Database>,
+    kiosk: tauri::State<'_, Kiosk>,
+) -> Result<Vec<KioskLoan>, String> {
+    let student = kiosk.student()?;
+    service::get_student_loans(&state.pool(), &student.id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn kiosk_checkout(
+    state: tauri::State<'_, Database>,
+    kiosk: tauri::State<'_, Kiosk>,
+    code: String,
+) -> Result<(), String> {
+    let student = kiosk.student()?;
+    service::checkout(&state.pool(), &student.id, &code)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn kiosk_return(
+    state: tauri::State<'_, Database>,
+    kiosk: tauri::State<'_, Kiosk>,
+    id: String,
+) -> Result<(), String> {
+    let student = kiosk.student()?;
+    service::return_loan(&state.pool(), &student.id, &id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn kiosk_renew(
+    state: tauri::State<'_, Database>,
+    kiosk: tauri::State<'_, Kiosk>,
+    id: String,
+) -> Result<DateTime<Utc>, String> {
+    let student = kiosk.student()?;
+    service::renew_loan(&state.pool(), &student.id, &id)
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -1,20 +1,38 @@
 use tauri::Manager;
 
-mod db;
+pub mod api;
+pub mod db;
 mod commands;
+pub mod kiosk;
 mod menu;
-mod models;
-mod services;
+pub mod models;
+mod scheduler;
+pub mod services;
 
 
-use menu::{backup_database, export_data, import_data, refresh_app, restore_database};
+use kiosk::{
+    enter_kiosk_mode, exit_kiosk_mode, kiosk_checkout, kiosk_loans, kiosk_login, kiosk_logout,
+    kiosk_renew, kiosk_return, kiosk_status,
+};
+use menu::{
+    backup_database, export_circulation_summary, export_data, export_library_archive,
+    export_overdue_notices, export_student_history, import_data, open_library_archive,
+    refresh_app, restore_database,
+};
 use commands::{
     greet, my_custom_command, test_command, get_all_books, get_book_by_id, create_book,
     update_book, delete_book, get_all_students, get_student_by_id, create_student,
     update_student, delete_student, get_all_lendings, get_lending_by_id,
     get_lending_records_by_book_id, get_lending_records_by_student_id, create_lending,
-    update_lending, return_lending, delete_lending, get_dashboard_stats,
-    get_popular_books, get_overdue_books, get_recent_activity,
+    update_lending, return_lending, renew_lending, delete_lending, get_dashboard_stats,
+    get_popular_books, get_overdue_books, get_recent_activity, preview_grade_rollover,
+    apply_grade_rollover, preview_roster_import, apply_roster_import,
+    get_circulation_series, get_reading_profile, get_grade_leaderboard,
+    get_collection_analysis, get_settings, update_settings, set_secret, generate_api_token,
+    get_backup_history,
+    is_new_library, send_test_email, send_email_reminders, get_notification_log,
+    send_test_sms, send_sms_reminders, set_sms_opt_out, get_daily_digest,
+    get_calendar, set_opening_hours, add_closure, delete_closure, import_calendar_ics,
 };
 
 
@@ -23,19 +41,35 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_notification::init())
         .setup(|app| {
             let app_handle = app.handle().clone();
-            let runtime = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
-            let db_pool = runtime
-                .block_on(db::init_db(&app_handle))
-                .expect("Failed to initialize database");
 
-            app.manage(db_pool);
+            // Errors here abort start-up with a message instead of a panic
+            let db_path = menu::get_db_path(&app_handle)?;
+            println!("Resolved database path: {}", db_path.display());
+            let db_pool = tauri::async_runtime::block_on(db::connect(&db_path))?;
+            let kiosk_locked =
+                tauri::async_runtime::block_on(services::kiosk::is_locked(&db_pool))?;
+
+            app.manage(db::Database::new(db_pool));
             println!("Database initialized successfully");
 
+            app.manage(kiosk::Kiosk::new(kiosk_locked));
+            kiosk::spawn_idle_logout(app_handle.clone());
+
+            app.manage(api::ApiServer::default());
+            let api_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                api::reload(&api_handle).await;
+            });
+
+            scheduler::spawn(app_handle.clone());
+            scheduler::spawn_daily_digest(app_handle);
+
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
+        .invoke_handler(kiosk::scoped(tauri::generate_handler![
             greet,
             my_custom_command,
             test_command,
@@ -56,17 +90,58 @@ pub fn run() {
             create_lending,
             update_lending,
             return_lending,
+            renew_lending,
             delete_lending,
             get_dashboard_stats,
             get_popular_books,
             get_overdue_books,
             get_recent_activity,
+            get_circulation_series,
+            get_reading_profile,
+            get_grade_leaderboard,
+            get_collection_analysis,
+            get_settings,
+            update_settings,
+            set_secret,
+            generate_api_token,
+            get_backup_history,
+            is_new_library,
+            send_test_email,
+            send_email_reminders,
+            get_notification_log,
+            send_test_sms,
+            send_sms_reminders,
+            set_sms_opt_out,
+            get_daily_digest,
+            get_calendar,
+            set_opening_hours,
+            add_closure,
+            delete_closure,
+            import_calendar_ics,
+            preview_grade_rollover,
+            apply_grade_rollover,
+            preview_roster_import,
+            apply_roster_import,
             backup_database,
             restore_database,
+            export_library_archive,
+            open_library_archive,
             export_data,
             import_data,
-            refresh_app
-        ])
+            export_overdue_notices,
+            export_circulation_summary,
+            export_student_history,
+            refresh_app,
+            kiosk_status,
+            enter_kiosk_mode,
+            exit_kiosk_mode,
+            kiosk_login,
+            kiosk_logout,
+            kiosk_loans,
+            kiosk_checkout,
+            kiosk_return,
+            kiosk_renew
+        ]))
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
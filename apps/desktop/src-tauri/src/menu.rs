@@ -1,3 +1,5 @@
+use crate::db::Database;
+use crate::services;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use tauri::{AppHandle, Manager, Emitter};
@@ -14,6 +16,10 @@ pub struct BackupResult {
 pub struct RestoreResult {
     success: bool,
     message: String,
+    /// Set when the chosen backup is encrypted and no passphrase was given.
+    /// The UI asks for one and calls `restore_database` again with `path`.
+    passphrase_required: bool,
+    path: Option<String>,
 }
 
 // Get the current database path
@@ -23,25 +29,31 @@ pub fn get_db_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
         .app_data_dir()
         .map_err(|e| format!("Could not get app data dir: {}", e))?;
 
-    Ok(app_data_dir.join("library.db"))
+    Ok(app_data_dir.join(crate::db::DB_FILE_NAME))
 }
 
 #[tauri::command]
-pub async fn backup_database(app_handle: AppHandle) -> Result<BackupResult, String> {
-    let db_path =
-        get_db_path(&app_handle).map_err(|e| format!("Failed to get database path: {}", e))?;
-
-    if !db_path.exists() {
-        return Ok(BackupResult {
-            success: false,
-            message: "Database file does not exist".to_string(),
-            path: None,
-        });
-    }
+pub async fn backup_database(
+    app_handle: AppHandle,
+    state: tauri::State<'_, Database>,
+    passphrase: Option<String>,
+) -> Result<BackupResult, String> {
+    let passphrase = passphrase.filter(|p| !p.is_empty());
 
     // Generate backup filename with timestamp
     let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
-    let backup_filename = format!("library_backup_{}.db", timestamp);
+    let (backup_filename, filter_name, extension) = match passphrase {
+        Some(_) => (
+            format!("library_backup_{}.db.enc", timestamp),
+            "Encrypted backups",
+            "enc",
+        ),
+        None => (
+            format!("library_backup_{}.db", timestamp),
+            "Database files",
+            "db",
+        ),
+    };
 
     // Use Tauri's file dialog to let user choose backup location
     let backup_path = match app_handle
@@ -49,7 +61,7 @@ pub async fn backup_database(app_handle: AppHandle) -> Result<BackupResult, Stri
         .file()
         .set_title("Save Database Backup")
         .set_file_name(&backup_filename)
-        .add_filter("Database files", &["db"])
+        .add_filter(filter_name, &[extension])
         .blocking_save_file()
     {
         Some(path) => {
@@ -70,8 +82,34 @@ pub async fn backup_database(app_handle: AppHandle) -> Result<BackupResult, Stri
         }
     };
 
-    // Copy the database file
-    match std::fs::copy(&db_path, &backup_path) {
+    // Copy through SQLite so the backup is consistent with the live pool
+    let app_version = app_handle.package_info().version.to_string();
+    let result = match &passphrase {
+        Some(passphrase) => {
+            // The plaintext snapshot is staged beside the live database,
+            // never on the backup's (possibly removable) drive.
+            let staging_dir = app_handle
+                .path()
+                .app_data_dir()
+                .map_err(|e| format!("Could not get app data dir: {}", e))?;
+            services::backup::create_encrypted_backup(
+                &state.pool(),
+                &backup_path,
+                &staging_dir,
+                &app_version,
+                passphrase,
+            )
+            .await
+        }
+        None => services::backup::create_backup(&state.pool(), &backup_path, &app_version).await,
+    };
+    if let Err(e) =
+        services::backup::record_backup(&state.pool(), "manual", &backup_path, &result).await
+    {
+        // Only the history entry is missing; the backup itself is fine.
+        eprintln!("Failed to record the backup in the history: {}", e);
+    }
+    match result {
         Ok(_) => Ok(BackupResult {
             success: true,
             message: format!(
@@ -89,15 +127,24 @@ pub async fn backup_database(app_handle: AppHandle) -> Result<BackupResult, Stri
 }
 
 #[tauri::command]
-pub async fn restore_database(app_handle: AppHandle) -> Result<RestoreResult, String> {
-    // Use file dialog to select backup file
-    let backup_path = match app_handle
-        .dialog()
-        .file()
-        .set_title("Select Database Backup to Restore")
-        .add_filter("Database files", &["db"])
-        .blocking_pick_file()
-    {
+pub async fn restore_database(
+    app_handle: AppHandle,
+    state: tauri::State<'_, Database>,
+    path: Option<String>,
+    passphrase: Option<String>,
+) -> Result<RestoreResult, String> {
+    // Use file dialog to select backup file, unless we're coming back with a
+    // passphrase for a file that was already picked
+    let picked = match path {
+        Some(path) => Some(tauri_plugin_dialog::FilePath::Path(PathBuf::from(path))),
+        None => app_handle
+            .dialog()
+            .file()
+            .set_title("Select Database Backup to Restore")
+            .add_filter("Database backups", &["db", "enc"])
+            .blocking_pick_file(),
+    };
+    let backup_path = match picked {
         Some(path) => {
             // Convert FilePath to PathBuf
             match path {
@@ -111,6 +158,8 @@ pub async fn restore_database(app_handle: AppHandle) -> Result<RestoreResult, St
             return Ok(RestoreResult {
                 success: false,
                 message: "Restore cancelled by user".to_string(),
+                passphrase_required: false,
+                path: None,
             });
         }
     };
@@ -119,38 +168,167 @@ pub async fn restore_database(app_handle: AppHandle) -> Result<RestoreResult, St
         return Ok(RestoreResult {
             success: false,
             message: "Selected backup file does not exist".to_string(),
+            passphrase_required: false,
+            path: None,
         });
     }
 
     let current_db_path =
         get_db_path(&app_handle).map_err(|e| format!("Failed to get database path: {}", e))?;
 
-    // Create backup of current database before restoring
-    if current_db_path.exists() {
-        let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
-        let backup_current =
-            current_db_path.with_file_name(format!("library_pre_restore_{}.db", timestamp));
+    // Validate the backup, keep a copy of the current database and swap the
+    // live pool over to the restored file
+    let app_version = app_handle.package_info().version.to_string();
+    match services::backup::restore_backup(
+        &state,
+        &backup_path,
+        &current_db_path,
+        &app_version,
+        passphrase.as_deref(),
+    )
+    .await
+    {
+        Ok(_) => {
+            crate::api::reload(&app_handle).await;
+            app_handle
+                .emit("refresh_data", ())
+                .map_err(|e| e.to_string())?;
+            Ok(RestoreResult {
+                success: true,
+                message: format!(
+                    "Database restored successfully from {}",
+                    backup_path.display()
+                ),
+                passphrase_required: false,
+                path: None,
+            })
+        }
+        Err(services::backup::BackupError::PassphraseRequired) => Ok(RestoreResult {
+            success: false,
+            message: "This backup is encrypted. Enter its passphrase to restore it.".to_string(),
+            passphrase_required: true,
+            path: Some(backup_path.to_string_lossy().to_string()),
+        }),
+        Err(e) => Ok(RestoreResult {
+            success: false,
+            message: format!("Failed to restore database: {}", e),
+            passphrase_required: false,
+            path: None,
+        }),
+    }
+}
+
+#[tauri::command]
+pub async fn export_library_archive(
+    app_handle: AppHandle,
+    state: tauri::State<'_, Database>,
+) -> Result<BackupResult, String> {
+    let timestamp = chrono::Utc::now().format("%Y%m%d");
+    let filename = format!(
+        "library_{}.{}",
+        timestamp,
+        services::archive::ARCHIVE_EXTENSION
+    );
 
-        if let Err(e) = std::fs::copy(&current_db_path, &backup_current) {
-            return Ok(RestoreResult {
+    let archive_path = match app_handle
+        .dialog()
+        .file()
+        .set_title("Export Library Archive")
+        .set_file_name(&filename)
+        .add_filter("Tiza archives", &[services::archive::ARCHIVE_EXTENSION])
+        .blocking_save_file()
+    {
+        Some(tauri_plugin_dialog::FilePath::Path(pb)) => pb,
+        Some(tauri_plugin_dialog::FilePath::Url(url)) => {
+            url.to_file_path().map_err(|_| "Invalid file path")?
+        }
+        None => {
+            return Ok(BackupResult {
                 success: false,
-                message: format!("Failed to backup current database before restore: {}", e),
+                message: "Export cancelled by user".to_string(),
+                path: None,
             });
         }
-    }
+    };
+
+    let data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Could not get app data dir: {}", e))?;
+    let app_version = app_handle.package_info().version.to_string();
 
-    // Copy the selected backup to current database location
-    match std::fs::copy(&backup_path, &current_db_path) {
-        Ok(_) => Ok(RestoreResult {
+    match services::archive::export_archive(&state.pool(), &data_dir, &archive_path, &app_version)
+        .await
+    {
+        Ok(_) => Ok(BackupResult {
             success: true,
-            message: format!(
-                "Database restored successfully from {}",
-                backup_path.display()
-            ),
+            message: format!("Library exported to {}", archive_path.display()),
+            path: Some(archive_path.to_string_lossy().to_string()),
         }),
+        Err(e) => Ok(BackupResult {
+            success: false,
+            message: format!("Failed to export library: {}", e),
+            path: None,
+        }),
+    }
+}
+
+/// Replaces the current library with a `.tiza` archive, e.g. on the first run
+/// of a new installation.
+#[tauri::command]
+pub async fn open_library_archive(
+    app_handle: AppHandle,
+    state: tauri::State<'_, Database>,
+) -> Result<RestoreResult, String> {
+    let archive_path = match app_handle
+        .dialog()
+        .file()
+        .set_title("Open Library Archive")
+        .add_filter("Tiza archives", &[services::archive::ARCHIVE_EXTENSION])
+        .blocking_pick_file()
+    {
+        Some(tauri_plugin_dialog::FilePath::Path(pb)) => pb,
+        Some(tauri_plugin_dialog::FilePath::Url(url)) => {
+            url.to_file_path().map_err(|_| "Invalid file path")?
+        }
+        None => {
+            return Ok(RestoreResult {
+                success: false,
+                message: "Import cancelled by user".to_string(),
+                passphrase_required: false,
+                path: None,
+            });
+        }
+    };
+
+    let current_db_path =
+        get_db_path(&app_handle).map_err(|e| format!("Failed to get database path: {}", e))?;
+    let app_version = app_handle.package_info().version.to_string();
+
+    match services::archive::import_archive(&state, &archive_path, &current_db_path, &app_version)
+        .await
+    {
+        Ok(manifest) => {
+            crate::api::reload(&app_handle).await;
+            app_handle
+                .emit("refresh_data", ())
+                .map_err(|e| e.to_string())?;
+            Ok(RestoreResult {
+                success: true,
+                message: format!(
+                    "Opened {} (exported {})",
+                    manifest.library_name,
+                    manifest.created_at.format("%Y-%m-%d")
+                ),
+                passphrase_required: false,
+                path: None,
+            })
+        }
         Err(e) => Ok(RestoreResult {
             success: false,
-            message: format!("Failed to restore database: {}", e),
+            message: format!("Failed to open library archive: {}", e),
+            passphrase_required: false,
+            path: None,
         }),
     }
 }
@@ -158,6 +336,7 @@ pub async fn restore_database(app_handle: AppHandle) -> Result<RestoreResult, St
 #[tauri::command]
 pub async fn export_data(
     app_handle: AppHandle,
+    state: tauri::State<'_, Database>,
     export_type: String,
 ) -> Result<BackupResult, String> {
     // This is a placeholder for data export functionality
@@ -167,6 +346,7 @@ pub async fn export_data(
     let filename = match export_type.as_str() {
         "csv" => format!("library_data_{}.csv", timestamp),
         "json" => format!("library_data_{}.json", timestamp),
+        "xlsx" => format!("library_data_{}.xlsx", timestamp),
         _ => format!("library_data_{}.csv", timestamp),
     };
 
@@ -195,6 +375,21 @@ pub async fn export_data(
         }
     };
 
+    if export_type == "xlsx" {
+        return match services::xlsx::export_workbook(&state.pool(), &export_path).await {
+            Ok(_) => Ok(BackupResult {
+                success: true,
+                message: format!("Data exported to {}", export_path.display()),
+                path: Some(export_path.to_string_lossy().to_string()),
+            }),
+            Err(e) => Ok(BackupResult {
+                success: false,
+                message: format!("Failed to export data: {}", e),
+                path: None,
+            }),
+        };
+    }
+
     // TODO: Implement actual data export logic here
     // For now, just create an empty file as placeholder
     match std::fs::write(&export_path, "Export functionality not yet implemented") {
@@ -234,6 +429,8 @@ pub async fn import_data(app_handle: AppHandle) -> Result<RestoreResult, String>
             return Ok(RestoreResult {
                 success: false,
                 message: "Import cancelled by user".to_string(),
+                passphrase_required: false,
+                path: None,
             });
         }
     };
@@ -242,6 +439,8 @@ pub async fn import_data(app_handle: AppHandle) -> Result<RestoreResult, String>
         return Ok(RestoreResult {
             success: false,
             message: "Selected import file does not exist".to_string(),
+            passphrase_required: false,
+            path: None,
         });
     }
 
@@ -254,6 +453,8 @@ pub async fn import_data(app_handle: AppHandle) -> Result<RestoreResult, String>
             "Import functionality not yet implemented for {}",
             import_path.display()
         ),
+        passphrase_required: false,
+        path: None,
     })
 }
 
@@ -263,4 +464,117 @@ pub async fn refresh_app(app_handle: AppHandle) -> Result<(), String> {
         .emit("refresh_data", ())
         .map_err(|e| e.to_string())?;
     Ok(())
-}
\ No newline at end of file
+}
+
+fn pick_pdf_path(app_handle: &AppHandle, title: &str, filename: &str) -> Result<Option<PathBuf>, String> {
+    match app_handle
+        .dialog()
+        .file()
+        .set_title(title)
+        .set_file_name(filename)
+        .add_filter("PDF files", &["pdf"])
+        .blocking_save_file()
+    {
+        Some(tauri_plugin_dialog::FilePath::Path(pb)) => Ok(Some(pb)),
+        Some(tauri_plugin_dialog::FilePath::Url(url)) => Ok(Some(
+            url.to_file_path().map_err(|_| "Invalid file path")?,
+        )),
+        None => Ok(None),
+    }
+}
+
+fn report_result(
+    result: Result<(), services::pdf::ReportError>,
+    path: PathBuf,
+) -> Result<BackupResult, String> {
+    match result {
+        Ok(_) => Ok(BackupResult {
+            success: true,
+            message: format!("Report saved to {}", path.display()),
+            path: Some(path.to_string_lossy().to_string()),
+        }),
+        Err(e) => Ok(BackupResult {
+            success: false,
+            message: format!("Failed to generate report: {}", e),
+            path: None,
+        }),
+    }
+}
+
+fn report_cancelled() -> Result<BackupResult, String> {
+    Ok(BackupResult {
+        success: false,
+        message: "Report cancelled by user".to_string(),
+        path: None,
+    })
+}
+
+#[tauri::command]
+pub async fn export_overdue_notices(
+    app_handle: AppHandle,
+    state: tauri::State<'_, Database>,
+) -> Result<BackupResult, String> {
+    let timestamp = chrono::Utc::now().format("%Y%m%d");
+    let filename = format!("overdue_notices_{}.pdf", timestamp);
+
+    let Some(path) = pick_pdf_path(&app_handle, "Save Overdue Notices", &filename)? else {
+        return report_cancelled();
+    };
+
+    report_result(services::pdf::render_overdue_notices(&state.pool(), &path).await, path)
+}
+
+#[tauri::command]
+pub async fn export_circulation_summary(
+    app_handle: AppHandle,
+    state: tauri::State<'_, Database>,
+    year: i32,
+    month: u32,
+) -> Result<BackupResult, String> {
+    use chrono::TimeZone;
+
+    let start = chrono::Utc
+        .with_ymd_and_hms(year, month, 1, 0, 0, 0)
+        .single()
+        .ok_or_else(|| format!("Invalid month: {}-{}", year, month))?;
+    let end = if month == 12 {
+        chrono::Utc.with_ymd_and_hms(year + 1, 1, 1, 0, 0, 0)
+    } else {
+        chrono::Utc.with_ymd_and_hms(year, month + 1, 1, 0, 0, 0)
+    }
+    .single()
+    .ok_or_else(|| format!("Invalid month: {}-{}", year, month))?;
+
+    let filename = format!("circulation_summary_{}.pdf", start.format("%Y_%m"));
+
+    let Some(path) = pick_pdf_path(&app_handle, "Save Circulation Summary", &filename)? else {
+        return report_cancelled();
+    };
+
+    report_result(
+        services::pdf::render_circulation_summary(&state.pool(), &path, start, end).await,
+        path,
+    )
+}
+
+#[tauri::command]
+pub async fn export_student_history(
+    app_handle: AppHandle,
+    state: tauri::State<'_, Database>,
+    id: String,
+) -> Result<BackupResult, String> {
+    let student = services::students::get_student_by_id(&state.pool(), &id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Student {} not found", id))?;
+    let filename = format!("loan_history_{}.pdf", student.student_id);
+
+    let Some(path) = pick_pdf_path(&app_handle, "Save Loan History", &filename)? else {
+        return report_cancelled();
+    };
+
+    report_result(
+        services::pdf::render_student_history(&state.pool(), &path, &id).await,
+        path,
+    )
+}
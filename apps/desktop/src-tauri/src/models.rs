@@ -26,6 +26,10 @@ pub struct Student {
     pub name: String,
     pub grade: String,
     pub phone_number: Option<String>,
+    pub email: Option<String>,
+    pub guardian_email: Option<String>,
+    /// The student's family asked not to receive SMS reminders.
+    pub sms_opt_out: bool,
     pub student_id: String,
     pub status: StudentStatus,
     pub created_at: DateTime<Utc>,
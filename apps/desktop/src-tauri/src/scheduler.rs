@@ -0,0 +1,208 @@
+use crate::db::Database;
+use crate::kiosk::Kiosk;
+use crate::services;
+use crate::services::settings::{AutoBackup, Secret};
+use chrono::{Duration, Timelike, Utc};
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+/// How often the background task wakes up to see whether work is due.
+const TICK: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// How often the digest task checks whether a new library day has started.
+const DIGEST_TICK: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+/// Gives the window time to start listening before the first digest.
+const DIGEST_STARTUP_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Local hour from which the digest goes out, so a window left open
+/// overnight doesn't get it at midnight.
+const DIGEST_FROM_HOUR: u32 = 6;
+
+/// Starts the background loop. Each tick reloads settings, so changes to the
+/// backup schedule or reminders take effect without restarting the app.
+pub fn spawn(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(TICK);
+        loop {
+            interval.tick().await;
+            if let Err(e) = run_auto_backup(&app_handle).await {
+                eprintln!("Scheduled backup failed: {}", e);
+            }
+            if let Err(e) = run_email_notifications(&app_handle).await {
+                eprintln!("Sending email reminders failed: {}", e);
+            }
+            if let Err(e) = run_sms_notifications(&app_handle).await {
+                eprintln!("Sending SMS reminders failed: {}", e);
+            }
+        }
+    });
+}
+
+fn backup_directory(app_handle: &AppHandle, configured: &str) -> Result<PathBuf, String> {
+    if !configured.trim().is_empty() {
+        return Ok(PathBuf::from(configured.trim()));
+    }
+    app_handle
+        .path()
+        .app_data_dir()
+        .map(|dir| dir.join("backups"))
+        .map_err(|e| format!("Could not get app data dir: {}", e))
+}
+
+async fn run_auto_backup(app_handle: &AppHandle) -> Result<(), String> {
+    let pool = app_handle.state::<Database>().pool();
+    let settings = services::settings::get_settings(&pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Pre-restore copies pile up regardless of the schedule.
+    let data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Could not get app data dir: {}", e))?;
+    services::backup::prune_pre_restore_backups(&data_dir).map_err(|e| e.to_string())?;
+
+    let every = match settings.auto_backup {
+        AutoBackup::Off => return Ok(()),
+        AutoBackup::Daily => Duration::days(1),
+        AutoBackup::Weekly => Duration::days(7),
+    };
+
+    let last = services::backup::last_auto_backup(&pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    if last.is_some_and(|last| Utc::now() - last < every) {
+        return Ok(());
+    }
+
+    let dir = backup_directory(app_handle, &settings.backup_directory)?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let path = services::backup::timestamped_path(
+        &dir,
+        services::backup::AUTO_BACKUP_PREFIX,
+        Utc::now(),
+    );
+    let app_version = app_handle.package_info().version.to_string();
+    let result = services::backup::create_backup(&pool, &path, &app_version).await;
+    if let Err(e) = services::backup::record_backup(&pool, "auto", &path, &result).await {
+        eprintln!("Failed to record the backup in the history: {}", e);
+    }
+    result.map_err(|e| e.to_string())?;
+
+    services::backup::prune_auto_backups(&dir).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Emails due-soon and overdue reminders that haven't gone out yet. The
+/// notification log makes repeated runs safe.
+async fn run_email_notifications(app_handle: &AppHandle) -> Result<(), String> {
+    let pool = app_handle.state::<Database>().pool();
+    let settings = services::settings::get_settings(&pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    if !settings.email_notifications {
+        return Ok(());
+    }
+
+    let password = services::settings::get_secret(&pool, Secret::SmtpPassword)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mailer = services::email::Mailer::from_settings(&settings, &password)
+        .map_err(|e| e.to_string())?;
+    let run = services::notifications::send_email_notices(&pool, &mailer)
+        .await
+        .map_err(|e| e.to_string())?;
+    if run.failed > 0 {
+        return Err(format!("{} of {} reminders failed", run.failed, run.sent + run.failed));
+    }
+
+    Ok(())
+}
+
+/// Texts due-soon and overdue reminders that haven't gone out yet.
+async fn run_sms_notifications(app_handle: &AppHandle) -> Result<(), String> {
+    let pool = app_handle.state::<Database>().pool();
+    let settings = services::settings::get_settings(&pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    if !settings.sms_notifications {
+        return Ok(());
+    }
+
+    let authorization = services::settings::get_secret(&pool, Secret::SmsHttpAuthorization)
+        .await
+        .map_err(|e| e.to_string())?;
+    let gateway = services::sms::ConfiguredGateway::from_settings(&settings, &authorization)
+        .map_err(|e| e.to_string())?;
+    let run = services::sms::send_sms_notices(&pool, &gateway)
+        .await
+        .map_err(|e| e.to_string())?;
+    if run.failed > 0 {
+        return Err(format!("{} of {} reminders failed", run.failed, run.sent + run.failed));
+    }
+
+    Ok(())
+}
+
+/// Shows the daily digest once per library day: shortly after start-up, or
+/// in the morning if the app was left running.
+pub fn spawn_daily_digest(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(DIGEST_STARTUP_DELAY).await;
+        let mut interval = tokio::time::interval(DIGEST_TICK);
+        loop {
+            interval.tick().await;
+            if let Err(e) = run_daily_digest(&app_handle).await {
+                eprintln!("Daily digest failed: {}", e);
+            }
+        }
+    });
+}
+
+async fn run_daily_digest(app_handle: &AppHandle) -> Result<(), String> {
+    // Students at the kiosk shouldn't see it; it goes out once the
+    // librarian is back.
+    if app_handle.state::<Kiosk>().is_active() {
+        return Ok(());
+    }
+
+    let pool = app_handle.state::<Database>().pool();
+    let settings = services::settings::get_settings(&pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    if !settings.daily_digest {
+        return Ok(());
+    }
+
+    let now = Utc::now().with_timezone(&settings.tz());
+    let last = services::digest::last_digest_date(&pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    if now.hour() < DIGEST_FROM_HOUR || last == Some(now.date_naive()) {
+        return Ok(());
+    }
+
+    let digest = services::digest::build_digest(&pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    services::digest::record_digest_date(&pool, digest.date)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    app_handle
+        .emit("daily_digest", &digest)
+        .map_err(|e| e.to_string())?;
+    app_handle
+        .notification()
+        .builder()
+        .title(format!("{}: today's summary", settings.library_name))
+        .body(digest.summary().join("\n"))
+        .show()
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
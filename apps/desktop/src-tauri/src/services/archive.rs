@@ -0,0 +1,262 @@
+use crate::db::Database;
+use crate::services::backup::{self, BackupError};
+use crate::services::settings;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+
+pub const ARCHIVE_EXTENSION: &str = "tiza";
+/// Cover images live in this directory under the app data dir.
+pub const COVERS_DIR: &str = "covers";
+
+const FORMAT_VERSION: u32 = 1;
+const MANIFEST_NAME: &str = "manifest.json";
+const DATABASE_NAME: &str = "library.db";
+const SETTINGS_NAME: &str = "settings.json";
+/// Staging file for the database snapshot while an archive is written or read.
+const STAGING_NAME: &str = "library_archive.tmp.db";
+/// Largest file read from an archive. Sizes in the zip headers come from the
+/// file being imported, so they are checked against this before anything is
+/// allocated.
+const MAX_ENTRY_BYTES: u64 = 512 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("File error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Archive error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Invalid manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+    #[error("{0}")]
+    Backup(#[from] BackupError),
+    #[error("Archive format {0} is not supported by this version of Tiza")]
+    UnsupportedFormat(u32),
+    #[error("Archive is missing {0}")]
+    MissingEntry(String),
+    #[error("Archive contains an unexpected file: {0}")]
+    UnexpectedEntry(String),
+    #[error("Checksum mismatch for {0}, the archive is damaged")]
+    ChecksumMismatch(String),
+    #[error("{0} in the archive is larger than {max} MB", max = MAX_ENTRY_BYTES / (1024 * 1024))]
+    EntryTooLarge(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    pub path: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+}
+
+/// `manifest.json` at the root of a `.tiza` archive. `settings.json` is a
+/// readable copy of the settings; on import the `settings` table inside the
+/// database is what counts.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub format_version: u32,
+    pub app_version: String,
+    pub schema_version: Option<i64>,
+    pub library_name: String,
+    pub created_at: DateTime<Utc>,
+    pub files: Vec<ArchiveEntry>,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Only the database, settings and flat `covers/<file>` entries are accepted,
+/// so nothing in an archive can be extracted outside the covers directory.
+fn cover_file_name(path: &str) -> Option<&str> {
+    path.strip_prefix("covers/")
+        .filter(|name| !name.is_empty() && !name.contains(['/', '\\']))
+        .filter(|name| !matches!(*name, "." | ".."))
+}
+
+fn is_known_entry(path: &str) -> bool {
+    path == DATABASE_NAME || path == SETTINGS_NAME || cover_file_name(path).is_some()
+}
+
+struct ArchiveWriter {
+    zip: zip::ZipWriter<std::fs::File>,
+    files: Vec<ArchiveEntry>,
+}
+
+impl ArchiveWriter {
+    fn add(&mut self, path: &str, data: &[u8]) -> Result<(), ArchiveError> {
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        self.zip.start_file(path, options)?;
+        self.zip.write_all(data)?;
+        self.files.push(ArchiveEntry {
+            path: path.to_string(),
+            size_bytes: data.len() as u64,
+            sha256: sha256_hex(data),
+        });
+        Ok(())
+    }
+}
+
+/// Writes the whole library to a single `.tiza` file: a consistent database
+/// snapshot, the settings, cover images and a manifest with the schema
+/// version and a SHA-256 checksum of every file.
+pub async fn export_archive(
+    pool: &Pool<Sqlite>,
+    data_dir: &Path,
+    destination: &Path,
+    app_version: &str,
+) -> Result<ArchiveManifest, ArchiveError> {
+    let staged = data_dir.join(STAGING_NAME);
+    let snapshot = backup::create_backup(pool, &staged, app_version).await;
+    let database = snapshot
+        .map_err(ArchiveError::from)
+        .and_then(|metadata| Ok((metadata, std::fs::read(&staged)?)));
+    let _ = std::fs::remove_file(&staged);
+    let _ = std::fs::remove_file(backup::metadata_path(&staged));
+    let (metadata, database) = database?;
+
+    let settings = settings::get_settings(pool).await?;
+
+    let mut writer = ArchiveWriter {
+        zip: zip::ZipWriter::new(std::fs::File::create(destination)?),
+        files: Vec::new(),
+    };
+    writer.add(DATABASE_NAME, &database)?;
+    writer.add(SETTINGS_NAME, &serde_json::to_vec_pretty(&settings)?)?;
+
+    let covers_dir = data_dir.join(COVERS_DIR);
+    if covers_dir.is_dir() {
+        for entry in std::fs::read_dir(&covers_dir)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if path.is_file() {
+                writer.add(&format!("{}/{}", COVERS_DIR, name), &std::fs::read(&path)?)?;
+            }
+        }
+    }
+
+    let ArchiveWriter { mut zip, files } = writer;
+    let manifest = ArchiveManifest {
+        format_version: FORMAT_VERSION,
+        app_version: app_version.to_string(),
+        schema_version: metadata.schema_version,
+        library_name: settings.library_name,
+        created_at: metadata.created_at,
+        files,
+    };
+
+    zip.start_file(MANIFEST_NAME, zip::write::SimpleFileOptions::default())?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+    zip.finish()?;
+
+    Ok(manifest)
+}
+
+fn read_entry(
+    archive: &mut zip::ZipArchive<std::fs::File>,
+    name: &str,
+) -> Result<Vec<u8>, ArchiveError> {
+    let file = archive.by_name(name).map_err(|e| match e {
+        zip::result::ZipError::FileNotFound => ArchiveError::MissingEntry(name.to_string()),
+        e => e.into(),
+    })?;
+    if file.size() > MAX_ENTRY_BYTES {
+        return Err(ArchiveError::EntryTooLarge(name.to_string()));
+    }
+
+    // The header may understate the size too, so the read itself is capped.
+    let mut data = Vec::new();
+    file.take(MAX_ENTRY_BYTES + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_ENTRY_BYTES {
+        return Err(ArchiveError::EntryTooLarge(name.to_string()));
+    }
+    Ok(data)
+}
+
+/// Opens an archive and checks every file listed in the manifest against its
+/// checksum. Returns the manifest and the verified file contents.
+pub fn read_archive(
+    path: &Path,
+) -> Result<(ArchiveManifest, HashMap<String, Vec<u8>>), ArchiveError> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?)?;
+    let manifest: ArchiveManifest =
+        serde_json::from_slice(&read_entry(&mut archive, MANIFEST_NAME)?)?;
+
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(ArchiveError::UnsupportedFormat(manifest.format_version));
+    }
+
+    let mut files = HashMap::new();
+    for entry in &manifest.files {
+        if !is_known_entry(&entry.path) {
+            return Err(ArchiveError::UnexpectedEntry(entry.path.clone()));
+        }
+        let data = read_entry(&mut archive, &entry.path)?;
+        if data.len() as u64 != entry.size_bytes || sha256_hex(&data) != entry.sha256 {
+            return Err(ArchiveError::ChecksumMismatch(entry.path.clone()));
+        }
+        files.insert(entry.path.clone(), data);
+    }
+
+    if !files.contains_key(DATABASE_NAME) {
+        return Err(ArchiveError::MissingEntry(DATABASE_NAME.to_string()));
+    }
+
+    Ok((manifest, files))
+}
+
+/// Replaces the live library with the contents of an archive. The database
+/// goes through the same validation, pre-restore copy and pool swap as a
+/// backup restore; cover images are added to the covers directory.
+pub async fn import_archive(
+    database: &Database,
+    archive_path: &Path,
+    db_path: &Path,
+    app_version: &str,
+) -> Result<ArchiveManifest, ArchiveError> {
+    let (manifest, mut files) = read_archive(archive_path)?;
+    let data_dir = db_path.parent().unwrap_or_else(|| Path::new("."));
+
+    let staged = data_dir.join(STAGING_NAME);
+    std::fs::write(&staged, files.remove(DATABASE_NAME).unwrap_or_default())?;
+    let restored = backup::restore_backup(database, &staged, db_path, app_version, None).await;
+    let _ = std::fs::remove_file(&staged);
+    restored?;
+
+    let covers_dir = data_dir.join(COVERS_DIR);
+    for (path, data) in &files {
+        if let Some(name) = cover_file_name(path) {
+            std::fs::create_dir_all(&covers_dir)?;
+            std::fs::write(covers_dir.join(name), data)?;
+        }
+    }
+
+    Ok(manifest)
+}
+
+/// True while the database is as a first run creates it, with no books,
+/// students or loans. The UI uses this to offer opening an archive instead
+/// of starting from scratch.
+pub async fn is_new_library(pool: &Pool<Sqlite>) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM books) +
+            (SELECT COUNT(*) FROM students) +
+            (SELECT COUNT(*) FROM lent) as "count!: i64"
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.count == 0)
+}
@@ -0,0 +1,598 @@
+use crate::db::{self, Database};
+use crate::services::encryption::{self, EncryptionError};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{Connection, Pool, Sqlite, SqliteConnection};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("{0}")]
+    Open(#[from] db::DbError),
+    #[error("File error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to write backup metadata: {0}")]
+    Metadata(#[from] serde_json::Error),
+    #[error("Backup failed integrity check: {0}")]
+    Integrity(String),
+    #[error("Not a Tiza database: {0}")]
+    NotALibrary(String),
+    #[error("Backup was made by a newer version of Tiza (schema {found}, this version supports up to {supported})")]
+    IncompatibleSchema { found: i64, supported: i64 },
+    #[error("This backup is encrypted, a passphrase is required")]
+    PassphraseRequired,
+    #[error("{0}")]
+    Encryption(#[from] EncryptionError),
+    #[error("Background task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+    #[error("Restore failed ({restore}) and the previous database could not be reopened ({rollback}), restart the app")]
+    RollbackFailed { restore: String, rollback: String },
+    #[error("Could not remove the unencrypted copy at {path}, delete it by hand: {source}")]
+    StagingCleanup {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+/// Tables every Tiza database has had since the first migration.
+const REQUIRED_TABLES: [&str; 3] = ["books", "students", "lent"];
+
+/// File name prefixes of the backups Tiza writes on its own. The rest of the
+/// name is a `%Y%m%d_%H%M%S` UTC timestamp.
+pub const AUTO_BACKUP_PREFIX: &str = "library_auto_";
+pub const PRE_RESTORE_PREFIX: &str = "library_pre_restore_";
+const TIMESTAMP_FORMAT: &str = "%Y%m%d_%H%M%S";
+
+/// Plaintext snapshot awaiting encryption, in the staging directory. A fixed
+/// name means a copy left behind by a crash is replaced by the next run.
+const ENCRYPT_STAGING_NAME: &str = "library_encrypt.tmp.db";
+
+/// Scheduled backups kept per period, newest backup of each period wins.
+const KEEP_DAILY: usize = 7;
+const KEEP_WEEKLY: usize = 4;
+const KEEP_MONTHLY: usize = 12;
+
+/// Pre-restore copies are only a safety net for the last few restores.
+const KEEP_PRE_RESTORE: usize = 3;
+
+/// Written next to every backup as `<backup>.json`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupMetadata {
+    pub app_version: String,
+    /// Latest applied migration, `None` for databases created outside sqlx.
+    pub schema_version: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub size_bytes: u64,
+    pub integrity_check: String,
+    #[serde(default)]
+    pub encrypted: bool,
+}
+
+/// One row of `backup_history`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupRecord {
+    pub id: String,
+    /// "manual", "auto" or "pre_restore".
+    pub kind: String,
+    pub path: String,
+    pub size_bytes: Option<i64>,
+    pub success: bool,
+    pub message: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub fn metadata_path(backup_path: &Path) -> PathBuf {
+    let mut name = backup_path.as_os_str().to_owned();
+    name.push(".json");
+    PathBuf::from(name)
+}
+
+/// Latest successfully applied migration in the connected database.
+pub async fn schema_version(conn: &mut SqliteConnection) -> Result<Option<i64>, sqlx::Error> {
+    let has_migrations: bool = sqlx::query_scalar(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations'",
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if !has_migrations {
+        return Ok(None);
+    }
+
+    sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success = 1")
+        .fetch_one(&mut *conn)
+        .await
+}
+
+/// Runs `PRAGMA integrity_check` on a database file without touching the
+/// live pool. Returns the check's report, which is `"ok"` for a sound file.
+pub async fn check_integrity(path: &Path) -> Result<(String, Option<i64>), sqlx::Error> {
+    let options = SqliteConnectOptions::new().filename(path).read_only(true);
+    let mut conn = SqliteConnection::connect_with(&options).await?;
+
+    let report: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(&mut conn)
+        .await?;
+    let version = schema_version(&mut conn).await?;
+
+    conn.close().await?;
+
+    Ok((report.join("; "), version))
+}
+
+/// Copies the live database through SQLite itself with `VACUUM INTO`, so
+/// in-flight writes and WAL content are captured consistently, then verifies
+/// the copy before reporting success. The copy is written next to
+/// `destination` and only renamed over it once it has passed the check, so a
+/// failed backup never costs the previous one at that path.
+pub async fn create_backup(
+    pool: &Pool<Sqlite>,
+    destination: &Path,
+    app_version: &str,
+) -> Result<BackupMetadata, BackupError> {
+    let partial = with_suffix(destination, ".partial");
+    // VACUUM INTO refuses to overwrite an existing file.
+    if partial.exists() {
+        std::fs::remove_file(&partial)?;
+    }
+
+    let verified = vacuum_and_check(pool, &partial).await;
+    let (integrity_check, schema_version) = match verified {
+        Ok(verified) => verified,
+        Err(e) => {
+            if partial.exists() {
+                std::fs::remove_file(&partial)?;
+            }
+            return Err(e);
+        }
+    };
+    std::fs::rename(&partial, destination)?;
+
+    let metadata = BackupMetadata {
+        app_version: app_version.to_string(),
+        schema_version,
+        created_at: Utc::now(),
+        size_bytes: std::fs::metadata(destination)?.len(),
+        integrity_check,
+        encrypted: false,
+    };
+
+    std::fs::write(
+        metadata_path(destination),
+        serde_json::to_string_pretty(&metadata)?,
+    )?;
+
+    Ok(metadata)
+}
+
+async fn vacuum_and_check(
+    pool: &Pool<Sqlite>,
+    path: &Path,
+) -> Result<(String, Option<i64>), BackupError> {
+    sqlx::query("VACUUM INTO ?")
+        .bind(path.to_string_lossy().to_string())
+        .execute(pool)
+        .await?;
+
+    let (integrity_check, schema_version) = check_integrity(path).await?;
+    if integrity_check != "ok" {
+        return Err(BackupError::Integrity(integrity_check));
+    }
+    Ok((integrity_check, schema_version))
+}
+
+/// Appends `suffix` to the file name, e.g. `backup.db` -> `backup.db.partial`.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Like [`create_backup`], but the file written to `destination` is the
+/// verified snapshot encrypted with `passphrase`. The plaintext snapshot is
+/// staged in `staging_dir`, which should be the live database's directory,
+/// never the destination's volume, and is removed before this returns. If it
+/// can't be removed, that is reported as the error.
+pub async fn create_encrypted_backup(
+    pool: &Pool<Sqlite>,
+    destination: &Path,
+    staging_dir: &Path,
+    app_version: &str,
+    passphrase: &str,
+) -> Result<BackupMetadata, BackupError> {
+    let plain = staging_dir.join(ENCRYPT_STAGING_NAME);
+    let snapshot = create_backup(pool, &plain, app_version).await;
+
+    let partial = with_suffix(destination, ".partial");
+    let encrypted = match snapshot {
+        Ok(metadata) => encrypt_file(&plain, &partial, destination, passphrase)
+            .await
+            .map(|()| metadata),
+        Err(e) => Err(e),
+    };
+
+    remove_staged(&plain)?;
+    if encrypted.is_err() && partial.exists() {
+        // Only ciphertext, but it isn't a usable backup either.
+        std::fs::remove_file(&partial)?;
+    }
+
+    let metadata = encrypted?;
+    let metadata = BackupMetadata {
+        size_bytes: std::fs::metadata(destination)?.len(),
+        encrypted: true,
+        ..metadata
+    };
+
+    std::fs::write(
+        metadata_path(destination),
+        serde_json::to_string_pretty(&metadata)?,
+    )?;
+
+    Ok(metadata)
+}
+
+/// Deletes a staged plaintext copy and its metadata, if present.
+fn remove_staged(path: &Path) -> Result<(), BackupError> {
+    for path in [path.to_path_buf(), metadata_path(path)] {
+        match std::fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(source) => return Err(BackupError::StagingCleanup { path, source }),
+        }
+    }
+    Ok(())
+}
+
+/// Encrypts `plain` with `passphrase` into `partial`, then moves it over
+/// `destination`. Key derivation is deliberately slow, so it runs on a
+/// blocking thread.
+async fn encrypt_file(
+    plain: &Path,
+    partial: &Path,
+    destination: &Path,
+    passphrase: &str,
+) -> Result<(), BackupError> {
+    let data = std::fs::read(plain)?;
+    let passphrase = passphrase.to_string();
+    let ciphertext =
+        tokio::task::spawn_blocking(move || encryption::encrypt(&data, &passphrase)).await??;
+    std::fs::write(partial, ciphertext)?;
+    std::fs::rename(partial, destination)?;
+    Ok(())
+}
+
+/// Checks that `path` is a sound SQLite file holding a Tiza library this build
+/// can migrate, and returns its schema version.
+pub async fn validate_backup(path: &Path) -> Result<Option<i64>, BackupError> {
+    let options = SqliteConnectOptions::new().filename(path).read_only(true);
+    let mut conn = SqliteConnection::connect_with(&options)
+        .await
+        .map_err(|e| BackupError::NotALibrary(e.to_string()))?;
+
+    let tables: Vec<String> =
+        sqlx::query_scalar("SELECT name FROM sqlite_master WHERE type = 'table'")
+            .fetch_all(&mut conn)
+            .await
+            .map_err(|e| BackupError::NotALibrary(e.to_string()))?;
+
+    let missing: Vec<&str> = REQUIRED_TABLES
+        .into_iter()
+        .filter(|table| !tables.iter().any(|t| t == table))
+        .collect();
+    if !missing.is_empty() {
+        return Err(BackupError::NotALibrary(format!(
+            "missing tables: {}",
+            missing.join(", ")
+        )));
+    }
+
+    let version = schema_version(&mut conn).await?;
+    conn.close().await?;
+
+    let supported = db::latest_migration_version();
+    if let Some(found) = version.filter(|found| *found > supported) {
+        return Err(BackupError::IncompatibleSchema { found, supported });
+    }
+
+    let (integrity_check, _) = check_integrity(path).await?;
+    if integrity_check != "ok" {
+        return Err(BackupError::Integrity(integrity_check));
+    }
+
+    Ok(version)
+}
+
+/// Replaces the live database with `backup_path`. The current database is
+/// first saved next to it as `library_pre_restore_<timestamp>.db`, then the
+/// managed pool is closed, the file swapped and a new pool opened, which also
+/// brings the restored database up to the current schema. If the swap fails
+/// after the pool was closed, the previous database is put back, so
+/// `database` is never left without a working pool.
+///
+/// Encrypted backups are decrypted to a temporary file next to the database
+/// first, and need `passphrase`.
+pub async fn restore_backup(
+    database: &Database,
+    backup_path: &Path,
+    db_path: &Path,
+    app_version: &str,
+    passphrase: Option<&str>,
+) -> Result<PathBuf, BackupError> {
+    if !encryption::is_encrypted_file(backup_path)? {
+        return restore_plain_backup(database, backup_path, db_path, app_version).await;
+    }
+
+    let passphrase = passphrase.ok_or(BackupError::PassphraseRequired)?.to_string();
+    let data = std::fs::read(backup_path)?;
+    // Key derivation is deliberately slow; keep it off the async runtime.
+    let data =
+        tokio::task::spawn_blocking(move || encryption::decrypt(&data, &passphrase)).await??;
+
+    let decrypted = with_suffix(db_path, ".restore");
+    std::fs::write(&decrypted, data)?;
+    let result = restore_plain_backup(database, &decrypted, db_path, app_version).await;
+    let _ = std::fs::remove_file(&decrypted);
+
+    result
+}
+
+async fn restore_plain_backup(
+    database: &Database,
+    backup_path: &Path,
+    db_path: &Path,
+    app_version: &str,
+) -> Result<PathBuf, BackupError> {
+    validate_backup(backup_path).await?;
+
+    let data_dir = db_path.parent().unwrap_or_else(|| Path::new("."));
+    let pre_restore = timestamped_path(data_dir, PRE_RESTORE_PREFIX, Utc::now());
+    let pre_restore_metadata = create_backup(&database.pool(), &pre_restore, app_version).await?;
+
+    database.pool().close().await;
+
+    let pool = match swap_in(backup_path, db_path).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            // Put the previous database back so the app keeps working.
+            let previous = match swap_in(&pre_restore, db_path).await {
+                Ok(pool) => pool,
+                Err(_) => db::connect(&pre_restore).await.map_err(|rollback| {
+                    BackupError::RollbackFailed {
+                        restore: e.to_string(),
+                        rollback: rollback.to_string(),
+                    }
+                })?,
+            };
+            database.replace(previous);
+            return Err(e);
+        }
+    };
+    database.replace(pool);
+
+    // The restore has happened; history and pruning failing must not report
+    // it as failed. History lives in the database, so the entry goes into
+    // the restored one.
+    let recorded = Ok(pre_restore_metadata);
+    if let Err(e) = record_backup(&database.pool(), "pre_restore", &pre_restore, &recorded).await {
+        eprintln!("Failed to record the pre-restore backup: {}", e);
+    }
+    if let Err(e) = prune_pre_restore_backups(data_dir) {
+        eprintln!("Failed to prune pre-restore backups: {}", e);
+    }
+    Ok(pre_restore)
+}
+
+/// Copies `source` over the closed database at `db_path` and opens it.
+async fn swap_in(source: &Path, db_path: &Path) -> Result<Pool<Sqlite>, BackupError> {
+    // Stale WAL/shared-memory files belong to the old database.
+    for suffix in ["-wal", "-shm"] {
+        let mut sidecar = db_path.as_os_str().to_owned();
+        sidecar.push(suffix);
+        let sidecar = PathBuf::from(sidecar);
+        if sidecar.exists() {
+            std::fs::remove_file(sidecar)?;
+        }
+    }
+
+    std::fs::copy(source, db_path)?;
+    Ok(db::connect(db_path).await?)
+}
+
+pub fn timestamped_path(dir: &Path, prefix: &str, at: DateTime<Utc>) -> PathBuf {
+    dir.join(format!("{}{}.db", prefix, at.format(TIMESTAMP_FORMAT)))
+}
+
+fn parse_timestamped_name(path: &Path, prefix: &str) -> Option<DateTime<Utc>> {
+    let stem = path.file_name()?.to_str()?.strip_prefix(prefix)?.strip_suffix(".db")?;
+    NaiveDateTime::parse_from_str(stem, TIMESTAMP_FORMAT)
+        .ok()
+        .map(|at| at.and_utc())
+}
+
+/// Backups in `dir` whose name is `<prefix><timestamp>.db`, newest first.
+fn list_timestamped(
+    dir: &Path,
+    prefix: &str,
+) -> Result<Vec<(PathBuf, DateTime<Utc>)>, std::io::Error> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups: Vec<(PathBuf, DateTime<Utc>)> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter_map(|path| parse_timestamped_name(&path, prefix).map(|at| (path, at)))
+        .collect();
+    backups.sort_by(|a, b| b.1.cmp(&a.1));
+
+    Ok(backups)
+}
+
+fn remove_with_metadata(path: &Path) -> Result<(), std::io::Error> {
+    std::fs::remove_file(path)?;
+    let sidecar = metadata_path(path);
+    if sidecar.exists() {
+        std::fs::remove_file(sidecar)?;
+    }
+    Ok(())
+}
+
+/// Applies the retention policy to `backups` (newest first): the newest backup
+/// of each of the last 7 days, 4 ISO weeks and 12 months that have backups is
+/// kept. Returns the paths that fall outside all three.
+pub fn expired_backups(backups: &[(PathBuf, DateTime<Utc>)]) -> Vec<PathBuf> {
+    fn keep_newest_per_period<K: Eq + std::hash::Hash>(
+        backups: &[(PathBuf, DateTime<Utc>)],
+        limit: usize,
+        period: impl Fn(NaiveDate) -> K,
+        keep: &mut HashSet<PathBuf>,
+    ) {
+        let mut seen = HashSet::new();
+        for (path, at) in backups {
+            if seen.len() == limit {
+                break;
+            }
+            if seen.insert(period(at.date_naive())) {
+                keep.insert(path.clone());
+            }
+        }
+    }
+
+    let mut keep = HashSet::new();
+    keep_newest_per_period(backups, KEEP_DAILY, |d| d, &mut keep);
+    keep_newest_per_period(
+        backups,
+        KEEP_WEEKLY,
+        |d| (d.iso_week().year(), d.iso_week().week()),
+        &mut keep,
+    );
+    keep_newest_per_period(backups, KEEP_MONTHLY, |d| (d.year(), d.month()), &mut keep);
+
+    backups
+        .iter()
+        .filter(|(path, _)| !keep.contains(path))
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
+/// Deletes scheduled backups in `dir` that the retention policy no longer
+/// keeps, together with their metadata files.
+pub fn prune_auto_backups(dir: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
+    let expired = expired_backups(&list_timestamped(dir, AUTO_BACKUP_PREFIX)?);
+    for path in &expired {
+        remove_with_metadata(path)?;
+    }
+    Ok(expired)
+}
+
+/// Deletes all but the newest few `library_pre_restore_*.db` files that
+/// [`restore_backup`] leaves next to the live database.
+pub fn prune_pre_restore_backups(dir: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
+    let expired: Vec<PathBuf> = list_timestamped(dir, PRE_RESTORE_PREFIX)?
+        .into_iter()
+        .skip(KEEP_PRE_RESTORE)
+        .map(|(path, _)| path)
+        .collect();
+    for path in &expired {
+        remove_with_metadata(path)?;
+    }
+    Ok(expired)
+}
+
+pub async fn record_backup(
+    pool: &Pool<Sqlite>,
+    kind: &str,
+    path: &Path,
+    result: &Result<BackupMetadata, BackupError>,
+) -> Result<(), sqlx::Error> {
+    let id = Uuid::new_v4().to_string();
+    let path = path.to_string_lossy().to_string();
+    let (success, size_bytes, message) = match result {
+        Ok(metadata) => (true, Some(metadata.size_bytes as i64), None),
+        Err(e) => (false, None, Some(e.to_string())),
+    };
+    let created_at = Utc::now();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO backup_history (id, kind, path, size_bytes, success, message, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        "#,
+        id,
+        kind,
+        path,
+        size_bytes,
+        success,
+        message,
+        created_at
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_backup_history(
+    pool: &Pool<Sqlite>,
+    limit: i64,
+) -> Result<Vec<BackupRecord>, sqlx::Error> {
+    sqlx::query_as!(
+        BackupRecord,
+        r#"
+        SELECT
+            id as "id!: String",
+            kind as "kind!: String",
+            path as "path!: String",
+            size_bytes as "size_bytes?: i64",
+            success as "success!: bool",
+            message as "message?: String",
+            created_at as "created_at!: DateTime<Utc>"
+        FROM backup_history
+        ORDER BY created_at DESC
+        LIMIT ?
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// When the last scheduled backup succeeded, if ever.
+pub async fn last_auto_backup(pool: &Pool<Sqlite>) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT created_at as "created_at!: DateTime<Utc>"
+        FROM backup_history
+        WHERE kind = 'auto' AND success = 1
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| row.created_at))
+}
+
+/// When the last manual or scheduled backup succeeded, if ever.
+pub async fn last_backup(pool: &Pool<Sqlite>) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT created_at as "created_at!: DateTime<Utc>"
+        FROM backup_history
+        WHERE kind IN ('manual', 'auto') AND success = 1
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| row.created_at))
+}
@@ -109,10 +109,25 @@ pub async fn update_book(
     Ok(())
 }
 
-pub async fn delete_book(pool: &Pool<Sqlite>, id: &str) -> Result<(), sqlx::Error> {
-    sqlx::query!(r#"DELETE FROM books WHERE id = ?"#, id)
-        .execute(pool)
-        .await?;
+#[derive(Debug, thiserror::Error)]
+pub enum DeleteBookError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("This book has loans on record and can't be deleted")]
+    HasLoans,
+}
 
-    Ok(())
+/// Deletes a book that has never been lent. Loans keep their book, so a book
+/// with loan history is refused rather than leaving the loans dangling.
+pub async fn delete_book(pool: &Pool<Sqlite>, id: &str) -> Result<(), DeleteBookError> {
+    match sqlx::query!(r#"DELETE FROM books WHERE id = ?"#, id)
+        .execute(pool)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(sqlx::Error::Database(e)) if e.is_foreign_key_violation() => {
+            Err(DeleteBookError::HasLoans)
+        }
+        Err(e) => Err(e.into()),
+    }
 }
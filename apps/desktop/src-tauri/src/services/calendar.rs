@@ -0,0 +1,416 @@
+use crate::services::settings;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::path::Path;
+use uuid::Uuid;
+
+/// Longest run of closed days we will walk through looking for an open one.
+/// Anything longer means the calendar is misconfigured (e.g. every weekday
+/// closed), and we fall back to the original date rather than loop forever.
+const MAX_CLOSED_RUN: i64 = 400;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CalendarError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Failed to read calendar file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid calendar file: {0}")]
+    InvalidIcs(String),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpeningHours {
+    /// 0 = Monday ... 6 = Sunday.
+    pub weekday: i64,
+    pub is_open: bool,
+    pub opens_at: Option<String>,
+    pub closes_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Closure {
+    pub id: String,
+    pub name: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub source: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Calendar {
+    pub hours: Vec<OpeningHours>,
+    pub closures: Vec<Closure>,
+}
+
+/// Open/closed lookup used when computing due dates and overdue days.
+#[derive(Debug, Clone)]
+pub struct LibraryCalendar {
+    open_weekdays: [bool; 7],
+    closing_times: [Option<NaiveTime>; 7],
+    closures: Vec<(NaiveDate, NaiveDate)>,
+}
+
+impl LibraryCalendar {
+    pub async fn load(pool: &Pool<Sqlite>) -> Result<Self, sqlx::Error> {
+        let calendar = get_calendar(pool).await?;
+
+        let mut open_weekdays = [true; 7];
+        let mut closing_times = [None; 7];
+        for hours in &calendar.hours {
+            if let Some(open) = open_weekdays.get_mut(hours.weekday as usize) {
+                *open = hours.is_open;
+            }
+            if let Some(closes_at) = closing_times.get_mut(hours.weekday as usize) {
+                *closes_at = hours
+                    .closes_at
+                    .as_deref()
+                    .and_then(|v| NaiveTime::parse_from_str(v, "%H:%M").ok());
+            }
+        }
+
+        Ok(Self {
+            open_weekdays,
+            closing_times,
+            closures: calendar
+                .closures
+                .iter()
+                .map(|c| (c.start_date, c.end_date))
+                .collect(),
+        })
+    }
+
+    pub fn is_open(&self, date: NaiveDate) -> bool {
+        self.open_weekdays[date.weekday().num_days_from_monday() as usize]
+            && !self
+                .closures
+                .iter()
+                .any(|(start, end)| *start <= date && date <= *end)
+    }
+
+    /// When the library closes on `date`, if its opening hours say.
+    pub fn closing_time(&self, date: NaiveDate) -> Option<NaiveTime> {
+        self.closing_times[date.weekday().num_days_from_monday() as usize]
+    }
+
+    /// The given date if the library is open then, otherwise the next open day.
+    pub fn next_open_day(&self, date: NaiveDate) -> NaiveDate {
+        (0..MAX_CLOSED_RUN)
+            .map(|offset| date + Duration::days(offset))
+            .find(|day| self.is_open(*day))
+            .unwrap_or(date)
+    }
+
+    /// Open days after `from` up to and including `to`.
+    pub fn open_days_between(&self, from: NaiveDate, to: NaiveDate) -> i64 {
+        let mut count = 0;
+        let mut day = from + Duration::days(1);
+        while day <= to {
+            if self.is_open(day) {
+                count += 1;
+            }
+            day += Duration::days(1);
+        }
+        count
+    }
+}
+
+pub async fn get_calendar(pool: &Pool<Sqlite>) -> Result<Calendar, sqlx::Error> {
+    let hours = sqlx::query_as!(
+        OpeningHours,
+        r#"
+        SELECT
+            weekday as "weekday!: i64",
+            is_open as "is_open!: bool",
+            opens_at as "opens_at?: String",
+            closes_at as "closes_at?: String"
+        FROM calendar_hours
+        ORDER BY weekday
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let closures = sqlx::query_as!(
+        Closure,
+        r#"
+        SELECT
+            COALESCE(id, '') as "id!: String",
+            COALESCE(name, '') as "name!: String",
+            start_date as "start_date!: chrono::NaiveDate",
+            end_date as "end_date!: chrono::NaiveDate",
+            COALESCE(source, '') as "source!: String"
+        FROM calendar
+        ORDER BY start_date
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Calendar { hours, closures })
+}
+
+fn parse_time(value: &Option<String>, weekday: i64) -> Result<Option<NaiveTime>, CalendarError> {
+    value
+        .as_deref()
+        .map(|v| {
+            NaiveTime::parse_from_str(v, "%H:%M").map_err(|_| {
+                CalendarError::Invalid(format!("Invalid time '{}' for weekday {}", v, weekday))
+            })
+        })
+        .transpose()
+}
+
+pub async fn set_opening_hours(
+    pool: &Pool<Sqlite>,
+    hours: &[OpeningHours],
+) -> Result<(), CalendarError> {
+    for day in hours {
+        if !(0..=6).contains(&day.weekday) {
+            return Err(CalendarError::Invalid(format!(
+                "Invalid weekday: {}",
+                day.weekday
+            )));
+        }
+        let opens_at = parse_time(&day.opens_at, day.weekday)?;
+        let closes_at = parse_time(&day.closes_at, day.weekday)?;
+        if let (Some(opens_at), Some(closes_at)) = (opens_at, closes_at) {
+            if closes_at <= opens_at {
+                return Err(CalendarError::Invalid(format!(
+                    "Closing time must be after opening time for weekday {}",
+                    day.weekday
+                )));
+            }
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+
+    for day in hours {
+        sqlx::query!(
+            r#"
+            INSERT INTO calendar_hours (weekday, is_open, opens_at, closes_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(weekday) DO UPDATE SET
+                is_open = excluded.is_open,
+                opens_at = excluded.opens_at,
+                closes_at = excluded.closes_at
+            "#,
+            day.weekday,
+            day.is_open,
+            day.opens_at,
+            day.closes_at
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn add_closure(
+    pool: &Pool<Sqlite>,
+    name: &str,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> Result<(), CalendarError> {
+    if end_date < start_date {
+        return Err(CalendarError::Invalid(
+            "Closure must end on or after its start date".to_string(),
+        ));
+    }
+
+    let id = Uuid::new_v4().to_string();
+    let created_at = Utc::now();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO calendar (id, name, start_date, end_date, source, created_at)
+        VALUES (?, ?, ?, ?, ?, ?)
+        "#,
+        id,
+        name,
+        start_date,
+        end_date,
+        "manual",
+        created_at
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_closure(pool: &Pool<Sqlite>, id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(r#"DELETE FROM calendar WHERE id = ?"#, id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+struct IcsEvent {
+    name: String,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+}
+
+/// DTSTART/DTEND values carry whether they were plain dates.
+#[derive(Default)]
+struct PendingEvent {
+    summary: Option<String>,
+    start: Option<(NaiveDate, bool)>,
+    end: Option<(NaiveDate, bool)>,
+}
+
+fn parse_ics_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()
+}
+
+/// The library-local date of a DTSTART/DTEND date-time. The value is read in
+/// its own zone: UTC with a trailing `Z`, the `TZID` parameter's zone, or the
+/// library's when it has neither (or an unknown TZID).
+fn parse_ics_datetime(value: &str, params: &str, tz: Tz) -> Option<NaiveDate> {
+    let (stamp, is_utc) = match value.strip_suffix('Z') {
+        Some(stamp) => (stamp, true),
+        None => (value, false),
+    };
+    let local = NaiveDateTime::parse_from_str(stamp, "%Y%m%dT%H%M%S").ok()?;
+    let at = if is_utc {
+        Utc.from_utc_datetime(&local)
+    } else {
+        let zone = params
+            .split(';')
+            .find_map(|param| param.strip_prefix("TZID="))
+            .and_then(|tzid| tzid.trim_matches('"').parse::<Tz>().ok())
+            .unwrap_or(tz);
+        zone.from_local_datetime(&local)
+            .earliest()
+            // The wall-clock time was skipped by a DST jump; an hour later exists.
+            .or_else(|| zone.from_local_datetime(&(local + Duration::hours(1))).earliest())?
+            .with_timezone(&Utc)
+    };
+    Some(at.with_timezone(&tz).date_naive())
+}
+
+/// Reads the VEVENTs of an iCalendar file as closures in the library's
+/// timezone `tz`. Only the dates are used: all-day events end the day before
+/// DTEND (which is exclusive), timed events close the library for every
+/// local day they touch. Recurrence rules are not expanded.
+fn parse_ics(content: &str, tz: Tz) -> Result<Vec<IcsEvent>, CalendarError> {
+    // Long lines are folded onto continuation lines starting with whitespace.
+    let mut lines: Vec<String> = Vec::new();
+    for raw in content.lines() {
+        match raw.strip_prefix(' ').or_else(|| raw.strip_prefix('\t')) {
+            Some(rest) if !lines.is_empty() => lines.last_mut().unwrap().push_str(rest),
+            _ => lines.push(raw.to_string()),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut current: Option<PendingEvent> = None;
+
+    for line in &lines {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, params) = key.split_once(';').unwrap_or((key, ""));
+        let is_date_only =
+            params.split(';').any(|param| param == "VALUE=DATE") || value.trim().len() == 8;
+
+        match (name, value.trim()) {
+            ("BEGIN", "VEVENT") => current = Some(PendingEvent::default()),
+            ("END", "VEVENT") => {
+                let Some(event) = current.take() else {
+                    continue;
+                };
+                let (start_date, _) = event.start.ok_or_else(|| {
+                    CalendarError::InvalidIcs("event without DTSTART".to_string())
+                })?;
+                let end_date = match event.end {
+                    Some((end, true)) if end > start_date => end - Duration::days(1),
+                    Some((end, false)) if end > start_date => end,
+                    _ => start_date,
+                };
+                events.push(IcsEvent {
+                    name: event.summary.unwrap_or_else(|| "Closed".to_string()),
+                    start_date,
+                    end_date,
+                });
+            }
+            ("SUMMARY", value) => {
+                if let Some(event) = current.as_mut() {
+                    event.summary = Some(value.replace("\\,", ",").replace("\\;", ";"));
+                }
+            }
+            ("DTSTART", value) | ("DTEND", value) => {
+                let Some(event) = current.as_mut() else {
+                    continue;
+                };
+                let date = if is_date_only {
+                    parse_ics_date(value)
+                } else {
+                    parse_ics_datetime(value, params, tz)
+                }
+                .ok_or_else(|| CalendarError::InvalidIcs(format!("invalid date '{}'", value)))?;
+                if name == "DTSTART" {
+                    event.start = Some((date, is_date_only));
+                } else {
+                    event.end = Some((date, is_date_only));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(events)
+}
+
+/// Imports closures from an .ics file. Re-importing the same file replaces
+/// the closures it created last time.
+pub async fn import_ics(pool: &Pool<Sqlite>, path: &Path) -> Result<usize, CalendarError> {
+    let content = std::fs::read_to_string(path)?;
+    let tz = settings::get_settings(pool).await?.tz();
+    let events = parse_ics(&content, tz)?;
+
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let source = format!("ics:{}", file_name);
+    let created_at = Utc::now();
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(r#"DELETE FROM calendar WHERE source = ?"#, source)
+        .execute(&mut *tx)
+        .await?;
+
+    for event in &events {
+        let id = Uuid::new_v4().to_string();
+        sqlx::query!(
+            r#"
+            INSERT INTO calendar (id, name, start_date, end_date, source, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+            id,
+            event.name,
+            event.start_date,
+            event.end_date,
+            source,
+            created_at
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(events.len())
+}
@@ -0,0 +1,88 @@
+use crate::services::calendar::LibraryCalendar;
+use crate::services::settings;
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use sqlx::{Pool, Sqlite};
+
+/// Converts between stored UTC instants and the library's local calendar.
+/// Due dates and overdue counts are reasoned about in local days, so a book
+/// due "Friday" is due by closing time on Friday wherever the library is, and
+/// days the library is closed neither receive due dates nor count as overdue.
+#[derive(Debug, Clone)]
+pub struct LibraryClock {
+    tz: Tz,
+    calendar: LibraryCalendar,
+}
+
+impl LibraryClock {
+    pub fn new(tz: Tz, calendar: LibraryCalendar) -> Self {
+        Self { tz, calendar }
+    }
+
+    pub async fn load(pool: &Pool<Sqlite>) -> Result<Self, sqlx::Error> {
+        let settings = settings::get_settings(pool).await?;
+        let calendar = LibraryCalendar::load(pool).await?;
+        Ok(Self::new(settings.tz(), calendar))
+    }
+
+    pub fn local_date(&self, at: DateTime<Utc>) -> NaiveDate {
+        at.with_timezone(&self.tz).date_naive()
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.local_date(Utc::now())
+    }
+
+    fn resolve(&self, date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+        let local = date.and_time(time);
+        match self.tz.from_local_datetime(&local) {
+            LocalResult::Single(at) | LocalResult::Ambiguous(at, _) => at.with_timezone(&Utc),
+            // The wall-clock time was skipped by a DST jump; an hour later exists.
+            LocalResult::None => self
+                .tz
+                .from_local_datetime(&(local + Duration::hours(1)))
+                .earliest()
+                .map(|at| at.with_timezone(&Utc))
+                .unwrap_or_else(|| Utc.from_utc_datetime(&local)),
+        }
+    }
+
+    /// First instant of a local calendar day.
+    pub fn start_of_day(&self, date: NaiveDate) -> DateTime<Utc> {
+        self.resolve(date, NaiveTime::MIN)
+    }
+
+    /// Last second of a local calendar day.
+    pub fn end_of_day(&self, date: NaiveDate) -> DateTime<Utc> {
+        self.start_of_day(date + Duration::days(1)) - Duration::seconds(1)
+    }
+
+    /// When a loan due on `date` has to be back: closing time, or the end of
+    /// the day if the opening hours don't give one.
+    pub fn due_time(&self, date: NaiveDate) -> DateTime<Utc> {
+        match self.calendar.closing_time(date) {
+            Some(closes_at) => self.resolve(date, closes_at),
+            None => self.end_of_day(date),
+        }
+    }
+
+    /// Moves a due date to closing time on the local day it falls on, rolling
+    /// forward to the next open day when the library is closed that day.
+    pub fn normalize_due_date(&self, due_date: DateTime<Utc>) -> DateTime<Utc> {
+        let due_day = self.calendar.next_open_day(self.local_date(due_date));
+        self.due_time(due_day)
+    }
+
+    /// A loan is overdue from the local day after its due date. Comparing the
+    /// stored due date against this instant selects exactly those loans.
+    pub fn overdue_cutoff(&self) -> DateTime<Utc> {
+        self.start_of_day(self.today())
+    }
+
+    /// Open days between the due date and today. A loan that became overdue
+    /// over a weekend or holiday reports 0 until the library reopens.
+    pub fn days_overdue(&self, due_date: DateTime<Utc>) -> i64 {
+        self.calendar
+            .open_days_between(self.local_date(due_date), self.today())
+    }
+}
@@ -0,0 +1,240 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::collections::BTreeMap;
+
+/// Titles younger than this are treated as this old when computing turnover,
+/// so a book added last week does not top the chart after a single loan.
+const MIN_AGE_DAYS: i64 = 90;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CollectionParams {
+    /// A title is dormant when it has not been lent for this many months.
+    pub dormant_months: Option<i64>,
+    /// Maximum number of rows in each list.
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TitleActivity {
+    pub id: String,
+    pub title: String,
+    pub author: String,
+    pub category: String,
+    pub copies: i64,
+    pub total_loans: i64,
+    pub last_loaned_at: Option<DateTime<Utc>>,
+    pub added_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TitleTurnover {
+    pub id: String,
+    pub title: String,
+    pub author: String,
+    pub category: String,
+    pub copies: i64,
+    pub active_loans: i64,
+    pub loans_last_year: i64,
+    /// Loans per copy per year since the title was added.
+    pub turnover: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategoryTurnover {
+    pub name: String,
+    pub titles: i64,
+    pub copies: i64,
+    pub loans_last_year: i64,
+    /// Loans in the last year per copy.
+    pub turnover: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectionAge {
+    pub average_years: f64,
+    pub under_one_year: i64,
+    pub one_to_three_years: i64,
+    pub three_to_five_years: i64,
+    pub over_five_years: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectionAnalysis {
+    pub never_loaned: Vec<TitleActivity>,
+    pub dormant: Vec<TitleActivity>,
+    pub title_turnover: Vec<TitleTurnover>,
+    pub category_turnover: Vec<CategoryTurnover>,
+    /// Titles whose copies were all out on loan and that saw steady demand
+    /// over the last year, i.e. that need more copies.
+    pub high_demand: Vec<TitleTurnover>,
+    pub age: CollectionAge,
+}
+
+struct BookUsage {
+    id: String,
+    title: String,
+    author: String,
+    category: String,
+    created_at: DateTime<Utc>,
+    quantity: i64,
+    active_loans: i64,
+    total_loans: i64,
+    loans_last_year: i64,
+    last_loaned_at: Option<DateTime<Utc>>,
+}
+
+impl BookUsage {
+    /// `books.quantity` counts copies on the shelf, so copies out on loan
+    /// have to be added back to get the size of the holding.
+    fn copies(&self) -> i64 {
+        self.quantity + self.active_loans
+    }
+
+    fn activity(&self) -> TitleActivity {
+        TitleActivity {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            author: self.author.clone(),
+            category: self.category.clone(),
+            copies: self.copies(),
+            total_loans: self.total_loans,
+            last_loaned_at: self.last_loaned_at,
+            added_at: self.created_at,
+        }
+    }
+
+    fn turnover(&self, now: DateTime<Utc>) -> TitleTurnover {
+        let age_days = (now - self.created_at).num_days().max(MIN_AGE_DAYS);
+        let copies = self.copies().max(1);
+
+        TitleTurnover {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            author: self.author.clone(),
+            category: self.category.clone(),
+            copies: self.copies(),
+            active_loans: self.active_loans,
+            loans_last_year: self.loans_last_year,
+            turnover: self.total_loans as f64 / copies as f64 / (age_days as f64 / 365.0),
+        }
+    }
+}
+
+pub async fn get_collection_analysis(
+    pool: &Pool<Sqlite>,
+    params: &CollectionParams,
+) -> Result<CollectionAnalysis, sqlx::Error> {
+    let now = Utc::now();
+    let year_ago = now - Duration::days(365);
+    let dormant_since = now - Duration::days(30 * params.dormant_months.unwrap_or(12).max(1));
+    let limit = params.limit.filter(|l| *l > 0).unwrap_or(20) as usize;
+
+    let books = sqlx::query_as!(
+        BookUsage,
+        r#"
+        SELECT
+            COALESCE(b.id, '') as "id!: String",
+            COALESCE(b.title, '') as "title!: String",
+            COALESCE(b.author, '') as "author!: String",
+            COALESCE(b.category, '') as "category!: String",
+            b.created_at as "created_at!: chrono::DateTime<chrono::Utc>",
+            CAST(b.quantity AS INTEGER) as "quantity!: i64",
+            (SELECT COUNT(*) FROM lent l WHERE l.book_id = b.id AND l.status = 'lent') as "active_loans!: i64",
+            (SELECT COUNT(*) FROM lent l WHERE l.book_id = b.id) as "total_loans!: i64",
+            (SELECT COUNT(*) FROM lent l WHERE l.book_id = b.id AND l.lent_at >= ?) as "loans_last_year!: i64",
+            (SELECT MAX(l.lent_at) FROM lent l WHERE l.book_id = b.id) as "last_loaned_at?: chrono::DateTime<chrono::Utc>"
+        FROM books b
+        "#,
+        year_ago
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut never_loaned: Vec<TitleActivity> = books
+        .iter()
+        .filter(|b| b.total_loans == 0)
+        .map(BookUsage::activity)
+        .collect();
+    never_loaned.sort_by_key(|b| b.added_at);
+    never_loaned.truncate(limit);
+
+    let mut dormant: Vec<TitleActivity> = books
+        .iter()
+        .filter(|b| matches!(b.last_loaned_at, Some(at) if at < dormant_since))
+        .map(BookUsage::activity)
+        .collect();
+    dormant.sort_by_key(|b| b.last_loaned_at);
+    dormant.truncate(limit);
+
+    let mut title_turnover: Vec<TitleTurnover> = books.iter().map(|b| b.turnover(now)).collect();
+    title_turnover.sort_by(|a, b| b.turnover.total_cmp(&a.turnover));
+    title_turnover.truncate(limit);
+
+    let mut high_demand: Vec<TitleTurnover> = books
+        .iter()
+        .filter(|b| b.quantity <= 0 && b.active_loans > 0 && b.loans_last_year > b.copies())
+        .map(|b| b.turnover(now))
+        .collect();
+    high_demand.sort_by(|a, b| b.loans_last_year.cmp(&a.loans_last_year));
+    high_demand.truncate(limit);
+
+    let mut categories: BTreeMap<String, CategoryTurnover> = BTreeMap::new();
+    for book in &books {
+        let name = if book.category.is_empty() {
+            "Uncategorized".to_string()
+        } else {
+            book.category.clone()
+        };
+        let entry = categories
+            .entry(name.clone())
+            .or_insert_with(|| CategoryTurnover {
+                name,
+                titles: 0,
+                copies: 0,
+                loans_last_year: 0,
+                turnover: 0.0,
+            });
+        entry.titles += 1;
+        entry.copies += book.copies();
+        entry.loans_last_year += book.loans_last_year;
+    }
+    let mut category_turnover: Vec<CategoryTurnover> = categories
+        .into_values()
+        .map(|mut c| {
+            c.turnover = c.loans_last_year as f64 / c.copies.max(1) as f64;
+            c
+        })
+        .collect();
+    category_turnover.sort_by(|a, b| b.turnover.total_cmp(&a.turnover));
+
+    let mut age = CollectionAge {
+        average_years: 0.0,
+        under_one_year: 0,
+        one_to_three_years: 0,
+        three_to_five_years: 0,
+        over_five_years: 0,
+    };
+    for book in &books {
+        let years = (now - book.created_at).num_days() as f64 / 365.0;
+        age.average_years += years;
+        match years {
+            y if y < 1.0 => age.under_one_year += 1,
+            y if y < 3.0 => age.one_to_three_years += 1,
+            y if y < 5.0 => age.three_to_five_years += 1,
+            _ => age.over_five_years += 1,
+        }
+    }
+    if !books.is_empty() {
+        age.average_years /= books.len() as f64;
+    }
+
+    Ok(CollectionAnalysis {
+        never_loaned,
+        dormant,
+        title_turnover,
+        category_turnover,
+        high_demand,
+        age,
+    })
+}
@@ -0,0 +1,165 @@
+use crate::services::backup;
+use crate::services::clock::LibraryClock;
+use crate::services::settings::{self, AutoBackup};
+use crate::services::statistics::{self, OverdueBook, StatsFilter};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+
+/// Settings row holding the library day the last digest was shown for. It
+/// isn't part of `Settings` because nobody edits it.
+const LAST_DIGEST_KEY: &str = "daily_digest_date";
+
+/// Slack on top of the backup schedule before a backup counts as missing,
+/// so a backup that is running as the digest goes out isn't reported.
+const BACKUP_GRACE_DAYS: i64 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DigestLoan {
+    pub id: String,
+    pub book_title: String,
+    pub student_name: String,
+    pub grade: String,
+    pub due_date: DateTime<Utc>,
+}
+
+impl From<OverdueBook> for DigestLoan {
+    fn from(book: OverdueBook) -> Self {
+        Self {
+            id: book.id,
+            book_title: book.book_title,
+            student_name: book.student_name,
+            grade: book.grade,
+            due_date: book.due_date,
+        }
+    }
+}
+
+/// The morning summary.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DailyDigest {
+    /// The library's local date the digest is for.
+    pub date: NaiveDate,
+    pub due_today: Vec<DigestLoan>,
+    /// Loans that became overdue since the library was last open.
+    pub newly_overdue: Vec<DigestLoan>,
+    pub overdue_total: usize,
+    pub last_backup: Option<DateTime<Utc>>,
+    /// No manual or scheduled backup within the backup schedule (a week
+    /// when scheduled backups are off), plus a day's grace.
+    pub backup_missing: bool,
+}
+
+impl DailyDigest {
+    /// One line per item that needs attention, for the OS notification.
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if !self.due_today.is_empty() {
+            lines.push(format!("{} loan(s) due today", self.due_today.len()));
+        }
+        if !self.newly_overdue.is_empty() {
+            lines.push(format!(
+                "{} newly overdue, {} overdue in all",
+                self.newly_overdue.len(),
+                self.overdue_total
+            ));
+        }
+        if self.backup_missing {
+            lines.push(match self.last_backup {
+                Some(at) => format!("No backup since {}", at.format("%-d %B %Y")),
+                None => "No backup has been made yet".to_string(),
+            });
+        }
+        if lines.is_empty() {
+            lines.push("Nothing due today and no new overdue loans".to_string());
+        }
+        lines
+    }
+}
+
+pub async fn build_digest(pool: &Pool<Sqlite>) -> Result<DailyDigest, sqlx::Error> {
+    let settings = settings::get_settings(pool).await?;
+    let clock = LibraryClock::load(pool).await?;
+    let today = clock.today();
+    let start = clock.start_of_day(today);
+    let end = clock.end_of_day(today);
+
+    let due_today = sqlx::query_as!(
+        DigestLoan,
+        r#"
+        SELECT
+            l.id as "id!: String",
+            COALESCE(b.title, '') as "book_title!: String",
+            COALESCE(s.name, '') as "student_name!: String",
+            COALESCE(s.grade, '') as "grade!: String",
+            l.due_date as "due_date!: DateTime<Utc>"
+        FROM lent l
+        JOIN books b ON l.book_id = b.id
+        JOIN students s ON l.student_id = s.id
+        WHERE l.status = 'lent' AND l.due_date >= ? AND l.due_date <= ?
+        ORDER BY l.due_date ASC, s.name ASC
+        "#,
+        start,
+        end
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let filter = StatsFilter {
+        limit: Some(i64::MAX),
+        ..Default::default()
+    };
+    let overdue = statistics::get_overdue_books(pool, &filter).await?;
+    let overdue_total = overdue.len();
+    // A loan due on the last open day is 1 open day overdue today, or 0
+    // while the library is closed.
+    let newly_overdue = overdue
+        .into_iter()
+        .filter(|book| book.days_overdue <= 1)
+        .map(DigestLoan::from)
+        .collect();
+
+    let last_backup = backup::last_backup(pool).await?;
+    let expected = match settings.auto_backup {
+        AutoBackup::Daily => Duration::days(1),
+        AutoBackup::Weekly | AutoBackup::Off => Duration::days(7),
+    } + Duration::days(BACKUP_GRACE_DAYS);
+    let backup_missing = last_backup.map_or(true, |at| Utc::now() - at > expected);
+
+    Ok(DailyDigest {
+        date: today,
+        due_today,
+        newly_overdue,
+        overdue_total,
+        last_backup,
+        backup_missing,
+    })
+}
+
+/// The library day the digest was last shown for, if ever.
+pub async fn last_digest_date(pool: &Pool<Sqlite>) -> Result<Option<NaiveDate>, sqlx::Error> {
+    let value = sqlx::query_scalar!(
+        r#"SELECT value as "value!: String" FROM settings WHERE key = ?"#,
+        LAST_DIGEST_KEY
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(value.and_then(|value| value.parse().ok()))
+}
+
+pub async fn record_digest_date(pool: &Pool<Sqlite>, date: NaiveDate) -> Result<(), sqlx::Error> {
+    let value = date.to_string();
+    sqlx::query!(
+        r#"
+        INSERT INTO settings (key, value) VALUES (?, ?)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value
+        "#,
+        LAST_DIGEST_KEY,
+        value
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use crate::services::settings::{Settings, SmtpSecurity};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::time::Duration;
+
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailError {
+    #[error("Email is not set up, add an SMTP server and sender address in settings")]
+    NotConfigured,
+    #[error("Invalid email address: {0}")]
+    Address(String),
+    #[error("Could not build message: {0}")]
+    Message(#[from] lettre::error::Error),
+    #[error("SMTP error: {0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+}
+
+fn mailbox(address: &str) -> Result<Mailbox, EmailError> {
+    address
+        .trim()
+        .parse()
+        .map_err(|_| EmailError::Address(address.to_string()))
+}
+
+/// Sends plain-text mail through the SMTP server in the settings.
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    /// `password` is [`Secret::SmtpPassword`](crate::services::settings::Secret),
+    /// which `Settings` doesn't carry.
+    pub fn from_settings(settings: &Settings, password: &str) -> Result<Self, EmailError> {
+        let host = settings.smtp_host.trim();
+        if host.is_empty() || settings.smtp_from.trim().is_empty() {
+            return Err(EmailError::NotConfigured);
+        }
+
+        let builder = match settings.smtp_security {
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+            SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+        }
+        .port(settings.smtp_port as u16)
+        .timeout(Some(SMTP_TIMEOUT));
+
+        let username = settings.smtp_username.trim();
+        let builder = if username.is_empty() {
+            builder
+        } else {
+            builder.credentials(Credentials::new(
+                username.to_string(),
+                password.to_string(),
+            ))
+        };
+
+        Ok(Self {
+            transport: builder.build(),
+            from: mailbox(&settings.smtp_from)?,
+        })
+    }
+
+    pub async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), EmailError> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(mailbox(to)?)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body.to_string())?;
+
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
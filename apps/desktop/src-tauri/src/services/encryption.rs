@@ -0,0 +1,222 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use std::io::Read;
+use std::path::Path;
+
+/// Encrypted files start with this, followed by the Argon2 parameters, salt
+/// and nonce. The whole header is authenticated along with the ciphertext.
+const MAGIC: &[u8; 8] = b"TIZAENC1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = MAGIC.len() + 3 * 4 + SALT_LEN + NONCE_LEN;
+
+/// Upper bounds on the Argon2 costs we accept from a file header, so a
+/// crafted file can't make us allocate gigabytes or hash for hours before
+/// failing to decrypt. Well above what [`encrypt`] writes.
+const MAX_MEMORY_KIB: u32 = 256 * 1024;
+const MAX_TIME_COST: u32 = 16;
+const MAX_PARALLELISM: u32 = 16;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EncryptionError {
+    #[error("Passphrase must not be empty")]
+    EmptyPassphrase,
+    #[error("Not an encrypted Tiza backup")]
+    NotEncrypted,
+    #[error("Encrypted backup is malformed: {0}")]
+    Malformed(String),
+    #[error("Key derivation failed: {0}")]
+    Kdf(String),
+    #[error("Encryption failed")]
+    Encryption,
+    #[error("Wrong passphrase, or the backup has been modified")]
+    Decryption,
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Checks only the magic bytes, without reading the whole file.
+pub fn is_encrypted_file(path: &Path) -> Result<bool, std::io::Error> {
+    let mut magic = [0u8; MAGIC.len()];
+    match std::fs::File::open(path)?.read_exact(&mut magic) {
+        Ok(()) => Ok(is_encrypted(&magic)),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], params: Params) -> Result<Key, EncryptionError> {
+    let mut key = Key::default();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| EncryptionError::Kdf(e.to_string()))?;
+    Ok(key)
+}
+
+/// Encrypts `plaintext` with XChaCha20-Poly1305 under a key derived from
+/// `passphrase` with Argon2id and a fresh random salt. Key derivation takes a
+/// while, so call this from a blocking task.
+pub fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>, EncryptionError> {
+    if passphrase.is_empty() {
+        return Err(EncryptionError::EmptyPassphrase);
+    }
+
+    let params = Params::default();
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let mut output = Vec::with_capacity(HEADER_LEN + plaintext.len() + 16);
+    output.extend_from_slice(MAGIC);
+    output.extend_from_slice(&params.m_cost().to_le_bytes());
+    output.extend_from_slice(&params.t_cost().to_le_bytes());
+    output.extend_from_slice(&params.p_cost().to_le_bytes());
+    output.extend_from_slice(&salt);
+    output.extend_from_slice(&nonce);
+
+    let key = derive_key(passphrase, &salt, params)?;
+    let ciphertext = XChaCha20Poly1305::new(&key)
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: &output,
+            },
+        )
+        .map_err(|_| EncryptionError::Encryption)?;
+
+    output.extend_from_slice(&ciphertext);
+    Ok(output)
+}
+
+/// Reverses [`encrypt`]. Any change to the header or ciphertext, like a wrong
+/// passphrase, fails authentication and yields [`EncryptionError::Decryption`].
+/// Key derivation takes a while, so call this from a blocking task.
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>, EncryptionError> {
+    if !is_encrypted(data) {
+        return Err(EncryptionError::NotEncrypted);
+    }
+    if data.len() < HEADER_LEN {
+        return Err(EncryptionError::Malformed("header is truncated".to_string()));
+    }
+
+    let (header, ciphertext) = data.split_at(HEADER_LEN);
+    let read_u32 = |offset: usize| {
+        u32::from_le_bytes(header[offset..offset + 4].try_into().expect("4-byte slice"))
+    };
+    let m_cost = read_u32(MAGIC.len());
+    let t_cost = read_u32(MAGIC.len() + 4);
+    let p_cost = read_u32(MAGIC.len() + 8);
+    if m_cost > MAX_MEMORY_KIB {
+        return Err(EncryptionError::Malformed(format!(
+            "memory cost {} KiB is too large",
+            m_cost
+        )));
+    }
+    if t_cost > MAX_TIME_COST {
+        return Err(EncryptionError::Malformed(format!(
+            "time cost {} is too large",
+            t_cost
+        )));
+    }
+    if p_cost > MAX_PARALLELISM {
+        return Err(EncryptionError::Malformed(format!(
+            "parallelism {} is too large",
+            p_cost
+        )));
+    }
+    let params = Params::new(m_cost, t_cost, p_cost, None)
+        .map_err(|e| EncryptionError::Malformed(e.to_string()))?;
+
+    let salt_start = MAGIC.len() + 12;
+    let salt = &header[salt_start..salt_start + SALT_LEN];
+    let nonce = XNonce::from_slice(&header[salt_start + SALT_LEN..]);
+
+    let key = derive_key(passphrase, salt, params)?;
+    XChaCha20Poly1305::new(&key)
+        .decrypt(
+            nonce,
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| EncryptionError::Decryption)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAINTEXT: &[u8] = b"SQLite format 3\0 students: Alice Uwase, 0788000000";
+
+    #[test]
+    fn round_trips_with_the_right_passphrase() {
+        let encrypted = encrypt(PLAINTEXT, "correct horse").unwrap();
+
+        assert!(is_encrypted(&encrypted));
+        assert_eq!(decrypt(&encrypted, "correct horse").unwrap(), PLAINTEXT);
+    }
+
+    #[test]
+    fn rejects_a_wrong_passphrase() {
+        let encrypted = encrypt(PLAINTEXT, "correct horse").unwrap();
+
+        assert!(matches!(
+            decrypt(&encrypted, "battery staple"),
+            Err(EncryptionError::Decryption)
+        ));
+    }
+
+    #[test]
+    fn rejects_a_tampered_backup() {
+        let encrypted = encrypt(PLAINTEXT, "correct horse").unwrap();
+
+        // Flipping a bit anywhere after the magic, in the authenticated header
+        // or in the ciphertext, must be detected.
+        for position in [MAGIC.len() + 12, HEADER_LEN - 1, HEADER_LEN, encrypted.len() - 1] {
+            let mut tampered = encrypted.clone();
+            tampered[position] ^= 0x01;
+            assert!(
+                matches!(
+                    decrypt(&tampered, "correct horse"),
+                    Err(EncryptionError::Decryption)
+                ),
+                "tampering at byte {} was not detected",
+                position
+            );
+        }
+
+        let truncated = &encrypted[..encrypted.len() - 1];
+        assert!(decrypt(truncated, "correct horse").is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_costs_before_deriving_a_key() {
+        let encrypted = encrypt(PLAINTEXT, "correct horse").unwrap();
+
+        // Memory, time and parallelism cost, in header order.
+        for offset in [0, 4, 8] {
+            let mut tampered = encrypted.clone();
+            let start = MAGIC.len() + offset;
+            tampered[start..start + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+            assert!(matches!(
+                decrypt(&tampered, "correct horse"),
+                Err(EncryptionError::Malformed(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn plain_databases_are_not_mistaken_for_encrypted_ones() {
+        assert!(!is_encrypted(PLAINTEXT));
+        assert!(matches!(
+            decrypt(PLAINTEXT, "correct horse"),
+            Err(EncryptionError::NotEncrypted)
+        ));
+    }
+}
@@ -0,0 +1,327 @@
+use crate::models::LendingStatus;
+use crate::services::clock::LibraryClock;
+use crate::services::lendings::{self, LendingError, RenewError};
+use crate::services::settings;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+
+/// Settings row holding the Argon2 hash of the PIN that unlocks the kiosk.
+/// Kept out of [`settings::Settings`] so it is never sent to the UI; while
+/// the row exists the app starts in kiosk mode.
+const PIN_HASH_KEY: &str = "kiosk_pin_hash";
+/// Wrong PINs entered in a row, and when the next attempt is allowed once
+/// there were too many. Stored like the hash, so a restart doesn't reset them.
+const PIN_FAILURES_KEY: &str = "kiosk_pin_failures";
+const PIN_LOCKED_UNTIL_KEY: &str = "kiosk_pin_locked_until";
+const MIN_PIN_LEN: usize = 4;
+/// Wrong PINs allowed before unlocking is locked out.
+const MAX_PIN_ATTEMPTS: i64 = 5;
+/// The first lockout; every further wrong PIN doubles it, up to 16 times.
+const PIN_LOCKOUT_MINUTES: i64 = 5;
+
+#[derive(Debug, thiserror::Error)]
+pub enum KioskError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("No active student with card {0}")]
+    UnknownCard(String),
+    #[error("No book with ISBN or ID {0}")]
+    UnknownBook(String),
+    #[error("No copies of this book are available")]
+    Unavailable,
+    #[error("You have reached the loan limit")]
+    LoanLimit,
+    #[error("This loan belongs to another student")]
+    NotYourLoan,
+    #[error("{0}")]
+    Lending(#[from] LendingError),
+    #[error("{0}")]
+    Renew(#[from] RenewError),
+    #[error("PIN must be at least {MIN_PIN_LEN} characters")]
+    PinTooShort,
+    #[error("Wrong PIN")]
+    WrongPin,
+    #[error("Too many wrong PINs, try again in {0} minutes")]
+    LockedOut(i64),
+    #[error("Could not hash PIN: {0}")]
+    Hash(String),
+}
+
+/// The student signed in at the kiosk, as much as the kiosk screen shows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KioskStudent {
+    pub id: String,
+    pub name: String,
+    pub grade: String,
+    pub student_id: String,
+}
+
+/// An active loan as listed for the signed-in student.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KioskLoan {
+    pub id: String,
+    pub book_title: String,
+    pub book_author: String,
+    pub lent_at: DateTime<Utc>,
+    pub due_date: DateTime<Utc>,
+    pub renewal_count: i64,
+    pub overdue: bool,
+    pub renewable: bool,
+}
+
+pub async fn find_student_by_card(
+    pool: &Pool<Sqlite>,
+    card: &str,
+) -> Result<KioskStudent, KioskError> {
+    let card = card.trim();
+    sqlx::query_as!(
+        KioskStudent,
+        r#"
+        SELECT
+            COALESCE(id, '') as "id!: String",
+            COALESCE(name, '') as "name!: String",
+            COALESCE(grade, '') as "grade!: String",
+            COALESCE(student_id, '') as "student_id!: String"
+        FROM students
+        WHERE student_id = ? AND status = 'active'
+        "#,
+        card
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| KioskError::UnknownCard(card.to_string()))
+}
+
+pub async fn get_student_loans(
+    pool: &Pool<Sqlite>,
+    student_id: &str,
+) -> Result<Vec<KioskLoan>, sqlx::Error> {
+    let max_renewals = settings::get_settings(pool).await?.max_renewals;
+    let cutoff = LibraryClock::load(pool).await?.overdue_cutoff();
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            l.id as "id!: String",
+            COALESCE(b.title, '') as "book_title!: String",
+            COALESCE(b.author, '') as "book_author!: String",
+            l.lent_at as "lent_at!: DateTime<Utc>",
+            l.due_date as "due_date!: DateTime<Utc>",
+            l.renewal_count as "renewal_count!: i64"
+        FROM lent l
+        JOIN books b ON l.book_id = b.id
+        WHERE l.student_id = ? AND l.status = 'lent'
+        ORDER BY l.due_date
+        "#,
+        student_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let overdue = row.due_date < cutoff;
+            KioskLoan {
+                renewable: !overdue && row.renewal_count < max_renewals,
+                overdue,
+                id: row.id,
+                book_title: row.book_title,
+                book_author: row.book_author,
+                lent_at: row.lent_at,
+                due_date: row.due_date,
+                renewal_count: row.renewal_count,
+            }
+        })
+        .collect())
+}
+
+/// Lends the book with the scanned ISBN (or book ID) to the student.
+pub async fn checkout(
+    pool: &Pool<Sqlite>,
+    student_id: &str,
+    code: &str,
+) -> Result<(), KioskError> {
+    let code = code.trim();
+    let book_id = sqlx::query_scalar!(
+        r#"
+        SELECT id as "id!: String"
+        FROM books
+        WHERE isbn = ? OR id = ?
+        ORDER BY quantity DESC
+        LIMIT 1
+        "#,
+        code,
+        code
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| KioskError::UnknownBook(code.to_string()))?;
+
+    match lendings::create_lending(pool, &book_id, student_id, None).await {
+        Err(LendingError::LoanLimit(_)) => Err(KioskError::LoanLimit),
+        Err(LendingError::Unavailable) => Err(KioskError::Unavailable),
+        result => Ok(result?),
+    }
+}
+
+/// Fails unless `lending_id` is an active loan of `student_id`, so a student
+/// can only return or renew their own books.
+async fn require_own_loan(
+    pool: &Pool<Sqlite>,
+    student_id: &str,
+    lending_id: &str,
+) -> Result<(), KioskError> {
+    let lending = sqlx::query!(
+        r#"
+        SELECT student_id as "student_id!: String", status as "status!: LendingStatus"
+        FROM lent
+        WHERE id = ?
+        "#,
+        lending_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if lending.student_id != student_id {
+        return Err(KioskError::NotYourLoan);
+    }
+    if !matches!(lending.status, LendingStatus::Lent) {
+        return Err(RenewError::Returned.into());
+    }
+    Ok(())
+}
+
+pub async fn return_loan(
+    pool: &Pool<Sqlite>,
+    student_id: &str,
+    lending_id: &str,
+) -> Result<(), KioskError> {
+    require_own_loan(pool, student_id, lending_id).await?;
+    lendings::return_lending(pool, lending_id).await?;
+    Ok(())
+}
+
+pub async fn renew_loan(
+    pool: &Pool<Sqlite>,
+    student_id: &str,
+    lending_id: &str,
+) -> Result<DateTime<Utc>, KioskError> {
+    require_own_loan(pool, student_id, lending_id).await?;
+    Ok(lendings::renew_lending(pool, lending_id).await?)
+}
+
+async fn setting(pool: &Pool<Sqlite>, key: &str) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT value as "value!: String" FROM settings WHERE key = ?"#,
+        key
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+async fn set_setting(pool: &Pool<Sqlite>, key: &str, value: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO settings (key, value) VALUES (?, ?)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value
+        "#,
+        key,
+        value
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+async fn clear_pin_failures(pool: &Pool<Sqlite>) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"DELETE FROM settings WHERE key IN (?, ?)"#,
+        PIN_FAILURES_KEY,
+        PIN_LOCKED_UNTIL_KEY
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+async fn pin_hash(pool: &Pool<Sqlite>) -> Result<Option<String>, sqlx::Error> {
+    setting(pool, PIN_HASH_KEY).await
+}
+
+/// Counts a wrong PIN and, from the `MAX_PIN_ATTEMPTS`th on, locks unlocking
+/// out for a lockout that doubles with every further wrong PIN.
+async fn record_wrong_pin(pool: &Pool<Sqlite>, now: DateTime<Utc>) -> Result<(), sqlx::Error> {
+    let failures = setting(pool, PIN_FAILURES_KEY)
+        .await?
+        .and_then(|value| value.parse::<i64>().ok())
+        .unwrap_or(0)
+        + 1;
+    set_setting(pool, PIN_FAILURES_KEY, &failures.to_string()).await?;
+
+    if failures >= MAX_PIN_ATTEMPTS {
+        let lockout = PIN_LOCKOUT_MINUTES << (failures - MAX_PIN_ATTEMPTS).min(4);
+        let until = now + Duration::minutes(lockout);
+        set_setting(pool, PIN_LOCKED_UNTIL_KEY, &until.to_rfc3339()).await?;
+    }
+    Ok(())
+}
+
+pub async fn is_locked(pool: &Pool<Sqlite>) -> Result<bool, sqlx::Error> {
+    Ok(pin_hash(pool).await?.is_some())
+}
+
+/// Stores the PIN needed to leave kiosk mode.
+pub async fn lock(pool: &Pool<Sqlite>, pin: &str) -> Result<(), KioskError> {
+    if pin.chars().count() < MIN_PIN_LEN {
+        return Err(KioskError::PinTooShort);
+    }
+
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(pin.as_bytes(), &salt)
+        .map_err(|e| KioskError::Hash(e.to_string()))?
+        .to_string();
+
+    set_setting(pool, PIN_HASH_KEY, &hash).await?;
+    clear_pin_failures(pool).await?;
+
+    Ok(())
+}
+
+/// Checks the PIN and, if it matches, leaves kiosk mode. After
+/// `MAX_PIN_ATTEMPTS` wrong PINs in a row, every attempt is refused until the
+/// lockout has passed, so the PIN can't be guessed at the kiosk.
+pub async fn unlock(pool: &Pool<Sqlite>, pin: &str) -> Result<(), KioskError> {
+    let now = Utc::now();
+    let locked_until = setting(pool, PIN_LOCKED_UNTIL_KEY)
+        .await?
+        .and_then(|value| DateTime::parse_from_rfc3339(&value).ok())
+        .map(|until| until.with_timezone(&Utc));
+    if let Some(until) = locked_until.filter(|until| *until > now) {
+        let seconds = (until - now).num_seconds();
+        return Err(KioskError::LockedOut((seconds + 59) / 60));
+    }
+
+    if let Some(hash) = pin_hash(pool).await? {
+        let parsed = PasswordHash::new(&hash).map_err(|e| KioskError::Hash(e.to_string()))?;
+        if Argon2::default()
+            .verify_password(pin.as_bytes(), &parsed)
+            .is_err()
+        {
+            record_wrong_pin(pool, now).await?;
+            return Err(KioskError::WrongPin);
+        }
+    }
+
+    sqlx::query!(r#"DELETE FROM settings WHERE key = ?"#, PIN_HASH_KEY)
+        .execute(pool)
+        .await?;
+    clear_pin_failures(pool).await?;
+
+    Ok(())
+}
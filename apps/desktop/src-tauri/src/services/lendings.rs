@@ -1,5 +1,7 @@
 use crate::models::{LendingStatus, LendingWithDetails};
-use chrono::{DateTime, Utc};
+use crate::services::clock::LibraryClock;
+use crate::services::settings;
+use chrono::{DateTime, Duration, Utc};
 use sqlx::{Pool, Sqlite};
 use uuid::Uuid;
 
@@ -121,32 +123,72 @@ pub async fn get_lending_records_by_student_id(
     .await
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum LendingError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("No copies of this book are available")]
+    Unavailable,
+    #[error("Student has reached the loan limit of {0}")]
+    LoanLimit(i64),
+    #[error("This book has already been returned")]
+    NotLent,
+}
+
+/// Lends a copy of the book. The copy is taken out first, which also takes
+/// SQLite's write lock, so the availability and loan limit checks can't race
+/// with another checkout; a refused loan rolls the copy back. A missing book
+/// is reported as `RowNotFound`.
 pub async fn create_lending(
     pool: &Pool<Sqlite>,
     book_id: &str,
     student_id: &str,
-    due_date: DateTime<Utc>,
-) -> Result<(), sqlx::Error> {
+    due_date: Option<DateTime<Utc>>,
+) -> Result<(), LendingError> {
+    let settings = settings::get_settings(pool).await?;
+
+    let id = Uuid::new_v4().to_string();
+    let lent_at = Utc::now();
+    let due_date =
+        due_date.unwrap_or_else(|| lent_at + Duration::days(settings.loan_period_days));
+    let due_date = LibraryClock::load(pool).await?.normalize_due_date(due_date);
+
+    let mut tx = pool.begin().await?;
+
+    let taken = sqlx::query!(
+        r#"
+        UPDATE books
+        SET quantity = quantity - 1,
+            status = CASE WHEN quantity - 1 <= 0 THEN 'unavailable' ELSE 'available' END
+        WHERE id = ? AND quantity > 0
+        "#,
+        book_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if taken.rows_affected() == 0 {
+        sqlx::query!(r#"SELECT id FROM books WHERE id = ?"#, book_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        return Err(LendingError::Unavailable);
+    }
+
     let existing = sqlx::query_scalar!(
         r#"
-        SELECT COUNT(*) as count
+        SELECT COUNT(*) as "count!: i64"
         FROM lent
         WHERE student_id = ? AND status = 'lent'
         "#,
         student_id
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?;
 
-    if existing > 0 {
-        return Err(sqlx::Error::RowNotFound);
+    if existing >= settings.max_loans_per_student {
+        return Err(LendingError::LoanLimit(settings.max_loans_per_student));
     }
 
-    let id = Uuid::new_v4().to_string();
-    let lent_at = Utc::now();
-
-    let mut tx = pool.begin().await?;
-
     sqlx::query!(
         r#"
         INSERT INTO lent (id, book_id, student_id, lent_at, due_date, status)
@@ -162,18 +204,6 @@ pub async fn create_lending(
     .execute(&mut *tx)
     .await?;
 
-    sqlx::query!(
-        r#"
-        UPDATE books
-        SET quantity = quantity - 1,
-            status = CASE WHEN quantity - 1 <= 0 THEN 'unavailable' ELSE 'available' END
-        WHERE id = ?
-        "#,
-        book_id
-    )
-    .execute(&mut *tx)
-    .await?;
-
     tx.commit().await?;
 
     Ok(())
@@ -187,6 +217,8 @@ pub async fn update_lending(
     due_date: DateTime<Utc>,
     returned_at: DateTime<Utc>,
 ) -> Result<(), sqlx::Error> {
+    let due_date = LibraryClock::load(pool).await?.normalize_due_date(due_date);
+
     sqlx::query!(
         r#"
         UPDATE lent
@@ -205,20 +237,19 @@ pub async fn update_lending(
     Ok(())
 }
 
-pub async fn return_lending(pool: &Pool<Sqlite>, id: &str) -> Result<(), sqlx::Error> {
+/// Marks an active loan returned and puts the copy back. Returning a loan
+/// twice is refused, so the copy is only counted once; a missing loan is
+/// reported as `RowNotFound`.
+pub async fn return_lending(pool: &Pool<Sqlite>, id: &str) -> Result<(), LendingError> {
     let returned_at = Utc::now();
 
     let mut tx = pool.begin().await?;
 
-    let lending = sqlx::query!(r#"SELECT book_id FROM lent WHERE id = ?"#, id)
-        .fetch_one(&mut *tx)
-        .await?;
-
-    sqlx::query!(
+    let returned = sqlx::query!(
         r#"
         UPDATE lent
         SET status = ?, returned_at = ?
-        WHERE id = ?
+        WHERE id = ? AND status = 'lent'
         "#,
         "returned",
         returned_at,
@@ -227,6 +258,14 @@ pub async fn return_lending(pool: &Pool<Sqlite>, id: &str) -> Result<(), sqlx::E
     .execute(&mut *tx)
     .await?;
 
+    let lending = sqlx::query!(r#"SELECT book_id FROM lent WHERE id = ?"#, id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    if returned.rows_affected() == 0 {
+        return Err(LendingError::NotLent);
+    }
+
     sqlx::query!(
         r#"
         UPDATE books
@@ -244,6 +283,68 @@ pub async fn return_lending(pool: &Pool<Sqlite>, id: &str) -> Result<(), sqlx::E
     Ok(())
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum RenewError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("This book has already been returned")]
+    Returned,
+    #[error("Overdue loans can't be renewed")]
+    Overdue,
+    #[error("This loan has already been renewed the maximum number of times ({0})")]
+    LimitReached(i64),
+}
+
+/// Extends an active loan by one loan period from today. Overdue loans and
+/// loans renewed `max_renewals` times already are refused. Returns the new
+/// due date.
+pub async fn renew_lending(pool: &Pool<Sqlite>, id: &str) -> Result<DateTime<Utc>, RenewError> {
+    let settings = settings::get_settings(pool).await?;
+    let clock = LibraryClock::load(pool).await?;
+
+    let lending = sqlx::query!(
+        r#"
+        SELECT
+            status as "status!: LendingStatus",
+            due_date as "due_date!: DateTime<Utc>",
+            renewal_count as "renewal_count!: i64"
+        FROM lent
+        WHERE id = ?
+        "#,
+        id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if !matches!(lending.status, LendingStatus::Lent) {
+        return Err(RenewError::Returned);
+    }
+    if lending.due_date < clock.overdue_cutoff() {
+        return Err(RenewError::Overdue);
+    }
+    if lending.renewal_count >= settings.max_renewals {
+        return Err(RenewError::LimitReached(settings.max_renewals));
+    }
+
+    let due_date = clock
+        .normalize_due_date(Utc::now() + Duration::days(settings.loan_period_days))
+        .max(lending.due_date);
+
+    sqlx::query!(
+        r#"
+        UPDATE lent
+        SET due_date = ?, renewal_count = renewal_count + 1
+        WHERE id = ?
+        "#,
+        due_date,
+        id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(due_date)
+}
+
 pub async fn delete_lending(pool: &Pool<Sqlite>, id: &str) -> Result<(), sqlx::Error> {
     sqlx::query!(r#"DELETE FROM lent WHERE id = ?"#, id)
         .execute(pool)
@@ -1,4 +1,22 @@
+pub mod archive;
+pub mod backup;
 pub mod books;
+pub mod calendar;
+pub mod clock;
+pub mod collection;
+pub mod digest;
+pub mod email;
+pub mod encryption;
+pub mod kiosk;
 pub mod lendings;
+pub mod notifications;
+pub mod pdf;
+pub mod reading;
+pub mod rollover;
+pub mod roster;
+pub mod settings;
+pub mod sms;
 pub mod statistics;
 pub mod students;
+pub mod trends;
+pub mod xlsx;
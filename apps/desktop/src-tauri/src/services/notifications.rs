@@ -0,0 +1,347 @@
+use crate::services::clock::LibraryClock;
+use crate::services::email::Mailer;
+use crate::services::settings::{self, Settings};
+use crate::services::statistics::{self, StatsFilter};
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+pub const EMAIL_CHANNEL: &str = "email";
+
+/// Why a reminder is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoticeKind {
+    DueSoon,
+    Overdue,
+}
+
+impl std::fmt::Display for NoticeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NoticeKind::DueSoon => write!(f, "due_soon"),
+            NoticeKind::Overdue => write!(f, "overdue"),
+        }
+    }
+}
+
+/// A reminder about one active loan.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Notice {
+    pub lending_id: String,
+    pub kind: NoticeKind,
+    pub student_id: String,
+    pub student_name: String,
+    pub book_title: String,
+    pub due_date: DateTime<Utc>,
+    pub days_overdue: i64,
+    pub email: Option<String>,
+    pub guardian_email: Option<String>,
+    pub phone_number: Option<String>,
+    pub sms_opt_out: bool,
+}
+
+impl Notice {
+    /// The student's and guardian's addresses, skipping blanks and duplicates.
+    pub fn email_recipients(&self) -> Vec<&str> {
+        let mut recipients: Vec<&str> = Vec::new();
+        for address in [&self.email, &self.guardian_email].into_iter().flatten() {
+            let address = address.trim();
+            if !address.is_empty() && !recipients.contains(&address) {
+                recipients.push(address);
+            }
+        }
+        recipients
+    }
+}
+
+/// Subject and body with `{student}`, `{book}`, `{due_date}`,
+/// `{days_overdue}` and `{library}` placeholders.
+pub struct Template {
+    pub subject: &'static str,
+    pub body: &'static str,
+}
+
+const DUE_SOON: Template = Template {
+    subject: "Reminder: \"{book}\" is due {due_date}",
+    body: "Hello,\n\n\
+           This is a reminder that \"{book}\", borrowed by {student}, is due back at \
+           {library} on {due_date}.\n\n\
+           Please return it on time so other students can borrow it.\n\n\
+           {library}",
+};
+
+const OVERDUE: Template = Template {
+    subject: "Overdue: \"{book}\" was due {due_date}",
+    body: "Hello,\n\n\
+           \"{book}\", borrowed by {student}, was due back at {library} on {due_date} \
+           and is now {days_overdue} day(s) overdue.\n\n\
+           Please return it as soon as possible.\n\n\
+           {library}",
+};
+
+pub fn template(kind: NoticeKind) -> &'static Template {
+    match kind {
+        NoticeKind::DueSoon => &DUE_SOON,
+        NoticeKind::Overdue => &OVERDUE,
+    }
+}
+
+/// Fills in a template for a notice. Dates are shown in the library's
+/// timezone.
+pub fn render(text: &str, notice: &Notice, library_name: &str, tz: Tz) -> String {
+    let due_date = notice
+        .due_date
+        .with_timezone(&tz)
+        .format("%A %-d %B %Y")
+        .to_string();
+
+    text.replace("{student}", &notice.student_name)
+        .replace("{book}", &notice.book_title)
+        .replace("{due_date}", &due_date)
+        .replace("{days_overdue}", &notice.days_overdue.to_string())
+        .replace("{library}", library_name)
+}
+
+struct LoanContact {
+    id: String,
+    student_id: String,
+    student_name: String,
+    book_title: String,
+    due_date: DateTime<Utc>,
+    email: Option<String>,
+    guardian_email: Option<String>,
+    phone_number: Option<String>,
+    sms_opt_out: bool,
+}
+
+impl LoanContact {
+    fn into_notice(self, kind: NoticeKind, days_overdue: i64) -> Notice {
+        Notice {
+            lending_id: self.id,
+            kind,
+            student_id: self.student_id,
+            student_name: self.student_name,
+            book_title: self.book_title,
+            due_date: self.due_date,
+            days_overdue,
+            email: self.email,
+            guardian_email: self.guardian_email,
+            phone_number: self.phone_number,
+            sms_opt_out: self.sms_opt_out,
+        }
+    }
+}
+
+/// Overdue loans (as reported by `get_overdue_books`) and loans due within
+/// `due_soon_days`, whether or not a reminder has gone out already.
+pub async fn pending_notices(
+    pool: &Pool<Sqlite>,
+    settings: &Settings,
+) -> Result<Vec<Notice>, sqlx::Error> {
+    let clock = LibraryClock::load(pool).await?;
+
+    let mut loans: HashMap<String, LoanContact> = sqlx::query_as!(
+        LoanContact,
+        r#"
+        SELECT
+            l.id as "id!: String",
+            s.id as "student_id!: String",
+            COALESCE(s.name, '') as "student_name!: String",
+            COALESCE(b.title, '') as "book_title!: String",
+            l.due_date as "due_date!: DateTime<Utc>",
+            s.email as "email?: String",
+            s.guardian_email as "guardian_email?: String",
+            s.phone_number as "phone_number?: String",
+            s.sms_opt_out as "sms_opt_out!: bool"
+        FROM lent l
+        JOIN books b ON l.book_id = b.id
+        JOIN students s ON l.student_id = s.id
+        WHERE l.status = 'lent'
+        "#
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|loan| (loan.id.clone(), loan))
+    .collect();
+
+    let filter = StatsFilter {
+        limit: Some(i64::MAX),
+        ..Default::default()
+    };
+    let mut notices = Vec::new();
+    for overdue in statistics::get_overdue_books(pool, &filter).await? {
+        if let Some(loan) = loans.remove(&overdue.id) {
+            notices.push(loan.into_notice(NoticeKind::Overdue, overdue.days_overdue));
+        }
+    }
+
+    let cutoff = clock.overdue_cutoff();
+    let due_soon_until =
+        clock.end_of_day(clock.today() + Duration::days(settings.due_soon_days));
+    notices.extend(
+        loans
+            .into_values()
+            .filter(|loan| loan.due_date >= cutoff && loan.due_date <= due_soon_until)
+            .map(|loan| loan.into_notice(NoticeKind::DueSoon, 0)),
+    );
+
+    notices.sort_by_key(|notice| notice.due_date);
+    Ok(notices)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NotificationRecord {
+    pub id: String,
+    pub lending_id: String,
+    pub channel: String,
+    pub kind: String,
+    pub recipient: String,
+    pub due_date: DateTime<Utc>,
+    pub success: bool,
+    pub message: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Outcome of one pass over the pending notices.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct NotificationRun {
+    pub sent: usize,
+    /// Already sent on an earlier run.
+    pub skipped: usize,
+    pub failed: usize,
+    /// Recipients whose number or address can't be used, not attempted.
+    #[serde(default)]
+    pub invalid: usize,
+}
+
+/// Whether this reminder already reached `recipient`. Failed attempts don't
+/// count, so they are retried on the next run.
+pub async fn was_sent(
+    pool: &Pool<Sqlite>,
+    notice: &Notice,
+    channel: &str,
+    recipient: &str,
+) -> Result<bool, sqlx::Error> {
+    let kind = notice.kind.to_string();
+    let count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!: i64"
+        FROM notification_log
+        WHERE lending_id = ? AND channel = ? AND kind = ? AND recipient = ?
+          AND due_date = ? AND success = 1
+        "#,
+        notice.lending_id,
+        channel,
+        kind,
+        recipient,
+        notice.due_date
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count > 0)
+}
+
+pub async fn record_notification(
+    pool: &Pool<Sqlite>,
+    notice: &Notice,
+    channel: &str,
+    recipient: &str,
+    result: &Result<(), String>,
+) -> Result<(), sqlx::Error> {
+    let id = Uuid::new_v4().to_string();
+    let kind = notice.kind.to_string();
+    let success = result.is_ok();
+    let message = result.as_ref().err().cloned();
+    let created_at = Utc::now();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO notification_log
+            (id, lending_id, channel, kind, recipient, due_date, success, message, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+        id,
+        notice.lending_id,
+        channel,
+        kind,
+        recipient,
+        notice.due_date,
+        success,
+        message,
+        created_at
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_notification_log(
+    pool: &Pool<Sqlite>,
+    limit: i64,
+) -> Result<Vec<NotificationRecord>, sqlx::Error> {
+    sqlx::query_as!(
+        NotificationRecord,
+        r#"
+        SELECT
+            id as "id!: String",
+            lending_id as "lending_id!: String",
+            channel as "channel!: String",
+            kind as "kind!: String",
+            recipient as "recipient!: String",
+            due_date as "due_date!: DateTime<Utc>",
+            success as "success!: bool",
+            message as "message?: String",
+            created_at as "created_at!: DateTime<Utc>"
+        FROM notification_log
+        ORDER BY created_at DESC
+        LIMIT ?
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Emails every pending reminder that hasn't been sent yet, to the student
+/// and guardian addresses on file, and logs each attempt.
+pub async fn send_email_notices(
+    pool: &Pool<Sqlite>,
+    mailer: &Mailer,
+) -> Result<NotificationRun, sqlx::Error> {
+    let settings = settings::get_settings(pool).await?;
+    let tz = settings.tz();
+    let mut run = NotificationRun::default();
+
+    for notice in pending_notices(pool, &settings).await? {
+        let template = template(notice.kind);
+        for recipient in notice.email_recipients() {
+            if was_sent(pool, &notice, EMAIL_CHANNEL, recipient).await? {
+                run.skipped += 1;
+                continue;
+            }
+
+            let subject = render(template.subject, &notice, &settings.library_name, tz);
+            let body = render(template.body, &notice, &settings.library_name, tz);
+            let result = mailer
+                .send(recipient, &subject, &body)
+                .await
+                .map_err(|e| e.to_string());
+
+            if result.is_ok() {
+                run.sent += 1;
+            } else {
+                run.failed += 1;
+            }
+            record_notification(pool, &notice, EMAIL_CHANNEL, recipient, &result).await?;
+        }
+    }
+
+    Ok(run)
+}
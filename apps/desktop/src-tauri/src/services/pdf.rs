@@ -0,0 +1,241 @@
+use crate::models::LendingStatus;
+use crate::services::{lendings, statistics, students};
+use chrono::{DateTime, Utc};
+use printpdf::{
+    BuiltinFont, IndirectFontRef, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference,
+};
+use sqlx::{Pool, Sqlite};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+const LINE_HEIGHT: f32 = 6.0;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReportError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Failed to render PDF: {0}")]
+    Pdf(#[from] printpdf::Error),
+    #[error("Failed to write PDF: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Student {0} not found")]
+    StudentNotFound(String),
+}
+
+/// Minimal top-to-bottom text layout over printpdf that starts a new page
+/// whenever the cursor runs into the bottom margin.
+struct ReportWriter {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    y: f32,
+}
+
+impl ReportWriter {
+    fn new(title: &str) -> Result<Self, ReportError> {
+        let (doc, page, layer) =
+            PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        let regular = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+        let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+        let layer = doc.get_page(page).get_layer(layer);
+
+        Ok(Self {
+            doc,
+            layer,
+            regular,
+            bold,
+            y: PAGE_HEIGHT - MARGIN,
+        })
+    }
+
+    fn page_break(&mut self) {
+        let (page, layer) = self
+            .doc
+            .add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn ensure_space(&mut self, lines: usize) {
+        if self.y - LINE_HEIGHT * lines as f32 < MARGIN {
+            self.page_break();
+        }
+    }
+
+    fn text(&mut self, text: &str, size: f32, bold: bool, indent: f32) {
+        self.ensure_space(1);
+        let font = if bold { &self.bold } else { &self.regular };
+        self.layer
+            .use_text(text, size, Mm(MARGIN + indent), Mm(self.y), font);
+        self.y -= LINE_HEIGHT * (size / 10.0).max(1.0);
+    }
+
+    fn title(&mut self, text: &str) {
+        self.text(text, 18.0, true, 0.0);
+        self.gap();
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.text(text, 13.0, true, 0.0);
+    }
+
+    fn line(&mut self, text: &str) {
+        self.text(text, 10.0, false, 0.0);
+    }
+
+    fn item(&mut self, text: &str) {
+        self.text(text, 10.0, false, 6.0);
+    }
+
+    fn gap(&mut self) {
+        self.y -= LINE_HEIGHT / 2.0;
+    }
+
+    fn save(self, path: &Path) -> Result<(), ReportError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.doc.save(&mut writer)?;
+        Ok(())
+    }
+}
+
+fn format_date(date: &DateTime<Utc>) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+/// One notice per student with overdue books, grouped by grade so each
+/// homeroom teacher's slips come out together.
+pub async fn render_overdue_notices(pool: &Pool<Sqlite>, path: &Path) -> Result<(), ReportError> {
+    let overdue = statistics::get_overdue_books(pool, &statistics::StatsFilter::default()).await?;
+
+    let mut by_grade: BTreeMap<&str, BTreeMap<(&str, &str), Vec<&statistics::OverdueBook>>> =
+        BTreeMap::new();
+    for book in &overdue {
+        by_grade
+            .entry(book.grade.as_str())
+            .or_default()
+            .entry((book.student_name.as_str(), book.student_id.as_str()))
+            .or_default()
+            .push(book);
+    }
+
+    let mut report = ReportWriter::new("Overdue Notices")?;
+    let generated = format_date(&Utc::now());
+
+    if by_grade.is_empty() {
+        report.title("Overdue Notices");
+        report.line(&format!("No overdue books as of {}.", generated));
+    }
+
+    for (index, (grade, notices)) in by_grade.iter().enumerate() {
+        if index > 0 {
+            report.page_break();
+        }
+        report.title(&format!("Overdue Notices - {}", grade));
+        report.line(&format!("Generated {}", generated));
+        report.gap();
+
+        for ((student_name, student_id), books) in notices {
+            report.ensure_space(books.len() + 3);
+            report.heading(&format!("{} ({})", student_name, student_id));
+            report.line("The following books are overdue. Please return them to the library.");
+            for book in books {
+                report.item(&format!(
+                    "{} by {} - due {}, {} day(s) overdue",
+                    book.book_title,
+                    book.author,
+                    format_date(&book.due_date),
+                    book.days_overdue
+                ));
+            }
+            report.gap();
+        }
+    }
+
+    report.save(path)
+}
+
+pub async fn render_circulation_summary(
+    pool: &Pool<Sqlite>,
+    path: &Path,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<(), ReportError> {
+    let summary = statistics::get_circulation_summary(pool, start, end).await?;
+
+    let mut report = ReportWriter::new("Circulation Summary")?;
+    report.title(&format!("Circulation Summary - {}", start.format("%B %Y")));
+    report.line(&format!(
+        "Period {} to {}",
+        format_date(&summary.period_start),
+        format_date(&summary.period_end)
+    ));
+    report.gap();
+
+    report.heading("Activity");
+    report.item(&format!("Books lent: {}", summary.loans));
+    report.item(&format!("Books returned: {}", summary.returns));
+    report.item(&format!("Returned late: {}", summary.late_returns));
+    report.item(&format!("Students borrowing: {}", summary.active_borrowers));
+    report.item(&format!("New students: {}", summary.new_students));
+    report.item(&format!("New books: {}", summary.new_books));
+    report.gap();
+
+    report.heading("Most Popular Books");
+    if summary.popular_books.is_empty() {
+        report.item("No books were lent in this period.");
+    }
+    for (rank, book) in summary.popular_books.iter().enumerate() {
+        report.item(&format!(
+            "{}. {} by {} ({}) - {} loan(s)",
+            rank + 1,
+            book.title,
+            book.author,
+            book.category,
+            book.times_loaned
+        ));
+    }
+
+    report.save(path)
+}
+
+pub async fn render_student_history(
+    pool: &Pool<Sqlite>,
+    path: &Path,
+    student_id: &str,
+) -> Result<(), ReportError> {
+    let student = students::get_student_by_id(pool, student_id)
+        .await?
+        .ok_or_else(|| ReportError::StudentNotFound(student_id.to_string()))?;
+    let records = lendings::get_lending_records_by_student_id(pool, student_id).await?;
+
+    let mut report = ReportWriter::new("Loan History")?;
+    report.title(&format!("Loan History - {}", student.name));
+    report.line(&format!("Student ID: {}", student.student_id));
+    report.line(&format!("Grade: {}", student.grade));
+    report.line(&format!("Total loans: {}", records.len()));
+    report.gap();
+
+    if records.is_empty() {
+        report.line("This student has not borrowed any books.");
+    }
+    for record in &records {
+        let returned = match (&record.status, &record.returned_at) {
+            (LendingStatus::Returned, Some(returned_at)) => {
+                format!("returned {}", format_date(returned_at))
+            }
+            (LendingStatus::Returned, None) => "returned".to_string(),
+            (LendingStatus::Lent, _) => format!("due {}", format_date(&record.due_date)),
+        };
+        report.ensure_space(2);
+        report.heading(&format!("{} by {}", record.book_title, record.book_author));
+        report.item(&format!("Lent {}, {}", format_date(&record.lent_at), returned));
+    }
+
+    report.save(path)
+}
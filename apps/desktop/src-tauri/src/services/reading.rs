@@ -0,0 +1,204 @@
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A named school term. Terms are supplied by the caller because the school
+/// calendar is not stored in the database.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Term {
+    pub name: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TermCount {
+    pub name: String,
+    pub books_read: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategoryCount {
+    pub name: String,
+    pub books_read: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadingProfile {
+    pub student_id: String,
+    pub books_read: i64,
+    pub current_loans: i64,
+    pub books_per_term: Vec<TermCount>,
+    pub categories: Vec<CategoryCount>,
+    pub average_loan_days: f64,
+    pub on_time_rate: f64,
+    pub longest_streak_weeks: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub id: String,
+    pub name: String,
+    pub student_number: String,
+    pub books_read: i64,
+    pub on_time_returns: i64,
+}
+
+struct LoanRow {
+    lent_at: DateTime<Utc>,
+    returned_at: Option<DateTime<Utc>>,
+    due_date: DateTime<Utc>,
+    category: String,
+}
+
+/// Counts the longest run of consecutive ISO weeks with at least one loan.
+fn longest_streak(weeks: &BTreeSet<NaiveDate>) -> i64 {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous: Option<NaiveDate> = None;
+
+    for week in weeks {
+        current = match previous {
+            Some(prev) if (*week - prev).num_days() == 7 => current + 1,
+            _ => 1,
+        };
+        longest = longest.max(current);
+        previous = Some(*week);
+    }
+
+    longest
+}
+
+/// A loan counts as read once it has been returned.
+pub async fn get_reading_profile(
+    pool: &Pool<Sqlite>,
+    student_id: &str,
+    terms: &[Term],
+) -> Result<ReadingProfile, sqlx::Error> {
+    let loans = sqlx::query_as!(
+        LoanRow,
+        r#"
+        SELECT
+            l.lent_at as "lent_at!: chrono::DateTime<chrono::Utc>",
+            l.returned_at as "returned_at?: chrono::DateTime<chrono::Utc>",
+            l.due_date as "due_date!: chrono::DateTime<chrono::Utc>",
+            COALESCE(b.category, '') as "category!: String"
+        FROM lent l
+        LEFT JOIN books b ON l.book_id = b.id
+        WHERE l.student_id = ?
+        ORDER BY l.lent_at ASC
+        "#,
+        student_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let returned: Vec<(&LoanRow, DateTime<Utc>)> = loans
+        .iter()
+        .filter_map(|loan| loan.returned_at.map(|at| (loan, at)))
+        .collect();
+
+    let books_read = returned.len() as i64;
+    let current_loans = loans.len() as i64 - books_read;
+
+    let books_per_term = terms
+        .iter()
+        .map(|term| TermCount {
+            name: term.name.clone(),
+            books_read: returned
+                .iter()
+                .filter(|(_, at)| *at >= term.start && *at < term.end)
+                .count() as i64,
+        })
+        .collect();
+
+    let mut by_category: BTreeMap<String, i64> = BTreeMap::new();
+    for (loan, _) in &returned {
+        let name = if loan.category.is_empty() {
+            "Uncategorized".to_string()
+        } else {
+            loan.category.clone()
+        };
+        *by_category.entry(name).or_default() += 1;
+    }
+    let mut categories: Vec<CategoryCount> = by_category
+        .into_iter()
+        .map(|(name, books_read)| CategoryCount { name, books_read })
+        .collect();
+    categories.sort_by(|a, b| b.books_read.cmp(&a.books_read));
+
+    let (average_loan_days, on_time_rate) = if books_read > 0 {
+        let total_seconds: i64 = returned
+            .iter()
+            .map(|(loan, at)| (*at - loan.lent_at).num_seconds())
+            .sum();
+        let on_time = returned
+            .iter()
+            .filter(|(loan, at)| *at <= loan.due_date)
+            .count();
+        (
+            total_seconds as f64 / 86_400.0 / books_read as f64,
+            on_time as f64 / books_read as f64,
+        )
+    } else {
+        (0.0, 0.0)
+    };
+
+    let weeks: BTreeSet<NaiveDate> = loans
+        .iter()
+        .map(|loan| {
+            let week = loan.lent_at.iso_week();
+            NaiveDate::from_isoywd_opt(week.year(), week.week(), chrono::Weekday::Mon)
+                .unwrap_or_else(|| loan.lent_at.date_naive())
+        })
+        .collect();
+
+    Ok(ReadingProfile {
+        student_id: student_id.to_string(),
+        books_read,
+        current_loans,
+        books_per_term,
+        categories,
+        average_loan_days,
+        on_time_rate,
+        longest_streak_weeks: longest_streak(&weeks),
+    })
+}
+
+pub async fn get_grade_leaderboard(
+    pool: &Pool<Sqlite>,
+    grade: &str,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    limit: i64,
+) -> Result<Vec<LeaderboardEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        LeaderboardEntry,
+        r#"
+        SELECT
+            COALESCE(s.id, '') as "id!: String",
+            COALESCE(s.name, '') as "name!: String",
+            COALESCE(s.student_id, '') as "student_number!: String",
+            COUNT(l.id) as "books_read!: i64",
+            COALESCE(SUM(CASE WHEN l.returned_at <= l.due_date THEN 1 ELSE 0 END), 0) as "on_time_returns!: i64"
+        FROM students s
+        JOIN lent l ON l.student_id = s.id AND l.status = 'returned'
+        WHERE s.grade = ?
+            AND s.status = 'active'
+            AND (? IS NULL OR l.returned_at >= ?)
+            AND (? IS NULL OR l.returned_at < ?)
+        GROUP BY s.id
+        ORDER BY COUNT(l.id) DESC, s.name ASC
+        LIMIT ?
+        "#,
+        grade,
+        from,
+        from,
+        to,
+        to,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+
+/// Maps a current grade onto the grade it becomes next year.
+/// A `None` target means students in that grade graduate and leave.
+pub type GradeProgression = HashMap<String, Option<String>>;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RolloverEntry {
+    id: String,
+    name: String,
+    student_id: String,
+    from_grade: String,
+    to_grade: Option<String>,
+    active_loans: i64,
+    flagged: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RolloverPreview {
+    promotions: Vec<RolloverEntry>,
+    graduates: Vec<RolloverEntry>,
+    unchanged: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RolloverResult {
+    promoted: i64,
+    graduated: i64,
+}
+
+struct ActiveStudent {
+    id: String,
+    name: String,
+    student_id: String,
+    grade: String,
+    active_loans: i64,
+}
+
+async fn get_active_students(pool: &Pool<Sqlite>) -> Result<Vec<ActiveStudent>, sqlx::Error> {
+    sqlx::query_as!(
+        ActiveStudent,
+        r#"
+        SELECT
+            COALESCE(s.id, '') as id,
+            COALESCE(s.name, '') as name,
+            COALESCE(s.student_id, '') as student_id,
+            COALESCE(s.grade, '') as grade,
+            (SELECT COUNT(*) FROM lent l WHERE l.student_id = s.id AND l.status = 'lent') as "active_loans!: i64"
+        FROM students s
+        WHERE s.status = 'active'
+        ORDER BY s.grade, s.name
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn preview_grade_rollover(
+    pool: &Pool<Sqlite>,
+    progression: &GradeProgression,
+) -> Result<RolloverPreview, sqlx::Error> {
+    let students = get_active_students(pool).await?;
+
+    let mut promotions = Vec::new();
+    let mut graduates = Vec::new();
+    let mut unchanged = 0;
+
+    for student in students {
+        let Some(target) = progression.get(student.grade.trim()) else {
+            unchanged += 1;
+            continue;
+        };
+
+        let entry = RolloverEntry {
+            flagged: target.is_none() && student.active_loans > 0,
+            id: student.id,
+            name: student.name,
+            student_id: student.student_id,
+            from_grade: student.grade,
+            to_grade: target.clone(),
+            active_loans: student.active_loans,
+        };
+
+        if target.is_some() {
+            promotions.push(entry);
+        } else {
+            graduates.push(entry);
+        }
+    }
+
+    Ok(RolloverPreview {
+        promotions,
+        graduates,
+        unchanged,
+    })
+}
+
+pub async fn apply_grade_rollover(
+    pool: &Pool<Sqlite>,
+    progression: &GradeProgression,
+) -> Result<RolloverResult, sqlx::Error> {
+    // Students are read up front and updated by id, so "Grade 6" -> "Grade 7"
+    // and "Grade 7" -> "Grade 8" never chain within a single rollover.
+    let students = get_active_students(pool).await?;
+
+    let mut promoted = 0;
+    let mut graduated = 0;
+
+    let mut tx = pool.begin().await?;
+
+    for student in &students {
+        match progression.get(student.grade.trim()) {
+            Some(Some(next_grade)) => {
+                sqlx::query!(
+                    r#"UPDATE students SET grade = ? WHERE id = ?"#,
+                    next_grade,
+                    student.id
+                )
+                .execute(&mut *tx)
+                .await?;
+                promoted += 1;
+            }
+            Some(None) => {
+                sqlx::query!(
+                    r#"UPDATE students SET status = 'inactive' WHERE id = ?"#,
+                    student.id
+                )
+                .execute(&mut *tx)
+                .await?;
+                graduated += 1;
+            }
+            None => {}
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(RolloverResult {
+        promoted,
+        graduated,
+    })
+}
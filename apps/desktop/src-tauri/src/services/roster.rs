@@ -0,0 +1,352 @@
+use calamine::{open_workbook_auto, Reader};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use uuid::Uuid;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RosterError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Failed to read CSV roster: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("Failed to read spreadsheet roster: {0}")]
+    Spreadsheet(#[from] calamine::Error),
+    #[error("Unsupported roster file type: {0}")]
+    UnsupportedFormat(String),
+    #[error("Roster is missing column '{0}'")]
+    MissingColumn(String),
+    #[error("Row {row}: {message}")]
+    InvalidRow { row: usize, message: String },
+}
+
+/// Header names in the school office export for each `Student` field.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    pub student_id: String,
+    pub name: String,
+    pub grade: String,
+    pub phone_number: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RosterRow {
+    student_id: String,
+    name: String,
+    grade: String,
+    phone_number: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RosterUpdate {
+    id: String,
+    student_id: String,
+    name: String,
+    old_grade: String,
+    new_grade: String,
+    old_phone_number: Option<String>,
+    new_phone_number: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RosterDeactivation {
+    id: String,
+    student_id: String,
+    name: String,
+    grade: String,
+    active_loans: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RosterDiff {
+    created: Vec<RosterRow>,
+    updated: Vec<RosterUpdate>,
+    /// Inactive students who are back on the roster. Their grade and phone
+    /// number are updated as well.
+    reactivated: Vec<RosterUpdate>,
+    deactivated: Vec<RosterDeactivation>,
+    unchanged: i64,
+}
+
+struct ExistingStudent {
+    id: String,
+    name: String,
+    grade: String,
+    phone_number: Option<String>,
+    student_id: String,
+    status: String,
+    active_loans: i64,
+}
+
+fn read_table(path: &Path) -> Result<Vec<Vec<String>>, RosterError> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    match extension.as_str() {
+        "csv" => {
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .trim(csv::Trim::All)
+                .flexible(true)
+                .from_path(path)?;
+
+            let mut rows = Vec::new();
+            for record in reader.records() {
+                rows.push(record?.iter().map(str::to_string).collect());
+            }
+            Ok(rows)
+        }
+        "xlsx" | "xls" | "ods" => {
+            let mut workbook = open_workbook_auto(path)?;
+            let range = workbook
+                .worksheet_range_at(0)
+                .ok_or_else(|| RosterError::UnsupportedFormat("workbook has no sheets".into()))??;
+
+            Ok(range
+                .rows()
+                .map(|row| row.iter().map(|cell| cell.to_string().trim().to_string()).collect())
+                .collect())
+        }
+        other => Err(RosterError::UnsupportedFormat(other.to_string())),
+    }
+}
+
+pub fn parse_roster(path: &Path, mapping: &ColumnMapping) -> Result<Vec<RosterRow>, RosterError> {
+    let mut table = read_table(path)?.into_iter();
+    let headers = table.next().unwrap_or_default();
+
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h.eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| RosterError::MissingColumn(name.to_string()))
+    };
+
+    let student_id_col = column(&mapping.student_id)?;
+    let name_col = column(&mapping.name)?;
+    let grade_col = column(&mapping.grade)?;
+    let phone_col = mapping.phone_number.as_deref().map(column).transpose()?;
+
+    let mut seen = HashSet::new();
+    let mut rows = Vec::new();
+
+    // Row numbers are 1-based and count the header, matching what the
+    // librarian sees in a spreadsheet.
+    for (index, record) in table.enumerate() {
+        let row = index + 2;
+        let cell = |col: usize| record.get(col).cloned().unwrap_or_default();
+
+        if record.iter().all(|c| c.is_empty()) {
+            continue;
+        }
+
+        let student_id = cell(student_id_col);
+        let name = cell(name_col);
+        let grade = cell(grade_col);
+        let phone_number = phone_col.map(cell).filter(|p| !p.is_empty());
+
+        for (field, value) in [("student ID", &student_id), ("name", &name), ("grade", &grade)] {
+            if value.is_empty() {
+                return Err(RosterError::InvalidRow {
+                    row,
+                    message: format!("missing {}", field),
+                });
+            }
+        }
+
+        if !seen.insert(student_id.clone()) {
+            return Err(RosterError::InvalidRow {
+                row,
+                message: format!("duplicate student ID {}", student_id),
+            });
+        }
+
+        rows.push(RosterRow {
+            student_id,
+            name,
+            grade,
+            phone_number,
+        });
+    }
+
+    Ok(rows)
+}
+
+async fn get_existing_students(
+    pool: &Pool<Sqlite>,
+) -> Result<HashMap<String, ExistingStudent>, sqlx::Error> {
+    let students = sqlx::query_as!(
+        ExistingStudent,
+        r#"
+        SELECT
+            COALESCE(s.id, '') as id,
+            COALESCE(s.name, '') as name,
+            COALESCE(s.grade, '') as grade,
+            s.phone_number as "phone_number?: String",
+            COALESCE(s.student_id, '') as student_id,
+            COALESCE(s.status, '') as status,
+            (SELECT COUNT(*) FROM lent l WHERE l.student_id = s.id AND l.status = 'lent') as "active_loans!: i64"
+        FROM students s
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(students
+        .into_iter()
+        .map(|s| (s.student_id.clone(), s))
+        .collect())
+}
+
+async fn diff_roster(
+    pool: &Pool<Sqlite>,
+    rows: &[RosterRow],
+    deactivate_missing: bool,
+) -> Result<RosterDiff, sqlx::Error> {
+    let existing = get_existing_students(pool).await?;
+
+    let mut created = Vec::new();
+    let mut updated = Vec::new();
+    let mut reactivated = Vec::new();
+    let mut unchanged = 0;
+
+    for row in rows {
+        match existing.get(&row.student_id) {
+            None => created.push(row.clone()),
+            Some(student) => {
+                // A roster without a phone column leaves stored numbers alone.
+                let new_phone_number = row
+                    .phone_number
+                    .clone()
+                    .or_else(|| student.phone_number.clone());
+
+                let update = RosterUpdate {
+                    id: student.id.clone(),
+                    student_id: student.student_id.clone(),
+                    name: student.name.clone(),
+                    old_grade: student.grade.clone(),
+                    new_grade: row.grade.clone(),
+                    old_phone_number: student.phone_number.clone(),
+                    new_phone_number,
+                };
+
+                if student.status != "active" {
+                    reactivated.push(update);
+                } else if update.old_grade != update.new_grade
+                    || update.old_phone_number != update.new_phone_number
+                {
+                    updated.push(update);
+                } else {
+                    unchanged += 1;
+                }
+            }
+        }
+    }
+
+    let mut deactivated = Vec::new();
+    if deactivate_missing {
+        let on_roster: HashSet<&str> = rows.iter().map(|r| r.student_id.as_str()).collect();
+        deactivated = existing
+            .values()
+            .filter(|s| s.status == "active" && !on_roster.contains(s.student_id.as_str()))
+            .map(|s| RosterDeactivation {
+                id: s.id.clone(),
+                student_id: s.student_id.clone(),
+                name: s.name.clone(),
+                grade: s.grade.clone(),
+                active_loans: s.active_loans,
+            })
+            .collect();
+        deactivated.sort_by(|a, b| a.grade.cmp(&b.grade).then(a.name.cmp(&b.name)));
+    }
+
+    Ok(RosterDiff {
+        created,
+        updated,
+        reactivated,
+        deactivated,
+        unchanged,
+    })
+}
+
+pub async fn preview_roster_import(
+    pool: &Pool<Sqlite>,
+    path: &Path,
+    mapping: &ColumnMapping,
+    deactivate_missing: bool,
+) -> Result<RosterDiff, RosterError> {
+    let rows = parse_roster(path, mapping)?;
+    Ok(diff_roster(pool, &rows, deactivate_missing).await?)
+}
+
+pub async fn apply_roster_import(
+    pool: &Pool<Sqlite>,
+    path: &Path,
+    mapping: &ColumnMapping,
+    deactivate_missing: bool,
+) -> Result<RosterDiff, RosterError> {
+    let rows = parse_roster(path, mapping)?;
+    let diff = diff_roster(pool, &rows, deactivate_missing).await?;
+    let created_at = Utc::now();
+
+    let mut tx = pool.begin().await?;
+
+    for row in &diff.created {
+        let id = Uuid::new_v4().to_string();
+        sqlx::query!(
+            r#"
+            INSERT INTO students (id, name, grade, phone_number, student_id, status, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+            id,
+            row.name,
+            row.grade,
+            row.phone_number,
+            row.student_id,
+            "active",
+            created_at
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for update in &diff.updated {
+        sqlx::query!(
+            r#"UPDATE students SET grade = ?, phone_number = ? WHERE id = ?"#,
+            update.new_grade,
+            update.new_phone_number,
+            update.id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for update in &diff.reactivated {
+        sqlx::query!(
+            r#"UPDATE students SET grade = ?, phone_number = ?, status = 'active' WHERE id = ?"#,
+            update.new_grade,
+            update.new_phone_number,
+            update.id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for student in &diff.deactivated {
+        sqlx::query!(
+            r#"UPDATE students SET status = 'inactive' WHERE id = ?"#,
+            student.id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(diff)
+}
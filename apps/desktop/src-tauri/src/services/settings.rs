@@ -0,0 +1,578 @@
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SettingsError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Invalid setting {field}: {message}")]
+    Invalid {
+        field: &'static str,
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoBackup {
+    Off,
+    Daily,
+    Weekly,
+}
+
+impl std::fmt::Display for AutoBackup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AutoBackup::Off => write!(f, "off"),
+            AutoBackup::Daily => write!(f, "daily"),
+            AutoBackup::Weekly => write!(f, "weekly"),
+        }
+    }
+}
+
+impl std::str::FromStr for AutoBackup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(AutoBackup::Off),
+            "daily" => Ok(AutoBackup::Daily),
+            "weekly" => Ok(AutoBackup::Weekly),
+            _ => Err(format!("Invalid backup schedule: {}", s)),
+        }
+    }
+}
+
+/// Which interfaces the local HTTP API listens on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiBind {
+    Localhost,
+    Lan,
+}
+
+impl std::fmt::Display for ApiBind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiBind::Localhost => write!(f, "localhost"),
+            ApiBind::Lan => write!(f, "lan"),
+        }
+    }
+}
+
+impl std::str::FromStr for ApiBind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "localhost" => Ok(ApiBind::Localhost),
+            "lan" => Ok(ApiBind::Lan),
+            _ => Err(format!("Invalid API bind address: {}", s)),
+        }
+    }
+}
+
+/// How the SMTP connection is secured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Plain text, only sensible for a relay on the same machine or network.
+    None,
+    StartTls,
+    Tls,
+}
+
+impl std::fmt::Display for SmtpSecurity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SmtpSecurity::None => write!(f, "none"),
+            SmtpSecurity::StartTls => write!(f, "starttls"),
+            SmtpSecurity::Tls => write!(f, "tls"),
+        }
+    }
+}
+
+impl std::str::FromStr for SmtpSecurity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(SmtpSecurity::None),
+            "starttls" => Ok(SmtpSecurity::StartTls),
+            "tls" => Ok(SmtpSecurity::Tls),
+            _ => Err(format!("Invalid SMTP security: {}", s)),
+        }
+    }
+}
+
+/// Where SMS reminders go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmsGatewayKind {
+    /// Appended to `sms_outbox_path`, or printed when that is empty. For
+    /// trying reminders out without sending anything.
+    File,
+    Http,
+}
+
+impl std::fmt::Display for SmsGatewayKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SmsGatewayKind::File => write!(f, "file"),
+            SmsGatewayKind::Http => write!(f, "http"),
+        }
+    }
+}
+
+impl std::str::FromStr for SmsGatewayKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "file" => Ok(SmsGatewayKind::File),
+            "http" => Ok(SmsGatewayKind::Http),
+            _ => Err(format!("Invalid SMS gateway: {}", s)),
+        }
+    }
+}
+
+/// Library-wide configuration, stored one row per field in the `settings`
+/// table. Missing or unparseable rows fall back to the defaults below, which
+/// match the values seeded by the migrations.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    pub library_name: String,
+    /// IANA timezone name, e.g. "Africa/Kigali".
+    pub timezone: String,
+    pub loan_period_days: i64,
+    pub max_loans_per_student: i64,
+    /// How many times a loan may be renewed before it has to come back.
+    pub max_renewals: i64,
+    pub auto_backup: AutoBackup,
+    /// Where scheduled backups go. Empty means `backups/` in the app data dir.
+    pub backup_directory: String,
+    /// The HTTP API is off unless switched on here.
+    pub api_enabled: bool,
+    pub api_bind: ApiBind,
+    pub api_port: i64,
+    /// Whether a bearer token for API clients is set; see [`Secret::ApiToken`].
+    #[serde(default)]
+    pub api_token_set: bool,
+    /// A kiosk session ends after this long without activity.
+    pub kiosk_timeout_seconds: i64,
+    /// Send due-soon and overdue reminders by email on a schedule.
+    pub email_notifications: bool,
+    /// How many days before the due date the due-soon reminder goes out.
+    pub due_soon_days: i64,
+    pub smtp_host: String,
+    pub smtp_port: i64,
+    pub smtp_security: SmtpSecurity,
+    pub smtp_username: String,
+    /// Whether an SMTP password is set; see [`Secret::SmtpPassword`].
+    #[serde(default)]
+    pub smtp_password_set: bool,
+    /// Sender address, e.g. "School Library <library@example.org>".
+    pub smtp_from: String,
+    /// Send due-soon and overdue reminders by SMS on a schedule.
+    pub sms_notifications: bool,
+    pub sms_gateway: SmsGatewayKind,
+    /// Calling code (digits only) for numbers stored without one, e.g. "250".
+    pub sms_country_code: String,
+    /// Gateway URL; `{to}` and `{message}` are replaced, URL-encoded.
+    pub sms_http_url: String,
+    /// Request body; empty sends a GET. See `services::sms::HttpGateway`.
+    pub sms_http_body: String,
+    /// Whether an Authorization header for the gateway is set; see
+    /// [`Secret::SmsHttpAuthorization`].
+    #[serde(default)]
+    pub sms_http_authorization_set: bool,
+    /// File the `file` gateway appends to. Empty prints to the console.
+    pub sms_outbox_path: String,
+    /// Show the morning summary of due, overdue and backup status.
+    pub daily_digest: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            library_name: "Tiza Library".to_string(),
+            timezone: "UTC".to_string(),
+            loan_period_days: 14,
+            max_loans_per_student: 1,
+            max_renewals: 1,
+            auto_backup: AutoBackup::Off,
+            backup_directory: String::new(),
+            api_enabled: false,
+            api_bind: ApiBind::Localhost,
+            api_port: 8787,
+            api_token_set: false,
+            kiosk_timeout_seconds: 90,
+            email_notifications: false,
+            due_soon_days: 2,
+            smtp_host: String::new(),
+            smtp_port: 587,
+            smtp_security: SmtpSecurity::StartTls,
+            smtp_username: String::new(),
+            smtp_password_set: false,
+            smtp_from: String::new(),
+            sms_notifications: false,
+            sms_gateway: SmsGatewayKind::File,
+            sms_country_code: "250".to_string(),
+            sms_http_url: String::new(),
+            sms_http_body: String::new(),
+            sms_http_authorization_set: false,
+            sms_outbox_path: String::new(),
+            daily_digest: true,
+        }
+    }
+}
+
+impl Settings {
+    fn from_rows(rows: HashMap<String, String>) -> Self {
+        let defaults = Self::default();
+        let number = |key: &str, default: i64| {
+            rows.get(key)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        let is_set = |secret: Secret| rows.get(secret.key()).is_some_and(|v| !v.is_empty());
+
+        Self {
+            library_name: rows
+                .get("library_name")
+                .cloned()
+                .unwrap_or(defaults.library_name),
+            timezone: rows
+                .get("timezone")
+                .cloned()
+                .unwrap_or(defaults.timezone),
+            loan_period_days: number("loan_period_days", defaults.loan_period_days),
+            max_loans_per_student: number("max_loans_per_student", defaults.max_loans_per_student),
+            max_renewals: number("max_renewals", defaults.max_renewals),
+            auto_backup: rows
+                .get("auto_backup")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.auto_backup),
+            backup_directory: rows
+                .get("backup_directory")
+                .cloned()
+                .unwrap_or(defaults.backup_directory),
+            api_enabled: rows
+                .get("api_enabled")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.api_enabled),
+            api_bind: rows
+                .get("api_bind")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.api_bind),
+            api_port: number("api_port", defaults.api_port),
+            api_token_set: is_set(Secret::ApiToken),
+            kiosk_timeout_seconds: number("kiosk_timeout_seconds", defaults.kiosk_timeout_seconds),
+            email_notifications: rows
+                .get("email_notifications")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.email_notifications),
+            due_soon_days: number("due_soon_days", defaults.due_soon_days),
+            smtp_host: rows
+                .get("smtp_host")
+                .cloned()
+                .unwrap_or(defaults.smtp_host),
+            smtp_port: number("smtp_port", defaults.smtp_port),
+            smtp_security: rows
+                .get("smtp_security")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.smtp_security),
+            smtp_username: rows
+                .get("smtp_username")
+                .cloned()
+                .unwrap_or(defaults.smtp_username),
+            smtp_password_set: is_set(Secret::SmtpPassword),
+            smtp_from: rows
+                .get("smtp_from")
+                .cloned()
+                .unwrap_or(defaults.smtp_from),
+            sms_notifications: rows
+                .get("sms_notifications")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.sms_notifications),
+            sms_gateway: rows
+                .get("sms_gateway")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.sms_gateway),
+            sms_country_code: rows
+                .get("sms_country_code")
+                .cloned()
+                .unwrap_or(defaults.sms_country_code),
+            sms_http_url: rows
+                .get("sms_http_url")
+                .cloned()
+                .unwrap_or(defaults.sms_http_url),
+            sms_http_body: rows
+                .get("sms_http_body")
+                .cloned()
+                .unwrap_or(defaults.sms_http_body),
+            sms_http_authorization_set: is_set(Secret::SmsHttpAuthorization),
+            sms_outbox_path: rows
+                .get("sms_outbox_path")
+                .cloned()
+                .unwrap_or(defaults.sms_outbox_path),
+            daily_digest: rows
+                .get("daily_digest")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.daily_digest),
+        }
+    }
+
+    fn to_rows(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("library_name", self.library_name.clone()),
+            ("timezone", self.timezone.clone()),
+            ("loan_period_days", self.loan_period_days.to_string()),
+            ("max_loans_per_student", self.max_loans_per_student.to_string()),
+            ("max_renewals", self.max_renewals.to_string()),
+            ("auto_backup", self.auto_backup.to_string()),
+            ("backup_directory", self.backup_directory.trim().to_string()),
+            ("api_enabled", self.api_enabled.to_string()),
+            ("api_bind", self.api_bind.to_string()),
+            ("api_port", self.api_port.to_string()),
+            ("kiosk_timeout_seconds", self.kiosk_timeout_seconds.to_string()),
+            ("email_notifications", self.email_notifications.to_string()),
+            ("due_soon_days", self.due_soon_days.to_string()),
+            ("smtp_host", self.smtp_host.trim().to_string()),
+            ("smtp_port", self.smtp_port.to_string()),
+            ("smtp_security", self.smtp_security.to_string()),
+            ("smtp_username", self.smtp_username.trim().to_string()),
+            ("smtp_from", self.smtp_from.trim().to_string()),
+            ("sms_notifications", self.sms_notifications.to_string()),
+            ("sms_gateway", self.sms_gateway.to_string()),
+            ("sms_country_code", self.sms_country_code.trim().to_string()),
+            ("sms_http_url", self.sms_http_url.trim().to_string()),
+            ("sms_http_body", self.sms_http_body.clone()),
+            ("sms_outbox_path", self.sms_outbox_path.trim().to_string()),
+            ("daily_digest", self.daily_digest.to_string()),
+        ]
+    }
+
+    pub fn tz(&self) -> Tz {
+        self.timezone.parse().unwrap_or(Tz::UTC)
+    }
+
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if self.library_name.trim().is_empty() {
+            return Err(SettingsError::Invalid {
+                field: "library_name",
+                message: "must not be empty".to_string(),
+            });
+        }
+        if self.timezone.parse::<Tz>().is_err() {
+            return Err(SettingsError::Invalid {
+                field: "timezone",
+                message: format!("unknown timezone '{}'", self.timezone),
+            });
+        }
+        if !(1..=365).contains(&self.loan_period_days) {
+            return Err(SettingsError::Invalid {
+                field: "loan_period_days",
+                message: "must be between 1 and 365".to_string(),
+            });
+        }
+        if !(1..=50).contains(&self.max_loans_per_student) {
+            return Err(SettingsError::Invalid {
+                field: "max_loans_per_student",
+                message: "must be between 1 and 50".to_string(),
+            });
+        }
+        if !(0..=10).contains(&self.max_renewals) {
+            return Err(SettingsError::Invalid {
+                field: "max_renewals",
+                message: "must be between 0 and 10".to_string(),
+            });
+        }
+        let backup_directory = self.backup_directory.trim();
+        if !backup_directory.is_empty() && !std::path::Path::new(backup_directory).is_absolute() {
+            return Err(SettingsError::Invalid {
+                field: "backup_directory",
+                message: "must be an absolute path".to_string(),
+            });
+        }
+        if !(1024..=65535).contains(&self.api_port) {
+            return Err(SettingsError::Invalid {
+                field: "api_port",
+                message: "must be between 1024 and 65535".to_string(),
+            });
+        }
+        if !(15..=3600).contains(&self.kiosk_timeout_seconds) {
+            return Err(SettingsError::Invalid {
+                field: "kiosk_timeout_seconds",
+                message: "must be between 15 and 3600".to_string(),
+            });
+        }
+        if !(1..=14).contains(&self.due_soon_days) {
+            return Err(SettingsError::Invalid {
+                field: "due_soon_days",
+                message: "must be between 1 and 14".to_string(),
+            });
+        }
+        if !(1..=65535).contains(&self.smtp_port) {
+            return Err(SettingsError::Invalid {
+                field: "smtp_port",
+                message: "must be between 1 and 65535".to_string(),
+            });
+        }
+        if self.email_notifications && self.smtp_host.trim().is_empty() {
+            return Err(SettingsError::Invalid {
+                field: "smtp_host",
+                message: "is required to send email notifications".to_string(),
+            });
+        }
+        if self.email_notifications && !self.smtp_from.contains('@') {
+            return Err(SettingsError::Invalid {
+                field: "smtp_from",
+                message: "must be an email address".to_string(),
+            });
+        }
+        let country_code = self.sms_country_code.trim();
+        if !country_code.is_empty()
+            && !(country_code.len() <= 3 && country_code.chars().all(|c| c.is_ascii_digit()))
+        {
+            return Err(SettingsError::Invalid {
+                field: "sms_country_code",
+                message: "must be 1 to 3 digits, without +".to_string(),
+            });
+        }
+        let url = self.sms_http_url.trim();
+        if self.sms_notifications
+            && self.sms_gateway == SmsGatewayKind::Http
+            && !(url.starts_with("http://") || url.starts_with("https://"))
+        {
+            return Err(SettingsError::Invalid {
+                field: "sms_http_url",
+                message: "must be an http:// or https:// URL".to_string(),
+            });
+        }
+        let outbox = self.sms_outbox_path.trim();
+        if !outbox.is_empty() && !std::path::Path::new(outbox).is_absolute() {
+            return Err(SettingsError::Invalid {
+                field: "sms_outbox_path",
+                message: "must be an absolute path".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+pub async fn get_settings(pool: &Pool<Sqlite>) -> Result<Settings, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"SELECT key as "key!: String", value as "value!: String" FROM settings"#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Settings::from_rows(
+        rows.into_iter().map(|row| (row.key, row.value)).collect(),
+    ))
+}
+
+pub async fn update_settings(
+    pool: &Pool<Sqlite>,
+    settings: &Settings,
+) -> Result<Settings, SettingsError> {
+    settings.validate()?;
+    if settings.api_enabled && get_secret(pool, Secret::ApiToken).await?.is_empty() {
+        return Err(SettingsError::Invalid {
+            field: "api_token",
+            message: "must be set before enabling the API".to_string(),
+        });
+    }
+
+    let mut tx = pool.begin().await?;
+
+    for (key, value) in settings.to_rows() {
+        sqlx::query!(
+            r#"
+            INSERT INTO settings (key, value) VALUES (?, ?)
+            ON CONFLICT(key) DO UPDATE SET value = excluded.value
+            "#,
+            key,
+            value
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(get_settings(pool).await?)
+}
+
+/// Credentials stored in the `settings` table but kept out of [`Settings`],
+/// like the kiosk PIN hash, so they are never sent to the UI or written to
+/// `settings.json` in an archive. They can be replaced, not read back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Secret {
+    SmtpPassword,
+    /// Bearer token clients of the HTTP API must send.
+    ApiToken,
+    /// Sent as the Authorization header to the SMS gateway when not empty.
+    SmsHttpAuthorization,
+}
+
+impl Secret {
+    fn key(self) -> &'static str {
+        match self {
+            Secret::SmtpPassword => "smtp_password",
+            Secret::ApiToken => "api_token",
+            Secret::SmsHttpAuthorization => "sms_http_authorization",
+        }
+    }
+}
+
+/// The stored secret, or an empty string if it isn't set. For the services
+/// that use it; never return it from a command.
+pub async fn get_secret(pool: &Pool<Sqlite>, secret: Secret) -> Result<String, sqlx::Error> {
+    let key = secret.key();
+    let value = sqlx::query_scalar!(
+        r#"SELECT value as "value!: String" FROM settings WHERE key = ?"#,
+        key
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(value.unwrap_or_default())
+}
+
+/// Replaces the secret; an empty value clears it. Passwords are stored as
+/// typed, tokens and headers are trimmed.
+pub async fn set_secret(
+    pool: &Pool<Sqlite>,
+    secret: Secret,
+    value: &str,
+) -> Result<(), sqlx::Error> {
+    let key = secret.key();
+    let value = match secret {
+        Secret::SmtpPassword => value,
+        Secret::ApiToken | Secret::SmsHttpAuthorization => value.trim(),
+    };
+    sqlx::query!(
+        r#"
+        INSERT INTO settings (key, value) VALUES (?, ?)
+        ON CONFLICT(key) DO UPDATE SET value = excluded.value
+        "#,
+        key,
+        value
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Stores a new random API token and returns it. This is the only time the
+/// token can be seen, so it is shown once for the librarian to copy.
+pub async fn generate_api_token(pool: &Pool<Sqlite>) -> Result<String, sqlx::Error> {
+    let token = Uuid::new_v4().simple().to_string();
+    set_secret(pool, Secret::ApiToken, &token).await?;
+    Ok(token)
+}
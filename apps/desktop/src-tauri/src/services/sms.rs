@@ -0,0 +1,318 @@
+use crate::services::notifications::{
+    self, pending_notices, record_notification, was_sent, NoticeKind, NotificationRun,
+};
+use crate::services::settings::{self, Settings, SmsGatewayKind};
+use chrono::Utc;
+use sqlx::{Pool, Sqlite};
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+pub const SMS_CHANNEL: &str = "sms";
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, thiserror::Error)]
+pub enum SmsError {
+    #[error("SMS is not set up, add a gateway URL in settings")]
+    NotConfigured,
+    #[error("Invalid phone number: {0}")]
+    InvalidNumber(String),
+    #[error("SMS gateway request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("SMS gateway rejected the message: {0}")]
+    Gateway(String),
+    #[error("Could not write to the SMS outbox: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Something that can deliver a text message to an E.164 number.
+pub trait SmsGateway: Send + Sync {
+    fn send(&self, to: &str, message: &str) -> impl Future<Output = Result<(), SmsError>> + Send;
+}
+
+/// Writes messages to a file, one tab-separated line each, or to the console
+/// when no file is given. Meant for trying the reminders out without a
+/// provider.
+pub struct FileGateway {
+    path: Option<PathBuf>,
+}
+
+impl FileGateway {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path }
+    }
+}
+
+impl SmsGateway for FileGateway {
+    async fn send(&self, to: &str, message: &str) -> Result<(), SmsError> {
+        let line = format!(
+            "{}\t{}\t{}\n",
+            Utc::now().to_rfc3339(),
+            to,
+            message.replace(['\n', '\r'], " ")
+        );
+
+        match &self.path {
+            Some(path) => {
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?;
+                file.write_all(line.as_bytes()).await?;
+            }
+            None => print!("SMS {}", line),
+        }
+        Ok(())
+    }
+}
+
+/// Calls a provider's HTTP endpoint. `{to}` and `{message}` in the URL are
+/// percent-encoded. An empty body sends a GET; otherwise the body is POSTed,
+/// as JSON with the values escaped if it starts with `{`, and form-encoded
+/// otherwise. `authorization`, if set, is sent as the `Authorization` header.
+pub struct HttpGateway {
+    client: reqwest::Client,
+    url: String,
+    body: String,
+    authorization: String,
+}
+
+impl HttpGateway {
+    pub fn new(url: &str, body: &str, authorization: &str) -> Result<Self, SmsError> {
+        if url.trim().is_empty() {
+            return Err(SmsError::NotConfigured);
+        }
+
+        Ok(Self {
+            client: reqwest::Client::builder().timeout(HTTP_TIMEOUT).build()?,
+            url: url.trim().to_string(),
+            body: body.to_string(),
+            authorization: authorization.trim().to_string(),
+        })
+    }
+}
+
+fn fill(template: &str, to: &str, message: &str, escape: impl Fn(&str) -> String) -> String {
+    template
+        .replace("{to}", &escape(to))
+        .replace("{message}", &escape(message))
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn json_escape(value: &str) -> String {
+    let quoted = serde_json::Value::from(value).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+impl SmsGateway for HttpGateway {
+    async fn send(&self, to: &str, message: &str) -> Result<(), SmsError> {
+        let url = fill(&self.url, to, message, percent_encode);
+        let body = self.body.trim();
+
+        let request = if body.is_empty() {
+            self.client.get(url)
+        } else if body.starts_with('{') {
+            self.client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(fill(body, to, message, json_escape))
+        } else {
+            self.client
+                .post(url)
+                .header(
+                    reqwest::header::CONTENT_TYPE,
+                    "application/x-www-form-urlencoded",
+                )
+                .body(fill(body, to, message, percent_encode))
+        };
+        let request = if self.authorization.is_empty() {
+            request
+        } else {
+            request.header(reqwest::header::AUTHORIZATION, &self.authorization)
+        };
+
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(SmsError::Gateway(format!("{} {}", status, text.trim())));
+        }
+        Ok(())
+    }
+}
+
+/// The gateway chosen in settings.
+pub enum ConfiguredGateway {
+    File(FileGateway),
+    Http(HttpGateway),
+}
+
+impl ConfiguredGateway {
+    /// `authorization` is [`settings::Secret::SmsHttpAuthorization`], which
+    /// `Settings` doesn't carry.
+    pub fn from_settings(settings: &Settings, authorization: &str) -> Result<Self, SmsError> {
+        match settings.sms_gateway {
+            SmsGatewayKind::File => {
+                let path = settings.sms_outbox_path.trim();
+                let path = (!path.is_empty()).then(|| PathBuf::from(path));
+                Ok(Self::File(FileGateway::new(path)))
+            }
+            SmsGatewayKind::Http => Ok(Self::Http(HttpGateway::new(
+                &settings.sms_http_url,
+                &settings.sms_http_body,
+                authorization,
+            )?)),
+        }
+    }
+}
+
+impl SmsGateway for ConfiguredGateway {
+    async fn send(&self, to: &str, message: &str) -> Result<(), SmsError> {
+        match self {
+            Self::File(gateway) => gateway.send(to, message).await,
+            Self::Http(gateway) => gateway.send(to, message).await,
+        }
+    }
+}
+
+/// Turns a number as typed into a student record into E.164 (`+` and 8 to
+/// 15 digits). Spaces, dashes, dots and brackets are ignored. `+` or `00`
+/// marks an international number; a leading `0` is the national trunk
+/// prefix and is replaced by `country_code`, which is also added to numbers
+/// that don't already start with it.
+pub fn normalize_e164(raw: &str, country_code: &str) -> Result<String, SmsError> {
+    let invalid = || SmsError::InvalidNumber(raw.to_string());
+    let compact: String = raw
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+        .collect();
+    let country_code = country_code.trim().trim_start_matches('+');
+
+    let digits = if let Some(rest) = compact.strip_prefix('+') {
+        rest.to_string()
+    } else if let Some(rest) = compact.strip_prefix("00") {
+        rest.to_string()
+    } else if let Some(rest) = compact.strip_prefix('0') {
+        if country_code.is_empty() {
+            return Err(invalid());
+        }
+        format!("{}{}", country_code, rest)
+    } else if compact.starts_with(country_code) {
+        compact
+    } else {
+        format!("{}{}", country_code, compact)
+    };
+
+    if !(8..=15).contains(&digits.len())
+        || digits.starts_with('0')
+        || !digits.chars().all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+    Ok(format!("+{}", digits))
+}
+
+const DUE_SOON: &str =
+    "{library}: \"{book}\" borrowed by {student} is due {due_date}. Please return it on time.";
+
+const OVERDUE: &str = "{library}: \"{book}\" borrowed by {student} is {days_overdue} day(s) \
+                       overdue. Please return it as soon as possible.";
+
+pub fn template(kind: NoticeKind) -> &'static str {
+    match kind {
+        NoticeKind::DueSoon => DUE_SOON,
+        NoticeKind::Overdue => OVERDUE,
+    }
+}
+
+/// Texts every pending reminder that hasn't been sent yet to the student's
+/// phone number, and logs each attempt. Students who opted out are left
+/// alone; numbers that can't be normalized are counted as invalid.
+pub async fn send_sms_notices(
+    pool: &Pool<Sqlite>,
+    gateway: &impl SmsGateway,
+) -> Result<NotificationRun, sqlx::Error> {
+    let settings = settings::get_settings(pool).await?;
+    let tz = settings.tz();
+    let mut run = NotificationRun::default();
+
+    for notice in pending_notices(pool, &settings).await? {
+        if notice.sms_opt_out {
+            continue;
+        }
+        let Some(phone_number) = notice.phone_number.as_deref() else {
+            continue;
+        };
+        if phone_number.trim().is_empty() {
+            continue;
+        }
+        let Ok(recipient) = normalize_e164(phone_number, &settings.sms_country_code) else {
+            run.invalid += 1;
+            continue;
+        };
+        if was_sent(pool, &notice, SMS_CHANNEL, &recipient).await? {
+            run.skipped += 1;
+            continue;
+        }
+
+        let message =
+            notifications::render(template(notice.kind), &notice, &settings.library_name, tz);
+        let result = gateway
+            .send(&recipient, &message)
+            .await
+            .map_err(|e| e.to_string());
+
+        if result.is_ok() {
+            run.sent += 1;
+        } else {
+            run.failed += 1;
+        }
+        record_notification(pool, &notice, SMS_CHANNEL, &recipient, &result).await?;
+    }
+
+    Ok(run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_numbers_get_the_country_code() {
+        assert_eq!(normalize_e164("0788 123 456", "250").unwrap(), "+250788123456");
+        assert_eq!(normalize_e164("788-123-456", "250").unwrap(), "+250788123456");
+        assert_eq!(normalize_e164("250788123456", "250").unwrap(), "+250788123456");
+    }
+
+    #[test]
+    fn international_numbers_are_kept() {
+        assert_eq!(normalize_e164("+44 (20) 7946 0018", "250").unwrap(), "+442079460018");
+        assert_eq!(normalize_e164("00442079460018", "250").unwrap(), "+442079460018");
+        assert_eq!(normalize_e164("0788123456", "+250").unwrap(), "+250788123456");
+    }
+
+    #[test]
+    fn unusable_numbers_are_rejected() {
+        for raw in ["", "12", "0788 123 45x", "+1234567890123456"] {
+            assert!(normalize_e164(raw, "250").is_err(), "{:?}", raw);
+        }
+        // A trunk prefix can't be replaced without a country code.
+        assert!(normalize_e164("0788123456", "").is_err());
+    }
+}
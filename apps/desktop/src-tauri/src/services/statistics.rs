@@ -1,134 +1,242 @@
-use chrono::{DateTime, Utc};
+use crate::services::clock::LibraryClock;
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{Pool, Sqlite};
+use sqlx::{Pool, QueryBuilder, Sqlite};
+
+/// Narrows statistics to a reporting period and a slice of the library.
+/// Every field is optional; an empty filter reproduces the dashboard defaults.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StatsFilter {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    pub grade: Option<String>,
+    pub category: Option<String>,
+}
+
+impl StatsFilter {
+    fn limit_or(&self, default: i64) -> i64 {
+        self.limit.filter(|limit| *limit > 0).unwrap_or(default)
+    }
+
+    /// Appends ` AND ...` clauses for the student grade (`s`), book category
+    /// (`b`) and, when `date_column` is given, the reporting period.
+    fn push_conditions(&self, query: &mut QueryBuilder<'_, Sqlite>, date_column: Option<&str>) {
+        if let Some(grade) = &self.grade {
+            query.push(" AND s.grade = ").push_bind(grade.clone());
+        }
+        if let Some(category) = &self.category {
+            query.push(" AND b.category = ").push_bind(category.clone());
+        }
+        if let Some(column) = date_column {
+            if let Some(from) = self.from {
+                query.push(format!(" AND {} >= ", column)).push_bind(from);
+            }
+            if let Some(to) = self.to {
+                query.push(format!(" AND {} < ", column)).push_bind(to);
+            }
+        }
+    }
+}
+
+/// Figures describing the library as it stands right now.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CurrentStats {
+    pub total_students: i64,
+    pub total_books: i64,
+    pub available_books: i64,
+    pub books_on_loan: i64,
+    pub overdue_books: i64,
+    /// Share of all copies currently out on loan, from 0 to 100.
+    pub utilization_rate: f64,
+    /// Books currently on loan, by category.
+    pub categories_on_loan: Vec<CategoryStats>,
+}
+
+/// Figures accumulated over the filter's period, or all time without one.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoricalStats {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub total_loans: i64,
+    /// Every loan made in the period, by category.
+    pub loans_by_category: Vec<CategoryStats>,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DashboardStats {
-    total_students: i64,
-    total_books: i64,
-    available_books: i64,
-    books_on_loan: i64,
-    overdue_books: i64,
-    utilization_rate: i64,
-    popular_categories: Vec<CategoryStats>,
+    pub current: CurrentStats,
+    pub historical: HistoricalStats,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CategoryStats {
-    name: String,
-    count: i64,
-    percentage: i64,
+    pub name: String,
+    pub count: i64,
+    /// Share of the breakdown's total, from 0 to 100.
+    pub percentage: f64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+fn percentage(part: i64, total: i64) -> f64 {
+    if total > 0 {
+        part as f64 * 100.0 / total as f64
+    } else {
+        0.0
+    }
+}
+
+/// Turns `(category, count)` rows into a breakdown whose percentages are of
+/// the full total, even when only the top `limit` entries are kept.
+fn category_breakdown(rows: Vec<(String, i64)>, limit: usize) -> (i64, Vec<CategoryStats>) {
+    let total: i64 = rows.iter().map(|(_, count)| count).sum();
+    let categories = rows
+        .into_iter()
+        .take(limit)
+        .map(|(name, count)| CategoryStats {
+            name,
+            count,
+            percentage: percentage(count, total),
+        })
+        .collect();
+    (total, categories)
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct PopularBook {
-    id: String,
-    title: String,
-    author: String,
-    category: String,
-    status: String,
-    times_loaned: i64,
+    pub id: String,
+    pub title: String,
+    pub author: String,
+    pub category: String,
+    pub status: String,
+    pub times_loaned: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct OverdueBook {
-    id: String,
-    book_title: String,
-    author: String,
-    student_name: String,
-    grade: String,
-    student_id: String,
-    due_date: DateTime<Utc>,
-    days_overdue: i64,
+    pub id: String,
+    pub book_title: String,
+    pub author: String,
+    pub student_name: String,
+    pub grade: String,
+    pub student_id: String,
+    pub due_date: DateTime<Utc>,
+    #[sqlx(default)]
+    pub days_overdue: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct RecentActivity {
-    id: String,
-    student_name: String,
-    book_title: String,
-    author: String,
-    activity_type: String,
-    due_date: Option<DateTime<Utc>>,
-    created_at: DateTime<Utc>,
-}
-
-pub async fn get_dashboard_stats(pool: &Pool<Sqlite>) -> Result<DashboardStats, sqlx::Error> {
-    let total_students: i64 = sqlx::query_scalar!("SELECT COUNT(*) FROM students")
-        .fetch_one(pool)
-        .await?
-        .into();
-
-    let in_library_books: i64 = sqlx::query_scalar!("SELECT SUM(quantity) FROM books")
-        .fetch_one(pool)
-        .await?
-        .unwrap_or(0)
-        .into();
-
-    let available_books: i64 =
-        sqlx::query_scalar!("SELECT SUM(quantity) FROM books WHERE status = 'available'")
-            .fetch_one(pool)
-            .await?
-            .unwrap_or(0)
-            .into();
-
-    let books_on_loan: i64 = sqlx::query_scalar!("SELECT COUNT(*) FROM lent WHERE status = 'lent'")
-        .fetch_one(pool)
-        .await?
-        .into();
+    pub id: String,
+    pub student_name: String,
+    pub book_title: String,
+    pub author: String,
+    pub activity_type: String,
+    pub due_date: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+async fn fetch_count(
+    pool: &Pool<Sqlite>,
+    mut query: QueryBuilder<'_, Sqlite>,
+) -> Result<i64, sqlx::Error> {
+    query.build_query_scalar::<i64>().fetch_one(pool).await
+}
+
+pub async fn get_dashboard_stats(
+    pool: &Pool<Sqlite>,
+    filter: &StatsFilter,
+) -> Result<DashboardStats, sqlx::Error> {
+    let mut query = QueryBuilder::new("SELECT COUNT(*) FROM students s WHERE 1 = 1");
+    if let Some(grade) = &filter.grade {
+        query.push(" AND s.grade = ").push_bind(grade.clone());
+    }
+    let total_students = fetch_count(pool, query).await?;
+
+    let mut query = QueryBuilder::new("SELECT COALESCE(SUM(quantity), 0) FROM books b WHERE 1 = 1");
+    if let Some(category) = &filter.category {
+        query.push(" AND b.category = ").push_bind(category.clone());
+    }
+    let in_library_books = fetch_count(pool, query).await?;
+
+    let mut query = QueryBuilder::new(
+        "SELECT COALESCE(SUM(quantity), 0) FROM books b WHERE b.status = 'available'",
+    );
+    if let Some(category) = &filter.category {
+        query.push(" AND b.category = ").push_bind(category.clone());
+    }
+    let available_books = fetch_count(pool, query).await?;
+
+    // Loan counts are point-in-time, so only the grade/category filters apply.
+    let loans_from = "SELECT COUNT(*) FROM lent l \
+        JOIN books b ON l.book_id = b.id \
+        JOIN students s ON l.student_id = s.id \
+        WHERE l.status = 'lent'";
+
+    let mut query = QueryBuilder::new(loans_from);
+    filter.push_conditions(&mut query, None);
+    let books_on_loan = fetch_count(pool, query).await?;
+
     let total_books = in_library_books + books_on_loan;
-    let overdue_books: i64 = sqlx::query_scalar!(
-        "SELECT COUNT(*) FROM lent WHERE status = 'lent' AND due_date < CURRENT_TIMESTAMP"
-    )
-    .fetch_one(pool)
-    .await?
-    .into();
 
-    let category_stats = sqlx::query!(
-        r#"
-        SELECT b.category, COUNT(*) as count
+    let mut query = QueryBuilder::new(loans_from);
+    query
+        .push(" AND l.due_date < ")
+        .push_bind(LibraryClock::load(pool).await?.overdue_cutoff());
+    filter.push_conditions(&mut query, None);
+    let overdue_books = fetch_count(pool, query).await?;
+
+    // Books with no category come back from the LEFT JOIN as NULL or '' and
+    // are reported together rather than as a nameless bucket.
+    let category_select = r#"
+        SELECT
+            CASE WHEN b.category IS NULL OR b.category = '' THEN 'Uncategorized'
+                 ELSE b.category END as category,
+            COUNT(*) as count
         FROM lent l
         LEFT JOIN books b ON l.book_id = b.id
-        GROUP BY b.category
-        ORDER BY count DESC
-        "#
-    )
-    .fetch_all(pool)
-    .await?;
+        LEFT JOIN students s ON l.student_id = s.id"#;
+    let limit = filter.limit_or(4) as usize;
 
-    let total_loans: i64 = category_stats.iter().map(|stat| stat.count as i64).sum();
-    let popular_categories = category_stats
-        .iter()
-        .take(4)
-        .map(|stat| CategoryStats {
-            name: stat.category.clone().unwrap_or_default(),
-            count: stat.count as i64,
-            percentage: if total_loans > 0 {
-                ((stat.count as i64) * 100) / total_loans
-            } else {
-                0
-            },
-        })
-        .collect();
+    let mut query = QueryBuilder::new(category_select);
+    query.push(" WHERE l.status = 'lent'");
+    filter.push_conditions(&mut query, None);
+    query.push(" GROUP BY 1 ORDER BY count DESC");
+    let (_, categories_on_loan) =
+        category_breakdown(query.build_query_as().fetch_all(pool).await?, limit);
+
+    let mut query = QueryBuilder::new(category_select);
+    query.push(" WHERE 1 = 1");
+    filter.push_conditions(&mut query, Some("l.lent_at"));
+    query.push(" GROUP BY 1 ORDER BY count DESC");
+    let (total_loans, loans_by_category) =
+        category_breakdown(query.build_query_as().fetch_all(pool).await?, limit);
 
     Ok(DashboardStats {
-        total_students,
-        total_books,
-        available_books,
-        books_on_loan,
-        overdue_books,
-        utilization_rate: if total_books > 0 {
-            (books_on_loan * 100) / total_books
-        } else {
-            0
+        current: CurrentStats {
+            total_students,
+            total_books,
+            available_books,
+            books_on_loan,
+            overdue_books,
+            utilization_rate: percentage(books_on_loan, total_books),
+            categories_on_loan,
+        },
+        historical: HistoricalStats {
+            from: filter.from,
+            to: filter.to,
+            total_loans,
+            loans_by_category,
         },
-        popular_categories,
     })
 }
 
-pub async fn get_popular_books(pool: &Pool<Sqlite>) -> Result<Vec<PopularBook>, sqlx::Error> {
-    sqlx::query_as!(
-        PopularBook,
+pub async fn get_popular_books(
+    pool: &Pool<Sqlite>,
+    filter: &StatsFilter,
+) -> Result<Vec<PopularBook>, sqlx::Error> {
+    // Period and grade conditions live in the join so books that were never
+    // loaned in the window still show up with a count of zero.
+    let mut query = QueryBuilder::new(
         r#"
         SELECT 
             COALESCE(b.id, '') as id,
@@ -138,19 +246,38 @@ pub async fn get_popular_books(pool: &Pool<Sqlite>) -> Result<Vec<PopularBook>,
             COALESCE(b.status, '') as status,
             COUNT(l.id) as times_loaned
         FROM books b
-        LEFT JOIN lent l ON b.id = l.book_id
-        GROUP BY b.id
-        ORDER BY times_loaned DESC
-        LIMIT 5
-        "#
-    )
-    .fetch_all(pool)
-    .await
+        LEFT JOIN lent l ON b.id = l.book_id"#,
+    );
+    if let Some(from) = filter.from {
+        query.push(" AND l.lent_at >= ").push_bind(from);
+    }
+    if let Some(to) = filter.to {
+        query.push(" AND l.lent_at < ").push_bind(to);
+    }
+    if let Some(grade) = &filter.grade {
+        query
+            .push(" AND l.student_id IN (SELECT id FROM students WHERE grade = ")
+            .push_bind(grade.clone())
+            .push(")");
+    }
+    query.push(" WHERE 1 = 1");
+    if let Some(category) = &filter.category {
+        query.push(" AND b.category = ").push_bind(category.clone());
+    }
+    query
+        .push(" GROUP BY b.id ORDER BY times_loaned DESC LIMIT ")
+        .push_bind(filter.limit_or(5));
+
+    query.build_query_as().fetch_all(pool).await
 }
 
-pub async fn get_overdue_books(pool: &Pool<Sqlite>) -> Result<Vec<OverdueBook>, sqlx::Error> {
-    sqlx::query_as!(
-        OverdueBook,
+pub async fn get_overdue_books(
+    pool: &Pool<Sqlite>,
+    filter: &StatsFilter,
+) -> Result<Vec<OverdueBook>, sqlx::Error> {
+    let clock = LibraryClock::load(pool).await?;
+
+    let mut query = QueryBuilder::new(
         r#"
         SELECT 
             COALESCE(l.id, '') as id,
@@ -159,22 +286,34 @@ pub async fn get_overdue_books(pool: &Pool<Sqlite>) -> Result<Vec<OverdueBook>,
             COALESCE(s.name, '') as student_name,
             COALESCE(s.grade, '') as grade,
             COALESCE(s.student_id, '') as student_id,
-            l.due_date as "due_date!: chrono::DateTime<chrono::Utc>",
-            CAST(JULIANDAY('now') - JULIANDAY(l.due_date) AS INTEGER) as "days_overdue!: i64"
+            l.due_date as due_date
         FROM lent l
         JOIN books b ON l.book_id = b.id
         JOIN students s ON l.student_id = s.id
-        WHERE l.status = 'lent' AND l.due_date < CURRENT_TIMESTAMP
-        ORDER BY l.due_date ASC
-        "#
-    )
-    .fetch_all(pool)
-    .await
+        WHERE l.status = 'lent' AND l.due_date < "#,
+    );
+    query.push_bind(clock.overdue_cutoff());
+    filter.push_conditions(&mut query, Some("l.due_date"));
+    query.push(" ORDER BY l.due_date ASC");
+    if let Some(limit) = filter.limit.filter(|limit| *limit > 0) {
+        query.push(" LIMIT ").push_bind(limit);
+    }
+
+    let mut overdue: Vec<OverdueBook> = query.build_query_as().fetch_all(pool).await?;
+    for book in &mut overdue {
+        book.days_overdue = clock.days_overdue(book.due_date);
+    }
+
+    Ok(overdue)
 }
 
-pub async fn get_recent_activity(pool: &Pool<Sqlite>) -> Result<Vec<RecentActivity>, sqlx::Error> {
-    sqlx::query_as!(
-        RecentActivity,
+pub async fn get_recent_activity(
+    pool: &Pool<Sqlite>,
+    filter: &StatsFilter,
+) -> Result<Vec<RecentActivity>, sqlx::Error> {
+    let from = filter.from.unwrap_or_else(|| Utc::now() - Duration::days(1));
+
+    let mut query = QueryBuilder::new(
         r#"
         SELECT 
             COALESCE(l.id, '') as id,
@@ -182,16 +321,127 @@ pub async fn get_recent_activity(pool: &Pool<Sqlite>) -> Result<Vec<RecentActivi
             COALESCE(b.title, '') as book_title,
             COALESCE(b.author, '') as author,
             COALESCE(l.status, '') as activity_type,
-            l.due_date as "due_date?: chrono::DateTime<chrono::Utc>",
-            l.lent_at as "created_at!: chrono::DateTime<chrono::Utc>"
+            l.due_date as due_date,
+            l.lent_at as created_at
         FROM lent l
         JOIN books b ON l.book_id = b.id
         JOIN students s ON l.student_id = s.id
-        WHERE l.lent_at >= datetime('now', '-1 day')
-        ORDER BY l.lent_at DESC
+        WHERE l.lent_at >= "#,
+    );
+    query.push_bind(from);
+    if let Some(to) = filter.to {
+        query.push(" AND l.lent_at < ").push_bind(to);
+    }
+    filter.push_conditions(&mut query, None);
+    query
+        .push(" ORDER BY l.lent_at DESC LIMIT ")
+        .push_bind(filter.limit_or(10));
+
+    query.build_query_as().fetch_all(pool).await
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CirculationSummary {
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub loans: i64,
+    pub returns: i64,
+    pub late_returns: i64,
+    pub active_borrowers: i64,
+    pub new_students: i64,
+    pub new_books: i64,
+    pub popular_books: Vec<PopularBook>,
+}
+
+pub async fn get_circulation_summary(
+    pool: &Pool<Sqlite>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<CirculationSummary, sqlx::Error> {
+    let loans = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!: i64" FROM lent WHERE lent_at >= ? AND lent_at < ?"#,
+        start,
+        end
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let returns = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!: i64" FROM lent WHERE returned_at >= ? AND returned_at < ?"#,
+        start,
+        end
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let late_returns = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!: i64"
+        FROM lent
+        WHERE returned_at >= ? AND returned_at < ? AND returned_at > due_date
+        "#,
+        start,
+        end
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let active_borrowers = sqlx::query_scalar!(
+        r#"SELECT COUNT(DISTINCT student_id) as "count!: i64" FROM lent WHERE lent_at >= ? AND lent_at < ?"#,
+        start,
+        end
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let new_students = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!: i64" FROM students WHERE created_at >= ? AND created_at < ?"#,
+        start,
+        end
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let new_books = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!: i64" FROM books WHERE created_at >= ? AND created_at < ?"#,
+        start,
+        end
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let popular_books = sqlx::query_as!(
+        PopularBook,
+        r#"
+        SELECT 
+            COALESCE(b.id, '') as id,
+            COALESCE(b.title, '') as title,
+            COALESCE(b.author, '') as author,
+            COALESCE(b.category, '') as category,
+            COALESCE(b.status, '') as status,
+            COUNT(l.id) as times_loaned
+        FROM lent l
+        JOIN books b ON b.id = l.book_id
+        WHERE l.lent_at >= ? AND l.lent_at < ?
+        GROUP BY b.id
+        ORDER BY times_loaned DESC
         LIMIT 10
-        "#
+        "#,
+        start,
+        end
     )
     .fetch_all(pool)
-    .await
+    .await?;
+
+    Ok(CirculationSummary {
+        period_start: start,
+        period_end: end,
+        loans,
+        returns,
+        late_returns,
+        active_borrowers,
+        new_students,
+        new_books,
+        popular_books,
+    })
 }
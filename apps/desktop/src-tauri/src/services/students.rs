@@ -12,6 +12,9 @@ pub async fn get_all_students(pool: &Pool<Sqlite>) -> Result<Vec<Student>, sqlx:
             COALESCE(name, '') as name,
             COALESCE(grade, '') as grade,
             phone_number as "phone_number?: String",
+            email as "email?: String",
+            guardian_email as "guardian_email?: String",
+            sms_opt_out as "sms_opt_out!: bool",
             COALESCE(student_id, '') as student_id,
             status as "status!: StudentStatus",
             created_at as "created_at!: chrono::DateTime<chrono::Utc>"
@@ -35,6 +38,9 @@ pub async fn get_student_by_id(
             COALESCE(name, '') as name,
             COALESCE(grade, '') as grade,
             phone_number as "phone_number?: String",
+            email as "email?: String",
+            guardian_email as "guardian_email?: String",
+            sms_opt_out as "sms_opt_out!: bool",
             COALESCE(student_id, '') as student_id,
             status as "status!: StudentStatus",
             created_at as "created_at!: chrono::DateTime<chrono::Utc>"
@@ -47,24 +53,36 @@ pub async fn get_student_by_id(
     .await
 }
 
+/// Blank addresses are stored as NULL so "no email" has one representation.
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|v| !v.is_empty())
+}
+
 pub async fn create_student(
     pool: &Pool<Sqlite>,
     name: &str,
     grade: &str,
     phone_number: Option<&str>,
+    email: Option<&str>,
+    guardian_email: Option<&str>,
     student_id: &str,
 ) -> Result<(), sqlx::Error> {
     let id = Uuid::new_v4().to_string();
     let created_at = Utc::now();
+    let email = non_empty(email);
+    let guardian_email = non_empty(guardian_email);
     sqlx::query!(
         r#"
-        INSERT INTO students (id, name, grade, phone_number, student_id, status, created_at)
-        VALUES (?, ?, ?, ?, ?, ?, ?)
+        INSERT INTO students
+            (id, name, grade, phone_number, email, guardian_email, student_id, status, created_at)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#,
         id,
         name,
         grade,
         phone_number,
+        email,
+        guardian_email,
         student_id,
         "active",
         created_at
@@ -75,26 +93,34 @@ pub async fn create_student(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn update_student(
     pool: &Pool<Sqlite>,
     id: &str,
     name: &str,
     grade: &str,
     phone_number: Option<&str>,
+    email: Option<&str>,
+    guardian_email: Option<&str>,
     student_id: &str,
     status: StudentStatus,
 ) -> Result<(), sqlx::Error> {
     let status_str = status.to_string();
+    let email = non_empty(email);
+    let guardian_email = non_empty(guardian_email);
 
     sqlx::query!(
         r#"
         UPDATE students
-        SET name = ?, grade = ?, phone_number = ?, student_id = ?, status = ?
+        SET name = ?, grade = ?, phone_number = ?, email = ?, guardian_email = ?,
+            student_id = ?, status = ?
         WHERE id = ?
         "#,
         name,
         grade,
         phone_number,
+        email,
+        guardian_email,
         student_id,
         status_str,
         id
@@ -105,10 +131,37 @@ pub async fn update_student(
     Ok(())
 }
 
-pub async fn delete_student(pool: &Pool<Sqlite>, id: &str) -> Result<(), sqlx::Error> {
-    sqlx::query!(r#"DELETE FROM students WHERE id = ?"#, id)
+pub async fn set_sms_opt_out(
+    pool: &Pool<Sqlite>,
+    id: &str,
+    opt_out: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(r#"UPDATE students SET sms_opt_out = ? WHERE id = ?"#, opt_out, id)
         .execute(pool)
         .await?;
 
     Ok(())
 }
+
+#[derive(Debug, thiserror::Error)]
+pub enum DeleteStudentError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("This student has loans on record; mark them inactive instead")]
+    HasLoans,
+}
+
+/// Deletes a student who has never borrowed. Students with loan history are
+/// refused, since their loans still point at them; deactivate those instead.
+pub async fn delete_student(pool: &Pool<Sqlite>, id: &str) -> Result<(), DeleteStudentError> {
+    match sqlx::query!(r#"DELETE FROM students WHERE id = ?"#, id)
+        .execute(pool)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(sqlx::Error::Database(e)) if e.is_foreign_key_violation() => {
+            Err(DeleteStudentError::HasLoans)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,262 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::collections::{BTreeMap, HashMap};
+
+/// Upper bound on buckets per request, roughly three years of days.
+const MAX_BUCKETS: usize = 1100;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TrendsError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Invalid range: {0}")]
+    InvalidRange(String),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interval {
+    Day,
+    Week,
+    Month,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitBy {
+    Category,
+    Grade,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SeriesRequest {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub interval: Interval,
+    pub split_by: Option<SplitBy>,
+}
+
+/// One line on the chart. `group` is `None` for the library-wide totals.
+/// Metrics that cannot be split by the chosen dimension (new books by grade,
+/// new students by category) are only counted in the totals line.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SeriesLine {
+    pub group: Option<String>,
+    pub loans: Vec<i64>,
+    pub returns: Vec<i64>,
+    pub new_students: Vec<i64>,
+    pub new_books: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CirculationSeries {
+    pub interval: Interval,
+    pub buckets: Vec<NaiveDate>,
+    pub lines: Vec<SeriesLine>,
+}
+
+#[derive(Clone, Copy)]
+enum Metric {
+    Loans,
+    Returns,
+    NewStudents,
+    NewBooks,
+}
+
+impl SeriesLine {
+    fn new(group: Option<String>, len: usize) -> Self {
+        Self {
+            group,
+            loans: vec![0; len],
+            returns: vec![0; len],
+            new_students: vec![0; len],
+            new_books: vec![0; len],
+        }
+    }
+
+    fn values(&mut self, metric: Metric) -> &mut Vec<i64> {
+        match metric {
+            Metric::Loans => &mut self.loans,
+            Metric::Returns => &mut self.returns,
+            Metric::NewStudents => &mut self.new_students,
+            Metric::NewBooks => &mut self.new_books,
+        }
+    }
+}
+
+fn bucket_start(date: NaiveDate, interval: Interval) -> NaiveDate {
+    match interval {
+        Interval::Day => date,
+        Interval::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        Interval::Month => date.with_day(1).unwrap_or(date),
+    }
+}
+
+fn next_bucket(date: NaiveDate, interval: Interval) -> NaiveDate {
+    match interval {
+        Interval::Day => date + Duration::days(1),
+        Interval::Week => date + Duration::weeks(1),
+        Interval::Month => {
+            let (year, month) = if date.month() == 12 {
+                (date.year() + 1, 1)
+            } else {
+                (date.year(), date.month() + 1)
+            };
+            NaiveDate::from_ymd_opt(year, month, 1).unwrap_or(date)
+        }
+    }
+}
+
+fn build_buckets(request: &SeriesRequest) -> Result<Vec<NaiveDate>, TrendsError> {
+    if request.from >= request.to {
+        return Err(TrendsError::InvalidRange(
+            "start must be before end".to_string(),
+        ));
+    }
+
+    let last = request.to.date_naive();
+    let mut buckets = Vec::new();
+    let mut current = bucket_start(request.from.date_naive(), request.interval);
+
+    while current <= last {
+        if buckets.len() == MAX_BUCKETS {
+            return Err(TrendsError::InvalidRange(format!(
+                "more than {} buckets requested, use a wider interval",
+                MAX_BUCKETS
+            )));
+        }
+        buckets.push(current);
+        current = next_bucket(current, request.interval);
+    }
+
+    Ok(buckets)
+}
+
+struct Event {
+    at: DateTime<Utc>,
+    category: Option<String>,
+    grade: Option<String>,
+}
+
+pub async fn get_circulation_series(
+    pool: &Pool<Sqlite>,
+    request: &SeriesRequest,
+) -> Result<CirculationSeries, TrendsError> {
+    let buckets = build_buckets(request)?;
+    let index: HashMap<NaiveDate, usize> =
+        buckets.iter().enumerate().map(|(i, d)| (*d, i)).collect();
+
+    let loans = sqlx::query_as!(
+        Event,
+        r#"
+        SELECT
+            l.lent_at as "at!: chrono::DateTime<chrono::Utc>",
+            b.category as "category?: String",
+            s.grade as "grade?: String"
+        FROM lent l
+        LEFT JOIN books b ON l.book_id = b.id
+        LEFT JOIN students s ON l.student_id = s.id
+        WHERE l.lent_at >= ? AND l.lent_at < ?
+        "#,
+        request.from,
+        request.to
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let returns = sqlx::query_as!(
+        Event,
+        r#"
+        SELECT
+            l.returned_at as "at!: chrono::DateTime<chrono::Utc>",
+            b.category as "category?: String",
+            s.grade as "grade?: String"
+        FROM lent l
+        LEFT JOIN books b ON l.book_id = b.id
+        LEFT JOIN students s ON l.student_id = s.id
+        WHERE l.returned_at >= ? AND l.returned_at < ?
+        "#,
+        request.from,
+        request.to
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let new_students = sqlx::query_as!(
+        Event,
+        r#"
+        SELECT
+            created_at as "at!: chrono::DateTime<chrono::Utc>",
+            NULL as "category?: String",
+            grade as "grade?: String"
+        FROM students
+        WHERE created_at >= ? AND created_at < ?
+        "#,
+        request.from,
+        request.to
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let new_books = sqlx::query_as!(
+        Event,
+        r#"
+        SELECT
+            created_at as "at!: chrono::DateTime<chrono::Utc>",
+            category as "category?: String",
+            NULL as "grade?: String"
+        FROM books
+        WHERE created_at >= ? AND created_at < ?
+        "#,
+        request.from,
+        request.to
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut totals = SeriesLine::new(None, buckets.len());
+    let mut groups: BTreeMap<String, SeriesLine> = BTreeMap::new();
+
+    for (metric, events) in [
+        (Metric::Loans, loans),
+        (Metric::Returns, returns),
+        (Metric::NewStudents, new_students),
+        (Metric::NewBooks, new_books),
+    ] {
+        for event in events {
+            let Some(&slot) = index.get(&bucket_start(event.at.date_naive(), request.interval))
+            else {
+                continue;
+            };
+            totals.values(metric)[slot] += 1;
+
+            let group = match request.split_by {
+                Some(SplitBy::Category) => event.category,
+                Some(SplitBy::Grade) => event.grade,
+                None => None,
+            };
+            if let Some(group) = group {
+                // Books saved without a category still get their own line.
+                let group = if group.is_empty() {
+                    "Uncategorized".to_string()
+                } else {
+                    group
+                };
+                groups
+                    .entry(group.clone())
+                    .or_insert_with(|| SeriesLine::new(Some(group), buckets.len()))
+                    .values(metric)[slot] += 1;
+            }
+        }
+    }
+
+    let mut lines = vec![totals];
+    lines.extend(groups.into_values());
+
+    Ok(CirculationSeries {
+        interval: request.interval,
+        buckets,
+        lines,
+    })
+}
@@ -0,0 +1,217 @@
+use crate::models::LendingStatus;
+use crate::services::{books, lendings, statistics, students};
+use chrono::{DateTime, Utc};
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+use sqlx::{Pool, Sqlite};
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Failed to write spreadsheet: {0}")]
+    Xlsx(#[from] XlsxError),
+}
+
+struct Formats {
+    header: Format,
+    date: Format,
+    percent: Format,
+}
+
+impl Formats {
+    fn new() -> Self {
+        Self {
+            header: Format::new().set_bold(),
+            date: Format::new().set_num_format("yyyy-mm-dd hh:mm"),
+            percent: Format::new().set_num_format("0.0%"),
+        }
+    }
+}
+
+fn write_headers(
+    sheet: &mut Worksheet,
+    formats: &Formats,
+    headers: &[(&str, f64)],
+) -> Result<(), XlsxError> {
+    for (col, (title, width)) in headers.iter().enumerate() {
+        let col = col as u16;
+        sheet.write_string_with_format(0, col, *title, &formats.header)?;
+        sheet.set_column_width(col, *width)?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    Ok(())
+}
+
+fn write_date(
+    sheet: &mut Worksheet,
+    formats: &Formats,
+    row: u32,
+    col: u16,
+    value: Option<&DateTime<Utc>>,
+) -> Result<(), XlsxError> {
+    if let Some(value) = value {
+        sheet.write_datetime_with_format(row, col, &value.naive_utc(), &formats.date)?;
+    }
+    Ok(())
+}
+
+pub async fn export_workbook(pool: &Pool<Sqlite>, path: &Path) -> Result<(), ExportError> {
+    let formats = Formats::new();
+    let mut workbook = Workbook::new();
+
+    let all_categories = statistics::StatsFilter {
+        limit: Some(i64::MAX),
+        ..Default::default()
+    };
+    let stats = statistics::get_dashboard_stats(pool, &all_categories).await?;
+    let sheet = workbook.add_worksheet().set_name("Summary")?;
+    write_headers(sheet, &formats, &[("Metric", 24.0), ("Value", 14.0)])?;
+    let current = &stats.current;
+    let totals = [
+        ("Total students", current.total_students),
+        ("Total books", current.total_books),
+        ("Available books", current.available_books),
+        ("Books on loan", current.books_on_loan),
+        ("Overdue books", current.overdue_books),
+        ("Loans (all time)", stats.historical.total_loans),
+    ];
+    let mut row = 1;
+    for (label, value) in totals {
+        sheet.write_string(row, 0, label)?;
+        sheet.write_number(row, 1, value as f64)?;
+        row += 1;
+    }
+    sheet.write_string(row, 0, "Utilization rate")?;
+    sheet.write_number_with_format(
+        row,
+        1,
+        current.utilization_rate / 100.0,
+        &formats.percent,
+    )?;
+    row += 2;
+    sheet.write_string_with_format(row, 0, "Category", &formats.header)?;
+    sheet.write_string_with_format(row, 1, "Loans (all time)", &formats.header)?;
+    sheet.write_string_with_format(row, 2, "Share", &formats.header)?;
+    for category in &stats.historical.loans_by_category {
+        row += 1;
+        sheet.write_string(row, 0, &category.name)?;
+        sheet.write_number(row, 1, category.count as f64)?;
+        sheet.write_number_with_format(
+            row,
+            2,
+            category.percentage / 100.0,
+            &formats.percent,
+        )?;
+    }
+
+    let all_books = books::get_all_books(pool).await?;
+    let sheet = workbook.add_worksheet().set_name("Books")?;
+    write_headers(
+        sheet,
+        &formats,
+        &[
+            ("Title", 36.0),
+            ("Author", 24.0),
+            ("ISBN", 18.0),
+            ("Category", 18.0),
+            ("Quantity", 10.0),
+            ("Status", 12.0),
+            ("Added", 18.0),
+        ],
+    )?;
+    for (i, book) in all_books.iter().enumerate() {
+        let row = i as u32 + 1;
+        sheet.write_string(row, 0, &book.title)?;
+        sheet.write_string(row, 1, &book.author)?;
+        sheet.write_string(row, 2, &book.isbn)?;
+        sheet.write_string(row, 3, &book.category)?;
+        sheet.write_number(row, 4, book.quantity)?;
+        sheet.write_string(row, 5, book.status.to_string())?;
+        write_date(sheet, &formats, row, 6, Some(&book.created_at))?;
+    }
+
+    let all_students = students::get_all_students(pool).await?;
+    let sheet = workbook.add_worksheet().set_name("Students")?;
+    write_headers(
+        sheet,
+        &formats,
+        &[
+            ("Student ID", 14.0),
+            ("Name", 28.0),
+            ("Grade", 12.0),
+            ("Phone number", 18.0),
+            ("Status", 12.0),
+            ("Registered", 18.0),
+        ],
+    )?;
+    for (i, student) in all_students.iter().enumerate() {
+        let row = i as u32 + 1;
+        sheet.write_string(row, 0, &student.student_id)?;
+        sheet.write_string(row, 1, &student.name)?;
+        sheet.write_string(row, 2, &student.grade)?;
+        if let Some(phone_number) = &student.phone_number {
+            sheet.write_string(row, 3, phone_number)?;
+        }
+        sheet.write_string(row, 4, student.status.to_string())?;
+        write_date(sheet, &formats, row, 5, Some(&student.created_at))?;
+    }
+
+    let active_loans: Vec<_> = lendings::get_all_lendings(pool)
+        .await?
+        .into_iter()
+        .filter(|l| matches!(l.status, LendingStatus::Lent))
+        .collect();
+    let sheet = workbook.add_worksheet().set_name("Active Loans")?;
+    write_headers(
+        sheet,
+        &formats,
+        &[
+            ("Book", 36.0),
+            ("Author", 24.0),
+            ("Student", 28.0),
+            ("Student ID", 14.0),
+            ("Lent", 18.0),
+            ("Due", 18.0),
+        ],
+    )?;
+    for (i, lending) in active_loans.iter().enumerate() {
+        let row = i as u32 + 1;
+        sheet.write_string(row, 0, &lending.book_title)?;
+        sheet.write_string(row, 1, &lending.book_author)?;
+        sheet.write_string(row, 2, &lending.student_name)?;
+        sheet.write_string(row, 3, &lending.student_number)?;
+        write_date(sheet, &formats, row, 4, Some(&lending.lent_at))?;
+        write_date(sheet, &formats, row, 5, Some(&lending.due_date))?;
+    }
+
+    let overdue = statistics::get_overdue_books(pool, &statistics::StatsFilter::default()).await?;
+    let sheet = workbook.add_worksheet().set_name("Overdue")?;
+    write_headers(
+        sheet,
+        &formats,
+        &[
+            ("Book", 36.0),
+            ("Author", 24.0),
+            ("Student", 28.0),
+            ("Grade", 12.0),
+            ("Student ID", 14.0),
+            ("Due", 18.0),
+            ("Days overdue", 14.0),
+        ],
+    )?;
+    for (i, book) in overdue.iter().enumerate() {
+        let row = i as u32 + 1;
+        sheet.write_string(row, 0, &book.book_title)?;
+        sheet.write_string(row, 1, &book.author)?;
+        sheet.write_string(row, 2, &book.student_name)?;
+        sheet.write_string(row, 3, &book.grade)?;
+        sheet.write_string(row, 4, &book.student_id)?;
+        write_date(sheet, &formats, row, 5, Some(&book.due_date))?;
+        sheet.write_number(row, 6, book.days_overdue as f64)?;
+    }
+
+    workbook.save(path)?;
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use common::{test_pool, BookFixture, StudentFixture};
+use reqwest::StatusCode;
+use sqlx::{Pool, Sqlite};
+use std::sync::Arc;
+use tiza_lib::api::{self, ApiState};
+
+const TOKEN: &str = "secret-token";
+
+/// Serves the API over `pool` on a free local port and returns its base URL.
+async fn serve(pool: Pool<Sqlite>, token: &str) -> String {
+    let state = ApiState {
+        pool: Arc::new(move || pool.clone()),
+        token: token.into(),
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, api::router(state)).await });
+    format!("http://127.0.0.1:{}/api", port)
+}
+
+async fn get(url: &str, token: Option<&str>) -> StatusCode {
+    let mut request = reqwest::Client::new().get(url);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    request.send().await.unwrap().status()
+}
+
+#[tokio::test]
+async fn requests_need_the_configured_token() {
+    let base = serve(test_pool().await, TOKEN).await;
+    let books = format!("{}/books", base);
+
+    assert_eq!(get(&books, None).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(get(&books, Some("wrong-token")).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(get(&books, Some(TOKEN)).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn an_empty_token_locks_everything() {
+    let base = serve(test_pool().await, "").await;
+    let books = format!("{}/books", base);
+
+    assert_eq!(get(&books, None).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(get(&books, Some("")).await, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn the_openapi_document_is_public() {
+    let base = serve(test_pool().await, TOKEN).await;
+    let response = reqwest::get(format!("{}/openapi.json", base)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let document: serde_json::Value =
+        serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    assert!(document["paths"]["/lendings"].is_object());
+}
+
+#[tokio::test]
+async fn lending_past_the_loan_limit_is_a_conflict() {
+    let pool = test_pool().await;
+    let book_id = BookFixture::default().quantity(5).insert(&pool).await;
+    let student_id = StudentFixture::default().insert(&pool).await;
+    let base = serve(pool, TOKEN).await;
+
+    let lend = || {
+        reqwest::Client::new()
+            .post(format!("{}/lendings", base))
+            .bearer_auth(TOKEN)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(serde_json::json!({ "book_id": book_id, "student_id": student_id }).to_string())
+            .send()
+    };
+
+    assert_eq!(lend().await.unwrap().status(), StatusCode::CREATED);
+    assert_eq!(lend().await.unwrap().status(), StatusCode::CONFLICT);
+}
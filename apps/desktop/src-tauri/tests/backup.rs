@@ -0,0 +1,110 @@
+mod common;
+
+use common::{get_book, test_pool, BookFixture};
+use std::path::PathBuf;
+use tiza_lib::db::Database;
+use tiza_lib::services::backup;
+
+/// A fresh, empty directory under the system temp dir.
+fn scratch_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tiza-backup-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[tokio::test]
+async fn a_new_backup_replaces_the_previous_one() {
+    let pool = test_pool().await;
+    BookFixture::default().insert(&pool).await;
+    let dir = scratch_dir();
+    let destination = dir.join("library.db");
+
+    backup::create_backup(&pool, &destination, "0.1.0").await.unwrap();
+    BookFixture::default().title("Second book").insert(&pool).await;
+    backup::create_backup(&pool, &destination, "0.1.0").await.unwrap();
+
+    assert!(backup::validate_backup(&destination).await.is_ok());
+    let mut names: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["library.db", "library.db.json"]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn a_failed_backup_keeps_the_previous_one() {
+    let pool = test_pool().await;
+    let dir = scratch_dir();
+    let destination = dir.join("library.db");
+    backup::create_backup(&pool, &destination, "0.1.0").await.unwrap();
+    let previous = std::fs::read(&destination).unwrap();
+
+    // Nothing can be written where the staging file has to go.
+    std::fs::create_dir(dir.join("library.db.partial")).unwrap();
+    assert!(backup::create_backup(&pool, &destination, "0.1.0")
+        .await
+        .is_err());
+
+    assert_eq!(std::fs::read(&destination).unwrap(), previous);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn encrypted_backups_leave_no_plaintext_behind() {
+    let pool = test_pool().await;
+    BookFixture::default().insert(&pool).await;
+    let staging = scratch_dir();
+    let usb = scratch_dir();
+    let destination = usb.join("library.db.enc");
+
+    let metadata =
+        backup::create_encrypted_backup(&pool, &destination, &staging, "0.1.0", "correct horse")
+            .await
+            .unwrap();
+    assert!(metadata.encrypted);
+
+    let mut names: Vec<String> = std::fs::read_dir(&usb)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["library.db.enc", "library.db.enc.json"]);
+    assert_eq!(std::fs::read_dir(&staging).unwrap().count(), 0);
+
+    std::fs::remove_dir_all(staging).unwrap();
+    std::fs::remove_dir_all(usb).unwrap();
+}
+
+#[tokio::test]
+async fn a_failed_restore_leaves_the_previous_database_open() {
+    let dir = scratch_dir();
+    let db_path = dir.join("library.db");
+    let pool = tiza_lib::db::connect(&db_path).await.unwrap();
+    let book_id = BookFixture::default().insert(&pool).await;
+    let database = Database::new(pool);
+
+    // A backup that passes validation but can't be migrated on open.
+    let backup_path = dir.join("tampered.db");
+    backup::create_backup(&database.pool(), &backup_path, "0.1.0")
+        .await
+        .unwrap();
+    let tampered = tiza_lib::db::connect(&backup_path).await.unwrap();
+    sqlx::query("UPDATE _sqlx_migrations SET checksum = x'00'")
+        .execute(&tampered)
+        .await
+        .unwrap();
+    tampered.close().await;
+
+    let result = backup::restore_backup(&database, &backup_path, &db_path, "0.1.0", None).await;
+    assert!(result.is_err());
+
+    let pool = database.pool();
+    assert!(!pool.is_closed());
+    assert_eq!(get_book(&pool, &book_id).await.id, book_id);
+
+    pool.close().await;
+    std::fs::remove_dir_all(dir).unwrap();
+}
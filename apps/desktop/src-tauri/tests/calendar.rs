@@ -0,0 +1,53 @@
+mod common;
+
+use chrono::NaiveDate;
+use common::test_pool;
+use tiza_lib::services::{calendar, settings};
+
+#[tokio::test]
+async fn ics_times_are_read_in_their_own_zone() {
+    let pool = test_pool().await;
+    let mut current = settings::get_settings(&pool).await.unwrap();
+    current.timezone = "Africa/Kigali".to_string();
+    settings::update_settings(&pool, &current).await.unwrap();
+
+    // 23:00 UTC and 20:00 in New York are both the next day in Kigali (UTC+2).
+    let ics = "BEGIN:VCALENDAR\r\n\
+        BEGIN:VEVENT\r\n\
+        SUMMARY:Christmas\r\n\
+        DTSTART:20251224T230000Z\r\n\
+        DTEND:20251224T235900Z\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        SUMMARY:New Year\r\n\
+        DTSTART;TZID=America/New_York:20251231T200000\r\n\
+        DTEND;TZID=America/New_York:20251231T210000\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        SUMMARY:Heroes Day\r\n\
+        DTSTART;VALUE=DATE:20260201\r\n\
+        DTEND;VALUE=DATE:20260202\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+    let path = std::env::temp_dir().join(format!("tiza-{}.ics", uuid::Uuid::new_v4()));
+    std::fs::write(&path, ics).unwrap();
+
+    assert_eq!(calendar::import_ics(&pool, &path).await.unwrap(), 3);
+
+    let closures = calendar::get_calendar(&pool).await.unwrap().closures;
+    let dates: Vec<(NaiveDate, NaiveDate)> = closures
+        .iter()
+        .map(|closure| (closure.start_date, closure.end_date))
+        .collect();
+    let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    assert_eq!(
+        dates,
+        [
+            (day(2025, 12, 25), day(2025, 12, 25)),
+            (day(2026, 1, 1), day(2026, 1, 1)),
+            (day(2026, 2, 1), day(2026, 2, 1)),
+        ]
+    );
+
+    std::fs::remove_file(path).unwrap();
+}
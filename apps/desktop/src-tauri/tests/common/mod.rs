@@ -0,0 +1,262 @@
+//! Shared set-up for the integration tests: a migrated in-memory database and
+//! builders that insert rows with sensible defaults.
+
+#![allow(dead_code)]
+
+pub mod smtp;
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{Pool, Sqlite};
+use tiza_lib::db::{self, DbConfig};
+use tiza_lib::models::Book;
+use tiza_lib::services;
+use tiza_lib::services::calendar::OpeningHours;
+use uuid::Uuid;
+
+/// A fresh database with every migration applied, as on a first run.
+pub async fn test_pool() -> Pool<Sqlite> {
+    db::open(&DbConfig::in_memory())
+        .await
+        .expect("failed to open in-memory database")
+}
+
+/// Opens the library every day of the week, so overdue counts don't depend
+/// on which weekday the tests run.
+pub async fn open_every_day(pool: &Pool<Sqlite>) {
+    let hours: Vec<OpeningHours> = (0..7)
+        .map(|weekday| OpeningHours {
+            weekday,
+            is_open: true,
+            opens_at: Some("08:00".to_string()),
+            closes_at: Some("17:00".to_string()),
+        })
+        .collect();
+    services::calendar::set_opening_hours(pool, &hours)
+        .await
+        .expect("failed to set opening hours");
+}
+
+pub async fn get_book(pool: &Pool<Sqlite>, id: &str) -> Book {
+    services::books::get_book_by_id(pool, id)
+        .await
+        .expect("failed to load book")
+        .expect("book does not exist")
+}
+
+/// Id of the student's loan that is still out.
+pub async fn active_loan_id(pool: &Pool<Sqlite>, student_id: &str) -> String {
+    sqlx::query_scalar("SELECT id FROM lent WHERE student_id = ? AND status = 'lent'")
+        .bind(student_id)
+        .fetch_one(pool)
+        .await
+        .expect("student has no active loan")
+}
+
+pub async fn set_due_date(pool: &Pool<Sqlite>, loan_id: &str, due_date: DateTime<Utc>) {
+    sqlx::query("UPDATE lent SET due_date = ? WHERE id = ?")
+        .bind(due_date)
+        .bind(loan_id)
+        .execute(pool)
+        .await
+        .expect("failed to update due date");
+}
+
+pub struct BookFixture {
+    title: String,
+    author: String,
+    quantity: i64,
+    category: String,
+}
+
+impl Default for BookFixture {
+    fn default() -> Self {
+        Self {
+            title: "Things Fall Apart".to_string(),
+            author: "Chinua Achebe".to_string(),
+            quantity: 1,
+            category: "Fiction".to_string(),
+        }
+    }
+}
+
+impl BookFixture {
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn quantity(mut self, quantity: i64) -> Self {
+        self.quantity = quantity;
+        self
+    }
+
+    pub fn category(mut self, category: &str) -> Self {
+        self.category = category.to_string();
+        self
+    }
+
+    pub async fn insert(self, pool: &Pool<Sqlite>) -> String {
+        let id = Uuid::new_v4().to_string();
+        let status = if self.quantity > 0 {
+            "available"
+        } else {
+            "unavailable"
+        };
+        sqlx::query(
+            "INSERT INTO books (id, title, author, quantity, isbn, category, status, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(&self.title)
+        .bind(&self.author)
+        .bind(self.quantity)
+        .bind(format!("978-{}", &id[..8]))
+        .bind(&self.category)
+        .bind(status)
+        .bind(Utc::now())
+        .execute(pool)
+        .await
+        .expect("failed to insert book");
+        id
+    }
+}
+
+pub struct StudentFixture {
+    name: String,
+    grade: String,
+    phone_number: Option<String>,
+    email: Option<String>,
+    guardian_email: Option<String>,
+    sms_opt_out: bool,
+}
+
+impl Default for StudentFixture {
+    fn default() -> Self {
+        Self {
+            name: "Aline Uwase".to_string(),
+            grade: "Grade 5".to_string(),
+            phone_number: None,
+            email: None,
+            guardian_email: None,
+            sms_opt_out: false,
+        }
+    }
+}
+
+impl StudentFixture {
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    pub fn grade(mut self, grade: &str) -> Self {
+        self.grade = grade.to_string();
+        self
+    }
+
+    pub fn phone_number(mut self, phone_number: &str) -> Self {
+        self.phone_number = Some(phone_number.to_string());
+        self
+    }
+
+    pub fn sms_opt_out(mut self) -> Self {
+        self.sms_opt_out = true;
+        self
+    }
+
+    pub fn email(mut self, email: &str) -> Self {
+        self.email = Some(email.to_string());
+        self
+    }
+
+    pub fn guardian_email(mut self, email: &str) -> Self {
+        self.guardian_email = Some(email.to_string());
+        self
+    }
+
+    pub async fn insert(self, pool: &Pool<Sqlite>) -> String {
+        let id = Uuid::new_v4().to_string();
+        sqlx::query(
+            "INSERT INTO students
+                (id, name, grade, phone_number, email, guardian_email, sms_opt_out,
+                 student_id, status, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, 'active', ?)",
+        )
+        .bind(&id)
+        .bind(&self.name)
+        .bind(&self.grade)
+        .bind(&self.phone_number)
+        .bind(&self.email)
+        .bind(&self.guardian_email)
+        .bind(self.sms_opt_out)
+        .bind(format!("S-{}", &id[..8]))
+        .bind(Utc::now())
+        .execute(pool)
+        .await
+        .expect("failed to insert student");
+        id
+    }
+}
+
+/// Inserts a loan row as-is, without the inventory bookkeeping that
+/// `create_lending` and `return_lending` do. Use it to set up history; go
+/// through the services when the book quantities matter.
+pub struct LoanFixture {
+    book_id: String,
+    student_id: String,
+    lent_at: DateTime<Utc>,
+    due_date: DateTime<Utc>,
+    returned_at: Option<DateTime<Utc>>,
+}
+
+impl LoanFixture {
+    pub fn new(book_id: &str, student_id: &str) -> Self {
+        let lent_at = Utc::now();
+        Self {
+            book_id: book_id.to_string(),
+            student_id: student_id.to_string(),
+            lent_at,
+            due_date: lent_at + Duration::days(14),
+            returned_at: None,
+        }
+    }
+
+    pub fn lent_at(mut self, lent_at: DateTime<Utc>) -> Self {
+        self.lent_at = lent_at;
+        self
+    }
+
+    pub fn due_date(mut self, due_date: DateTime<Utc>) -> Self {
+        self.due_date = due_date;
+        self
+    }
+
+    pub fn returned_at(mut self, returned_at: DateTime<Utc>) -> Self {
+        self.returned_at = Some(returned_at);
+        self
+    }
+
+    pub async fn insert(self, pool: &Pool<Sqlite>) -> String {
+        let id = Uuid::new_v4().to_string();
+        let status = if self.returned_at.is_some() {
+            "returned"
+        } else {
+            "lent"
+        };
+        sqlx::query(
+            "INSERT INTO lent (id, book_id, student_id, lent_at, returned_at, status, due_date)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(&self.book_id)
+        .bind(&self.student_id)
+        .bind(self.lent_at)
+        .bind(self.returned_at)
+        .bind(status)
+        .bind(self.due_date)
+        .execute(pool)
+        .await
+        .expect("failed to insert loan");
+        id
+    }
+}
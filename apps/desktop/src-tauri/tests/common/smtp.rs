@@ -0,0 +1,99 @@
+//! A minimal SMTP server on localhost that accepts every message and keeps
+//! it in memory, so email can be tested without a real mail server.
+
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Debug, Clone)]
+pub struct ReceivedMail {
+    pub from: String,
+    pub to: Vec<String>,
+    /// Headers and body as sent after DATA.
+    pub data: String,
+}
+
+pub struct SmtpStandIn {
+    pub port: u16,
+    received: Arc<Mutex<Vec<ReceivedMail>>>,
+}
+
+impl SmtpStandIn {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind SMTP stand-in");
+        let port = listener.local_addr().unwrap().port();
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let store = received.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, store.clone()));
+            }
+        });
+
+        Self { port, received }
+    }
+
+    pub fn received(&self) -> Vec<ReceivedMail> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+fn address(argument: &str) -> String {
+    argument
+        .split_once(':')
+        .map(|(_, address)| address)
+        .unwrap_or_default()
+        .trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .to_string()
+}
+
+async fn serve(stream: TcpStream, received: Arc<Mutex<Vec<ReceivedMail>>>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut from = String::new();
+    let mut to = Vec::new();
+
+    let _ = writer.write_all(b"220 localhost SMTP stand-in\r\n").await;
+    while let Ok(Some(line)) = lines.next_line().await {
+        let command = line.to_ascii_uppercase();
+        let reply: &[u8] = if command.starts_with("EHLO") || command.starts_with("HELO") {
+            b"250 localhost\r\n"
+        } else if command.starts_with("MAIL FROM") {
+            from = address(&line);
+            to.clear();
+            b"250 OK\r\n"
+        } else if command.starts_with("RCPT TO") {
+            to.push(address(&line));
+            b"250 OK\r\n"
+        } else if command == "DATA" {
+            let _ = writer.write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n").await;
+            let mut data = String::new();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line == "." {
+                    break;
+                }
+                data.push_str(line.strip_prefix('.').unwrap_or(&line));
+                data.push('\n');
+            }
+            received.lock().unwrap().push(ReceivedMail {
+                from: from.clone(),
+                to: std::mem::take(&mut to),
+                data,
+            });
+            b"250 OK\r\n"
+        } else if command == "QUIT" {
+            let _ = writer.write_all(b"221 Bye\r\n").await;
+            break;
+        } else {
+            b"250 OK\r\n"
+        };
+        if writer.write_all(reply).await.is_err() {
+            break;
+        }
+    }
+}
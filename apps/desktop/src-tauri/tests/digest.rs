@@ -0,0 +1,78 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use common::{open_every_day, test_pool, BookFixture, LoanFixture, StudentFixture};
+use std::path::Path;
+use tiza_lib::services::backup::{self, BackupError, BackupMetadata};
+use tiza_lib::services::digest;
+
+#[tokio::test]
+async fn digest_lists_due_and_newly_overdue_loans() {
+    let pool = test_pool().await;
+    open_every_day(&pool).await;
+    let book_id = BookFixture::default().quantity(5).insert(&pool).await;
+    let student_id = StudentFixture::default().insert(&pool).await;
+
+    let now = Utc::now();
+    let due_today = LoanFixture::new(&book_id, &student_id)
+        .due_date(now)
+        .insert(&pool)
+        .await;
+    let newly_overdue = LoanFixture::new(&book_id, &student_id)
+        .due_date(now - Duration::days(1))
+        .insert(&pool)
+        .await;
+    for due_date in [now - Duration::days(5), now + Duration::days(3)] {
+        LoanFixture::new(&book_id, &student_id)
+            .due_date(due_date)
+            .insert(&pool)
+            .await;
+    }
+
+    let digest = digest::build_digest(&pool).await.unwrap();
+    assert_eq!(digest.due_today.len(), 1);
+    assert_eq!(digest.due_today[0].id, due_today);
+    assert_eq!(digest.newly_overdue.len(), 1);
+    assert_eq!(digest.newly_overdue[0].id, newly_overdue);
+    assert_eq!(digest.overdue_total, 2);
+    assert!(digest.backup_missing);
+    assert_eq!(digest.summary().len(), 3);
+}
+
+#[tokio::test]
+async fn a_recent_backup_is_not_missing() {
+    let pool = test_pool().await;
+    let failed: Result<BackupMetadata, BackupError> =
+        Err(BackupError::Io(std::io::Error::other("disk full")));
+    backup::record_backup(&pool, "auto", Path::new("/tmp/failed.db"), &failed)
+        .await
+        .unwrap();
+    assert!(digest::build_digest(&pool).await.unwrap().backup_missing);
+
+    let succeeded = Ok(BackupMetadata {
+        app_version: "0.1.0".to_string(),
+        schema_version: None,
+        created_at: Utc::now(),
+        size_bytes: 1024,
+        integrity_check: "ok".to_string(),
+        encrypted: false,
+    });
+    backup::record_backup(&pool, "manual", Path::new("/tmp/library.db"), &succeeded)
+        .await
+        .unwrap();
+
+    let digest = digest::build_digest(&pool).await.unwrap();
+    assert!(!digest.backup_missing);
+    assert_eq!(digest.summary(), ["Nothing due today and no new overdue loans"]);
+}
+
+#[tokio::test]
+async fn digest_date_is_remembered() {
+    let pool = test_pool().await;
+    assert_eq!(digest::last_digest_date(&pool).await.unwrap(), None);
+
+    let today = Utc::now().date_naive();
+    digest::record_digest_date(&pool, today).await.unwrap();
+    digest::record_digest_date(&pool, today).await.unwrap();
+    assert_eq!(digest::last_digest_date(&pool).await.unwrap(), Some(today));
+}
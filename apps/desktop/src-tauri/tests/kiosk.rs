@@ -0,0 +1,78 @@
+mod common;
+
+use common::{active_loan_id, get_book, test_pool, BookFixture, StudentFixture};
+use tiza_lib::kiosk::Kiosk;
+use tiza_lib::services::kiosk::{self, KioskError};
+use tiza_lib::services::lendings;
+
+#[test]
+fn kiosk_mode_only_allows_kiosk_commands() {
+    let kiosk = Kiosk::new(true);
+    for command in ["kiosk_login", "kiosk_checkout", "kiosk_renew", "exit_kiosk_mode"] {
+        assert!(kiosk.allows(command), "{} should be allowed", command);
+    }
+    for command in ["get_all_students", "update_settings", "delete_book", "restore_database"] {
+        assert!(!kiosk.allows(command), "{} should be refused", command);
+    }
+
+    assert!(Kiosk::new(false).allows("update_settings"));
+}
+
+#[tokio::test]
+async fn students_can_only_return_and_renew_their_own_loans() {
+    let pool = test_pool().await;
+    let book_id = BookFixture::default().insert(&pool).await;
+    let owner = StudentFixture::default().insert(&pool).await;
+    let other = StudentFixture::default().name("Eric Mugisha").insert(&pool).await;
+    lendings::create_lending(&pool, &book_id, &owner, None)
+        .await
+        .unwrap();
+    let loan_id = active_loan_id(&pool, &owner).await;
+
+    let returned = kiosk::return_loan(&pool, &other, &loan_id).await;
+    assert!(matches!(returned, Err(KioskError::NotYourLoan)));
+    let renewed = kiosk::renew_loan(&pool, &other, &loan_id).await;
+    assert!(matches!(renewed, Err(KioskError::NotYourLoan)));
+    assert_eq!(active_loan_id(&pool, &owner).await, loan_id);
+
+    kiosk::return_loan(&pool, &owner, &loan_id).await.unwrap();
+    assert_eq!(get_book(&pool, &book_id).await.quantity, 1);
+}
+
+#[tokio::test]
+async fn checkout_refuses_a_book_with_no_copies_left() {
+    let pool = test_pool().await;
+    let book_id = BookFixture::default().quantity(0).insert(&pool).await;
+    let student_id = StudentFixture::default().insert(&pool).await;
+
+    let result = kiosk::checkout(&pool, &student_id, &book_id).await;
+
+    assert!(matches!(result, Err(KioskError::Unavailable)));
+    assert_eq!(get_book(&pool, &book_id).await.quantity, 0);
+}
+
+#[tokio::test]
+async fn wrong_pins_lock_the_kiosk_out() {
+    let pool = test_pool().await;
+    kiosk::lock(&pool, "2468").await.unwrap();
+
+    for _ in 0..5 {
+        assert!(matches!(
+            kiosk::unlock(&pool, "1111").await,
+            Err(KioskError::WrongPin)
+        ));
+    }
+    // Even the right PIN is refused until the lockout has passed.
+    assert!(matches!(
+        kiosk::unlock(&pool, "2468").await,
+        Err(KioskError::LockedOut(5))
+    ));
+    assert!(kiosk::is_locked(&pool).await.unwrap());
+
+    sqlx::query("DELETE FROM settings WHERE key = 'kiosk_pin_locked_until'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    kiosk::unlock(&pool, "2468").await.unwrap();
+    assert!(!kiosk::is_locked(&pool).await.unwrap());
+}
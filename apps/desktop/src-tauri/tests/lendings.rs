@@ -0,0 +1,220 @@
+mod common;
+
+use chrono::{Duration, NaiveTime, Utc};
+use common::{
+    active_loan_id, get_book, open_every_day, test_pool, BookFixture, LoanFixture, StudentFixture,
+};
+use tiza_lib::models::BookStatus;
+use tiza_lib::services::calendar::LibraryCalendar;
+use tiza_lib::services::clock::LibraryClock;
+use tiza_lib::services::lendings::{self, LendingError, RenewError};
+use tiza_lib::services::settings;
+
+#[tokio::test]
+async fn lending_takes_a_copy_out_and_returning_puts_it_back() {
+    let pool = test_pool().await;
+    let book_id = BookFixture::default().quantity(2).insert(&pool).await;
+    let student_id = StudentFixture::default().insert(&pool).await;
+
+    lendings::create_lending(&pool, &book_id, &student_id, None)
+        .await
+        .unwrap();
+
+    let book = get_book(&pool, &book_id).await;
+    assert_eq!(book.quantity, 1);
+    assert!(matches!(book.status, BookStatus::Available));
+
+    let loan_id = active_loan_id(&pool, &student_id).await;
+    lendings::return_lending(&pool, &loan_id).await.unwrap();
+
+    let book = get_book(&pool, &book_id).await;
+    assert_eq!(book.quantity, 2);
+    assert!(matches!(book.status, BookStatus::Available));
+
+    let loan = lendings::get_lending_by_id(&pool, &loan_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(loan.status.to_string(), "returned");
+    assert!(loan.returned_at.is_some());
+}
+
+#[tokio::test]
+async fn lending_the_last_copy_makes_the_book_unavailable() {
+    let pool = test_pool().await;
+    let book_id = BookFixture::default().quantity(1).insert(&pool).await;
+    let student_id = StudentFixture::default().insert(&pool).await;
+
+    lendings::create_lending(&pool, &book_id, &student_id, None)
+        .await
+        .unwrap();
+
+    let book = get_book(&pool, &book_id).await;
+    assert_eq!(book.quantity, 0);
+    assert!(matches!(book.status, BookStatus::Unavailable));
+
+    let loan_id = active_loan_id(&pool, &student_id).await;
+    lendings::return_lending(&pool, &loan_id).await.unwrap();
+
+    let book = get_book(&pool, &book_id).await;
+    assert_eq!(book.quantity, 1);
+    assert!(matches!(book.status, BookStatus::Available));
+}
+
+#[tokio::test]
+async fn a_student_can_only_have_one_active_loan() {
+    let pool = test_pool().await;
+    let first_book = BookFixture::default().insert(&pool).await;
+    let second_book = BookFixture::default()
+        .title("Half of a Yellow Sun")
+        .quantity(3)
+        .insert(&pool)
+        .await;
+    let student_id = StudentFixture::default().insert(&pool).await;
+
+    lendings::create_lending(&pool, &first_book, &student_id, None)
+        .await
+        .unwrap();
+
+    let second = lendings::create_lending(&pool, &second_book, &student_id, None).await;
+    assert!(matches!(second, Err(LendingError::LoanLimit(1))));
+    // The refused loan must not touch the inventory.
+    assert_eq!(get_book(&pool, &second_book).await.quantity, 3);
+
+    // Once the first book is back, the student can borrow again.
+    let loan_id = active_loan_id(&pool, &student_id).await;
+    lendings::return_lending(&pool, &loan_id).await.unwrap();
+    lendings::create_lending(&pool, &second_book, &student_id, None)
+        .await
+        .unwrap();
+    assert_eq!(get_book(&pool, &second_book).await.quantity, 2);
+}
+
+#[tokio::test]
+async fn the_loan_limit_follows_the_settings() {
+    let pool = test_pool().await;
+    let book_id = BookFixture::default().quantity(5).insert(&pool).await;
+    let student_id = StudentFixture::default().insert(&pool).await;
+
+    let mut current = settings::get_settings(&pool).await.unwrap();
+    current.max_loans_per_student = 2;
+    settings::update_settings(&pool, &current).await.unwrap();
+
+    for _ in 0..2 {
+        lendings::create_lending(&pool, &book_id, &student_id, None)
+            .await
+            .unwrap();
+    }
+    let third = lendings::create_lending(&pool, &book_id, &student_id, None).await;
+
+    assert!(matches!(third, Err(LendingError::LoanLimit(2))));
+    assert_eq!(get_book(&pool, &book_id).await.quantity, 3);
+}
+
+#[tokio::test]
+async fn default_due_date_is_closing_time_on_an_open_day_after_the_loan_period() {
+    let pool = test_pool().await;
+    let book_id = BookFixture::default().insert(&pool).await;
+    let student_id = StudentFixture::default().insert(&pool).await;
+
+    lendings::create_lending(&pool, &book_id, &student_id, None)
+        .await
+        .unwrap();
+
+    let loan_id = active_loan_id(&pool, &student_id).await;
+    let loan = lendings::get_lending_by_id(&pool, &loan_id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let clock = LibraryClock::load(&pool).await.unwrap();
+    let calendar = LibraryCalendar::load(&pool).await.unwrap();
+    let due_day = clock.local_date(loan.due_date);
+    let loan_period = settings::get_settings(&pool).await.unwrap().loan_period_days;
+
+    assert!(due_day >= clock.local_date(loan.lent_at) + Duration::days(loan_period));
+    assert!(calendar.is_open(due_day));
+    assert_eq!(loan.due_date, clock.due_time(due_day));
+    // The library's timezone defaults to UTC and it closes at 17:00.
+    assert_eq!(loan.due_date.time(), NaiveTime::from_hms_opt(17, 0, 0).unwrap());
+}
+
+#[tokio::test]
+async fn a_book_with_no_copies_left_cannot_be_lent() {
+    let pool = test_pool().await;
+    let book_id = BookFixture::default().quantity(0).insert(&pool).await;
+    let student_id = StudentFixture::default().insert(&pool).await;
+
+    let result = lendings::create_lending(&pool, &book_id, &student_id, None).await;
+
+    assert!(matches!(result, Err(LendingError::Unavailable)));
+    assert_eq!(get_book(&pool, &book_id).await.quantity, 0);
+    let loans = lendings::get_lending_records_by_student_id(&pool, &student_id)
+        .await
+        .unwrap();
+    assert!(loans.is_empty());
+}
+
+#[tokio::test]
+async fn returning_a_loan_twice_only_puts_the_copy_back_once() {
+    let pool = test_pool().await;
+    let book_id = BookFixture::default().quantity(1).insert(&pool).await;
+    let student_id = StudentFixture::default().insert(&pool).await;
+    lendings::create_lending(&pool, &book_id, &student_id, None)
+        .await
+        .unwrap();
+    let loan_id = active_loan_id(&pool, &student_id).await;
+
+    lendings::return_lending(&pool, &loan_id).await.unwrap();
+    let again = lendings::return_lending(&pool, &loan_id).await;
+
+    assert!(matches!(again, Err(LendingError::NotLent)));
+    assert_eq!(get_book(&pool, &book_id).await.quantity, 1);
+}
+
+#[tokio::test]
+async fn overdue_loans_cannot_be_renewed() {
+    let pool = test_pool().await;
+    open_every_day(&pool).await;
+    let book_id = BookFixture::default().insert(&pool).await;
+    let student_id = StudentFixture::default().insert(&pool).await;
+    let loan_id = LoanFixture::new(&book_id, &student_id)
+        .due_date(Utc::now() - Duration::days(3))
+        .insert(&pool)
+        .await;
+
+    let result = lendings::renew_lending(&pool, &loan_id).await;
+    assert!(matches!(result, Err(RenewError::Overdue)));
+}
+
+#[tokio::test]
+async fn renewals_stop_at_the_limit() {
+    let pool = test_pool().await;
+    open_every_day(&pool).await;
+    let book_id = BookFixture::default().insert(&pool).await;
+    let student_id = StudentFixture::default().insert(&pool).await;
+    let loan_id = LoanFixture::new(&book_id, &student_id).insert(&pool).await;
+
+    let max_renewals = settings::get_settings(&pool).await.unwrap().max_renewals;
+    for _ in 0..max_renewals {
+        lendings::renew_lending(&pool, &loan_id).await.unwrap();
+    }
+    let result = lendings::renew_lending(&pool, &loan_id).await;
+    assert!(matches!(result, Err(RenewError::LimitReached(max)) if max == max_renewals));
+}
+
+#[tokio::test]
+async fn renewing_never_brings_the_due_date_forward() {
+    let pool = test_pool().await;
+    open_every_day(&pool).await;
+    let book_id = BookFixture::default().insert(&pool).await;
+    let student_id = StudentFixture::default().insert(&pool).await;
+    let due_date = Utc::now() + Duration::days(60);
+    let loan_id = LoanFixture::new(&book_id, &student_id)
+        .due_date(due_date)
+        .insert(&pool)
+        .await;
+
+    let renewed = lendings::renew_lending(&pool, &loan_id).await.unwrap();
+    assert!(renewed >= due_date);
+}
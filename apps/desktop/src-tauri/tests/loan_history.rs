@@ -0,0 +1,43 @@
+mod common;
+
+use common::{test_pool, BookFixture, LoanFixture, StudentFixture};
+use tiza_lib::services::books::{self, DeleteBookError};
+use tiza_lib::services::students::{self, DeleteStudentError};
+
+#[tokio::test]
+async fn books_and_students_with_loans_are_not_deleted() {
+    let pool = test_pool().await;
+    let book_id = BookFixture::default().insert(&pool).await;
+    let student_id = StudentFixture::default().insert(&pool).await;
+    LoanFixture::new(&book_id, &student_id)
+        .returned_at(chrono::Utc::now())
+        .insert(&pool)
+        .await;
+
+    let book = books::delete_book(&pool, &book_id).await;
+    assert!(matches!(book, Err(DeleteBookError::HasLoans)));
+    let student = students::delete_student(&pool, &student_id).await;
+    assert!(matches!(student, Err(DeleteStudentError::HasLoans)));
+
+    assert!(books::get_book_by_id(&pool, &book_id).await.unwrap().is_some());
+    assert!(students::get_student_by_id(&pool, &student_id)
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[tokio::test]
+async fn books_and_students_without_loans_are_deleted() {
+    let pool = test_pool().await;
+    let book_id = BookFixture::default().insert(&pool).await;
+    let student_id = StudentFixture::default().insert(&pool).await;
+
+    books::delete_book(&pool, &book_id).await.unwrap();
+    students::delete_student(&pool, &student_id).await.unwrap();
+
+    assert!(books::get_book_by_id(&pool, &book_id).await.unwrap().is_none());
+    assert!(students::get_student_by_id(&pool, &student_id)
+        .await
+        .unwrap()
+        .is_none());
+}
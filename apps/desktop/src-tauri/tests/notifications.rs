@@ -0,0 +1,132 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use common::smtp::SmtpStandIn;
+use common::{open_every_day, test_pool, BookFixture, LoanFixture, StudentFixture};
+use sqlx::{Pool, Sqlite};
+use tiza_lib::services::email::Mailer;
+use tiza_lib::services::notifications;
+use tiza_lib::services::settings::{self, SmtpSecurity};
+
+async fn mailer_for_port(pool: &Pool<Sqlite>, port: u16) -> Mailer {
+    let mut settings = settings::get_settings(pool).await.unwrap();
+    settings.email_notifications = true;
+    settings.smtp_host = "127.0.0.1".to_string();
+    settings.smtp_port = port as i64;
+    settings.smtp_security = SmtpSecurity::None;
+    settings.smtp_from = "School Library <library@school.test>".to_string();
+    let settings = settings::update_settings(pool, &settings).await.unwrap();
+
+    Mailer::from_settings(&settings, "").unwrap()
+}
+
+#[tokio::test]
+async fn reminders_reach_students_and_guardians_once() {
+    let pool = test_pool().await;
+    open_every_day(&pool).await;
+    let book_id = BookFixture::default().quantity(5).insert(&pool).await;
+
+    let late = StudentFixture::default()
+        .email("aline@school.test")
+        .guardian_email("parent@home.test")
+        .insert(&pool)
+        .await;
+    let due_soon = StudentFixture::default()
+        .name("Eric Mugisha")
+        .email("eric@school.test")
+        .insert(&pool)
+        .await;
+    let not_due = StudentFixture::default()
+        .name("Grace Ineza")
+        .email("grace@school.test")
+        .insert(&pool)
+        .await;
+    let no_email = StudentFixture::default()
+        .name("Jean Habimana")
+        .insert(&pool)
+        .await;
+
+    let now = Utc::now();
+    for (student_id, due_date) in [
+        (&late, now - Duration::days(5)),
+        (&due_soon, now + Duration::days(1)),
+        (&not_due, now + Duration::days(10)),
+        (&no_email, now - Duration::days(5)),
+    ] {
+        LoanFixture::new(&book_id, student_id)
+            .due_date(due_date)
+            .insert(&pool)
+            .await;
+    }
+
+    let server = SmtpStandIn::start().await;
+    let mailer = mailer_for_port(&pool, server.port).await;
+
+    let run = notifications::send_email_notices(&pool, &mailer)
+        .await
+        .unwrap();
+    assert_eq!((run.sent, run.skipped, run.failed), (3, 0, 0));
+
+    let received = server.received();
+    let mail_to = |address: &str| {
+        received
+            .iter()
+            .find(|mail| mail.to == [address])
+            .unwrap_or_else(|| panic!("nothing sent to {}", address))
+    };
+    assert!(mail_to("aline@school.test").data.contains("Subject: Overdue:"));
+    assert!(mail_to("parent@home.test").data.contains("Subject: Overdue:"));
+    assert!(mail_to("eric@school.test").data.contains("Subject: Reminder:"));
+    assert_eq!(mail_to("eric@school.test").from, "library@school.test");
+    assert_eq!(received.len(), 3);
+
+    // The log stops the next run from sending the same reminders again.
+    let run = notifications::send_email_notices(&pool, &mailer)
+        .await
+        .unwrap();
+    assert_eq!((run.sent, run.skipped, run.failed), (0, 3, 0));
+    assert_eq!(server.received().len(), 3);
+
+    let log = notifications::get_notification_log(&pool, 10).await.unwrap();
+    assert_eq!(log.len(), 3);
+    assert!(log.iter().all(|entry| entry.success && entry.channel == "email"));
+}
+
+#[tokio::test]
+async fn failed_reminders_are_logged_and_retried() {
+    let pool = test_pool().await;
+    open_every_day(&pool).await;
+    let book_id = BookFixture::default().insert(&pool).await;
+    let student_id = StudentFixture::default()
+        .email("aline@school.test")
+        .insert(&pool)
+        .await;
+    LoanFixture::new(&book_id, &student_id)
+        .due_date(Utc::now() - Duration::days(3))
+        .insert(&pool)
+        .await;
+
+    // Nothing listens on a port that was just released.
+    let closed_port = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    let mailer = mailer_for_port(&pool, closed_port).await;
+    let run = notifications::send_email_notices(&pool, &mailer)
+        .await
+        .unwrap();
+    assert_eq!((run.sent, run.failed), (0, 1));
+
+    let log = notifications::get_notification_log(&pool, 10).await.unwrap();
+    assert_eq!(log.len(), 1);
+    assert!(!log[0].success);
+    assert!(log[0].message.is_some());
+
+    let server = SmtpStandIn::start().await;
+    let mailer = mailer_for_port(&pool, server.port).await;
+    let run = notifications::send_email_notices(&pool, &mailer)
+        .await
+        .unwrap();
+    assert_eq!((run.sent, run.skipped, run.failed), (1, 0, 0));
+    assert_eq!(server.received().len(), 1);
+}
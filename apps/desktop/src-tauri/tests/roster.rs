@@ -0,0 +1,59 @@
+mod common;
+
+use common::{test_pool, StudentFixture};
+use tiza_lib::services::roster::{self, ColumnMapping};
+use tiza_lib::services::students;
+
+fn mapping() -> ColumnMapping {
+    ColumnMapping {
+        student_id: "ID".to_string(),
+        name: "Name".to_string(),
+        grade: "Grade".to_string(),
+        phone_number: None,
+    }
+}
+
+#[tokio::test]
+async fn returning_students_are_reactivated() {
+    let pool = test_pool().await;
+    let id = StudentFixture::default().insert(&pool).await;
+    let student = students::get_student_by_id(&pool, &id).await.unwrap().unwrap();
+    sqlx::query("UPDATE students SET status = 'inactive' WHERE id = ?")
+        .bind(&id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let path = std::env::temp_dir().join(format!("tiza-roster-{}.csv", uuid::Uuid::new_v4()));
+    std::fs::write(
+        &path,
+        format!("ID,Name,Grade\n{},{},Grade 6\n", student.student_id, student.name),
+    )
+    .unwrap();
+
+    let diff = roster::preview_roster_import(&pool, &path, &mapping(), true)
+        .await
+        .unwrap();
+    let diff = serde_json::to_value(&diff).unwrap();
+    assert_eq!(diff["reactivated"].as_array().unwrap().len(), 1);
+    assert_eq!(diff["reactivated"][0]["new_grade"], "Grade 6");
+    assert_eq!(diff["updated"].as_array().unwrap().len(), 0);
+    assert_eq!(diff["unchanged"], 0);
+
+    roster::apply_roster_import(&pool, &path, &mapping(), true)
+        .await
+        .unwrap();
+    let student = students::get_student_by_id(&pool, &id).await.unwrap().unwrap();
+    assert_eq!(student.status.to_string(), "active");
+    assert_eq!(student.grade, "Grade 6");
+
+    // Applying the same roster again finds nothing left to do.
+    let diff = roster::preview_roster_import(&pool, &path, &mapping(), true)
+        .await
+        .unwrap();
+    let diff = serde_json::to_value(&diff).unwrap();
+    assert_eq!(diff["reactivated"].as_array().unwrap().len(), 0);
+    assert_eq!(diff["unchanged"], 1);
+
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use common::test_pool;
+use tiza_lib::services::settings::{self, Secret, SettingsError};
+
+#[tokio::test]
+async fn secrets_are_never_part_of_the_settings() {
+    let pool = test_pool().await;
+    settings::set_secret(&pool, Secret::SmtpPassword, " hunter2 ")
+        .await
+        .unwrap();
+
+    let current = settings::get_settings(&pool).await.unwrap();
+    assert!(current.smtp_password_set);
+    assert!(!current.api_token_set);
+    assert!(!serde_json::to_string(&current).unwrap().contains("hunter2"));
+
+    // Saving the settings the UI sent back leaves the password alone.
+    settings::update_settings(&pool, &current).await.unwrap();
+    assert_eq!(
+        settings::get_secret(&pool, Secret::SmtpPassword).await.unwrap(),
+        " hunter2 "
+    );
+
+    settings::set_secret(&pool, Secret::SmtpPassword, "").await.unwrap();
+    assert!(!settings::get_settings(&pool).await.unwrap().smtp_password_set);
+}
+
+#[tokio::test]
+async fn the_api_needs_a_token_before_it_can_be_enabled() {
+    let pool = test_pool().await;
+    let mut current = settings::get_settings(&pool).await.unwrap();
+    current.api_enabled = true;
+
+    let refused = settings::update_settings(&pool, &current).await;
+    assert!(matches!(
+        refused,
+        Err(SettingsError::Invalid { field: "api_token", .. })
+    ));
+
+    let token = settings::generate_api_token(&pool).await.unwrap();
+    assert_eq!(settings::get_secret(&pool, Secret::ApiToken).await.unwrap(), token);
+    let saved = settings::update_settings(&pool, &current).await.unwrap();
+    assert!(saved.api_enabled && saved.api_token_set);
+}
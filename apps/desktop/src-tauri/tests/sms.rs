@@ -0,0 +1,131 @@
+mod common;
+
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use chrono::{Duration, Utc};
+use common::{open_every_day, test_pool, BookFixture, LoanFixture, StudentFixture};
+use std::sync::{Arc, Mutex};
+use tiza_lib::services::notifications;
+use tiza_lib::services::sms::{self, FileGateway, HttpGateway, SmsGateway};
+
+#[tokio::test]
+async fn reminders_are_texted_to_normalized_numbers_once() {
+    let pool = test_pool().await;
+    open_every_day(&pool).await;
+    let book_id = BookFixture::default().quantity(5).insert(&pool).await;
+
+    let late = StudentFixture::default()
+        .phone_number("0788 123 456")
+        .insert(&pool)
+        .await;
+    let opted_out = StudentFixture::default()
+        .name("Eric Mugisha")
+        .phone_number("0788 654 321")
+        .sms_opt_out()
+        .insert(&pool)
+        .await;
+    let bad_number = StudentFixture::default()
+        .name("Grace Ineza")
+        .phone_number("12")
+        .insert(&pool)
+        .await;
+    let no_phone = StudentFixture::default()
+        .name("Jean Habimana")
+        .insert(&pool)
+        .await;
+
+    for student_id in [&late, &opted_out, &bad_number, &no_phone] {
+        LoanFixture::new(&book_id, student_id)
+            .due_date(Utc::now() - Duration::days(4))
+            .insert(&pool)
+            .await;
+    }
+
+    let outbox = std::env::temp_dir().join(format!("tiza-sms-{}.txt", uuid::Uuid::new_v4()));
+    let gateway = FileGateway::new(Some(outbox.clone()));
+
+    let run = sms::send_sms_notices(&pool, &gateway).await.unwrap();
+    assert_eq!((run.sent, run.skipped, run.failed, run.invalid), (1, 0, 0, 1));
+
+    let written = std::fs::read_to_string(&outbox).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    assert_eq!(lines.len(), 1);
+    let fields: Vec<&str> = lines[0].split('\t').collect();
+    assert_eq!(fields[1], "+250788123456");
+    assert!(fields[2].contains("overdue"));
+
+    let run = sms::send_sms_notices(&pool, &gateway).await.unwrap();
+    assert_eq!((run.sent, run.skipped), (0, 1));
+    assert_eq!(std::fs::read_to_string(&outbox).unwrap().lines().count(), 1);
+
+    let log = notifications::get_notification_log(&pool, 10).await.unwrap();
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].channel, "sms");
+    assert_eq!(log[0].recipient, "+250788123456");
+
+    std::fs::remove_file(outbox).unwrap();
+}
+
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<(String, Option<String>, String)>>>);
+
+async fn capture(
+    State(captured): State<Captured>,
+    headers: HeaderMap,
+    body: String,
+) -> StatusCode {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    captured.0.lock().unwrap().push((
+        header("content-type").unwrap_or_default(),
+        header("authorization"),
+        body,
+    ));
+    StatusCode::OK
+}
+
+#[tokio::test]
+async fn http_gateway_fills_in_the_request_template() {
+    let captured = Captured::default();
+    let app = Router::new()
+        .route("/send", post(capture))
+        .route("/fail", post(|| async { StatusCode::BAD_GATEWAY }))
+        .with_state(captured.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let url = format!("http://127.0.0.1:{}/send?to={{to}}", port);
+    let json = HttpGateway::new(&url, r#"{"to":"{to}","text":"{message}"}"#, "Bearer secret")
+        .unwrap();
+    json.send("+250788123456", "Say \"hi\"\nthen leave")
+        .await
+        .unwrap();
+
+    let form = HttpGateway::new(&url, "to={to}&text={message}", "").unwrap();
+    form.send("+250788123456", "A & B").await.unwrap();
+
+    let requests = captured.0.lock().unwrap().clone();
+    assert_eq!(requests.len(), 2);
+
+    let (content_type, authorization, body) = &requests[0];
+    assert_eq!(content_type, "application/json");
+    assert_eq!(authorization.as_deref(), Some("Bearer secret"));
+    let body: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(body["to"], "+250788123456");
+    assert_eq!(body["text"], "Say \"hi\"\nthen leave");
+
+    let (content_type, authorization, body) = &requests[1];
+    assert_eq!(content_type, "application/x-www-form-urlencoded");
+    assert_eq!(authorization, &None);
+    assert_eq!(body, "to=%2B250788123456&text=A%20%26%20B");
+
+    let failing = HttpGateway::new(&format!("http://127.0.0.1:{}/fail", port), "x", "").unwrap();
+    assert!(failing.send("+250788123456", "hello").await.is_err());
+}
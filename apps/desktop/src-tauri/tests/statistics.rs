@@ -0,0 +1,167 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use common::{
+    active_loan_id, open_every_day, set_due_date, test_pool, BookFixture, LoanFixture,
+    StudentFixture,
+};
+use tiza_lib::services::calendar;
+use tiza_lib::services::clock::LibraryClock;
+use tiza_lib::services::lendings;
+use tiza_lib::services::statistics::{self, StatsFilter};
+
+#[tokio::test]
+async fn only_unreturned_loans_due_before_today_are_overdue() {
+    let pool = test_pool().await;
+    open_every_day(&pool).await;
+    let clock = LibraryClock::load(&pool).await.unwrap();
+    let today = clock.today();
+
+    let book_id = BookFixture::default().quantity(10).insert(&pool).await;
+    let late = StudentFixture::default().name("Late").insert(&pool).await;
+    let due_today = StudentFixture::default().name("Due today").insert(&pool).await;
+    let returned = StudentFixture::default().name("Returned").insert(&pool).await;
+    let on_time = StudentFixture::default().name("On time").insert(&pool).await;
+
+    let ten_days_ago = clock.end_of_day(today - Duration::days(10));
+    LoanFixture::new(&book_id, &late)
+        .due_date(ten_days_ago)
+        .insert(&pool)
+        .await;
+    LoanFixture::new(&book_id, &due_today)
+        .due_date(clock.end_of_day(today))
+        .insert(&pool)
+        .await;
+    LoanFixture::new(&book_id, &returned)
+        .due_date(ten_days_ago)
+        .returned_at(Utc::now())
+        .insert(&pool)
+        .await;
+    LoanFixture::new(&book_id, &on_time)
+        .due_date(clock.end_of_day(today + Duration::days(3)))
+        .insert(&pool)
+        .await;
+
+    let overdue = statistics::get_overdue_books(&pool, &StatsFilter::default())
+        .await
+        .unwrap();
+
+    assert_eq!(overdue.len(), 1);
+    assert_eq!(overdue[0].student_name, "Late");
+    assert_eq!(overdue[0].due_date, ten_days_ago);
+    assert_eq!(overdue[0].days_overdue, 10);
+}
+
+#[tokio::test]
+async fn closed_days_do_not_count_as_overdue() {
+    let pool = test_pool().await;
+    open_every_day(&pool).await;
+    let today = LibraryClock::load(&pool).await.unwrap().today();
+    calendar::add_closure(
+        &pool,
+        "Mid-term break",
+        today - Duration::days(6),
+        today - Duration::days(4),
+    )
+    .await
+    .unwrap();
+
+    // Reload so the clock sees the closure.
+    let clock = LibraryClock::load(&pool).await.unwrap();
+    let book_id = BookFixture::default().insert(&pool).await;
+    let student_id = StudentFixture::default().insert(&pool).await;
+    LoanFixture::new(&book_id, &student_id)
+        .due_date(clock.end_of_day(today - Duration::days(10)))
+        .insert(&pool)
+        .await;
+
+    let overdue = statistics::get_overdue_books(&pool, &StatsFilter::default())
+        .await
+        .unwrap();
+
+    assert_eq!(overdue.len(), 1);
+    assert_eq!(overdue[0].days_overdue, 7);
+}
+
+#[tokio::test]
+async fn dashboard_stats_add_up() {
+    let pool = test_pool().await;
+    let clock = LibraryClock::load(&pool).await.unwrap();
+
+    let novel = BookFixture::default()
+        .category("Fiction")
+        .quantity(3)
+        .insert(&pool)
+        .await;
+    let atlas = BookFixture::default()
+        .title("World Atlas")
+        .category("Science")
+        .quantity(1)
+        .insert(&pool)
+        .await;
+    let aline = StudentFixture::default().grade("Grade 5").insert(&pool).await;
+    let brian = StudentFixture::default()
+        .name("Brian Mugisha")
+        .grade("Grade 6")
+        .insert(&pool)
+        .await;
+    let claire = StudentFixture::default()
+        .name("Claire Ishimwe")
+        .grade("Grade 5")
+        .insert(&pool)
+        .await;
+
+    // Claire borrows and returns the novel; Aline and Brian still have theirs,
+    // and Brian's is overdue.
+    lendings::create_lending(&pool, &novel, &claire, None)
+        .await
+        .unwrap();
+    let claire_loan = active_loan_id(&pool, &claire).await;
+    lendings::return_lending(&pool, &claire_loan).await.unwrap();
+    lendings::create_lending(&pool, &novel, &aline, None)
+        .await
+        .unwrap();
+    lendings::create_lending(&pool, &atlas, &brian, None)
+        .await
+        .unwrap();
+    let brian_loan = active_loan_id(&pool, &brian).await;
+    set_due_date(&pool, &brian_loan, clock.end_of_day(clock.today() - Duration::days(3))).await;
+
+    let stats = statistics::get_dashboard_stats(&pool, &StatsFilter::default())
+        .await
+        .unwrap();
+    let current = &stats.current;
+
+    assert_eq!(current.total_students, 3);
+    // 2 novels on the shelf, the atlas out, plus the 2 loans.
+    assert_eq!(current.total_books, 4);
+    assert_eq!(current.available_books, 2);
+    assert_eq!(current.books_on_loan, 2);
+    assert_eq!(current.overdue_books, 1);
+    assert_eq!(current.utilization_rate, 50.0);
+
+    let mut on_loan: Vec<(&str, i64, f64)> = current
+        .categories_on_loan
+        .iter()
+        .map(|c| (c.name.as_str(), c.count, c.percentage))
+        .collect();
+    on_loan.sort_by(|a, b| a.0.cmp(b.0));
+    assert_eq!(on_loan, vec![("Fiction", 1, 50.0), ("Science", 1, 50.0)]);
+
+    assert_eq!(stats.historical.total_loans, 3);
+    let fiction = &stats.historical.loans_by_category[0];
+    assert_eq!((fiction.name.as_str(), fiction.count), ("Fiction", 2));
+
+    let grade_5 = StatsFilter {
+        grade: Some("Grade 5".to_string()),
+        ..Default::default()
+    };
+    let stats = statistics::get_dashboard_stats(&pool, &grade_5)
+        .await
+        .unwrap();
+
+    assert_eq!(stats.current.total_students, 2);
+    assert_eq!(stats.current.books_on_loan, 1);
+    assert_eq!(stats.current.overdue_books, 0);
+    assert_eq!(stats.historical.total_loans, 2);
+}
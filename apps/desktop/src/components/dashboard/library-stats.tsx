@@ -12,7 +12,8 @@ export function LibraryStats() {
     queryKey: ["dashboardStats"],
     queryFn: getDashboardStats,
   });
-  const stats = statsReq.data;
+  const stats = statsReq.data?.current;
+  const history = statsReq.data?.historical;
   if (statsReq.isLoading) {
     return (
       <CardContent className="p-6">
@@ -36,7 +37,7 @@ export function LibraryStats() {
     );
   }
 
-  if (!stats) return null;
+  if (!stats || !history) return null;
   return (
     <Card>
       <CardHeader>
@@ -91,7 +92,7 @@ export function LibraryStats() {
               <div className="flex justify-between items-center mb-2">
                 <span className="text-sm font-medium">Utilization Rate</span>
                 <span className="text-sm font-semibold">
-                  {stats.utilization_rate}%
+                  {stats.utilization_rate.toFixed(1)}%
                 </span>
               </div>
               <Progress value={stats.utilization_rate} className="h-2" />
@@ -101,7 +102,7 @@ export function LibraryStats() {
           <div className="space-y-4">
             <h4 className="text-sm font-medium">Popular Categories</h4>
             <div className="space-y-3">
-              {stats.popular_categories.map((category, index) => (
+              {history.loans_by_category.map((category, index) => (
                 <div key={index} className="space-y-2">
                   <div className="flex justify-between items-center">
                     <span className="text-sm text-muted-foreground">
@@ -112,7 +113,7 @@ export function LibraryStats() {
                         {category.count} loans
                       </span>
                       <Badge variant="outline" className="text-xs">
-                        {category.percentage}%
+                        {category.percentage.toFixed(1)}%
                       </Badge>
                     </div>
                   </div>
@@ -16,14 +16,17 @@ import {
   CardTitle,
 } from "@/components/ui/card";
 import { Badge } from "@/components/ui/badge";
+import { Switch } from "@/components/ui/switch";
+import { toast } from "sonner";
 import { format } from "date-fns";
 import { Eye, Calendar, BookOpen, CheckCircle, Clock } from "lucide-react";
-import { useQuery } from "@tanstack/react-query";
+import { useMutation, useQuery, useQueryClient } from "@tanstack/react-query";
 import {
   getBookById,
   getLendingRecordsByBookId,
   getLendingRecordsByStudentId,
   getStudentById,
+  setSmsOptOut,
 } from "@/utils/api";
 import type { Lending } from "@/types/models";
 
@@ -72,6 +75,18 @@ export function DetailsDialog({
     queryFn: () => getLendingRecordsByStudentId(id),
     enabled: type === "student" && isDialogOpen,
   });
+  const queryClient = useQueryClient();
+  const smsOptOutMutation = useMutation({
+    mutationFn: (optOut: boolean) => setSmsOptOut(id, optOut),
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ["getStudentById", id] });
+    },
+    onError: (error) => {
+      toast.error("Could not update SMS reminders", {
+        description: String(error),
+      });
+    },
+  });
 
   const renderLendingHistory = (
     lendings: Lending[] | null | undefined,
@@ -248,6 +263,33 @@ export function DetailsDialog({
                   {student.phone_number || "Not provided"}
                 </p>
               </div>
+              <div>
+                <h3 className="font-semibold">Email</h3>
+                <p className="text-sm text-muted-foreground">
+                  {student.email || "Not provided"}
+                </p>
+              </div>
+              <div>
+                <h3 className="font-semibold">Guardian Email</h3>
+                <p className="text-sm text-muted-foreground">
+                  {student.guardian_email || "Not provided"}
+                </p>
+              </div>
+              <div>
+                <h3 className="font-semibold">SMS Reminders</h3>
+                <div className="flex items-center gap-2 pt-1">
+                  <Switch
+                    checked={!student.sms_opt_out}
+                    disabled={smsOptOutMutation.isPending}
+                    onCheckedChange={(checked) =>
+                      smsOptOutMutation.mutate(!checked)
+                    }
+                  />
+                  <span className="text-sm text-muted-foreground">
+                    {student.sms_opt_out ? "Opted out" : "On"}
+                  </span>
+                </div>
+              </div>
               <div>
                 <h3 className="font-semibold">Student ID</h3>
                 <p className="text-sm text-muted-foreground">
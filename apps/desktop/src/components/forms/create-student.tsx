@@ -57,6 +57,16 @@ export const studentSchema = z.object({
       }
     )
     .optional(),
+  email: z
+    .string()
+    .email({ message: "Enter a valid email address." })
+    .or(z.literal(""))
+    .optional(),
+  guardian_email: z
+    .string()
+    .email({ message: "Enter a valid email address." })
+    .or(z.literal(""))
+    .optional(),
   status: z.enum(["Active", "Inactive"]).default("Active"),
   created_at: z.string().optional(),
 });
@@ -90,6 +100,8 @@ export default function CreateStudent({ student }: { student?: Student }) {
       name: student ? student.name : "",
       grade: student ? student.grade : "P6",
       phone_number: student ? student.phone_number ?? "" : "",
+      email: student ? student.email ?? "" : "",
+      guardian_email: student ? student.guardian_email ?? "" : "",
       student_id: student ? student.student_id : "",
     },
   });
@@ -101,10 +113,14 @@ export default function CreateStudent({ student }: { student?: Student }) {
             ...values,
             id: student.id,
             phone_number: values.phone_number ?? null,
+            email: values.email || null,
+            guardian_email: values.guardian_email || null,
           })
         : createMutation.mutate({
             ...values,
             phone_number: values.phone_number ?? null,
+            email: values.email || null,
+            guardian_email: values.guardian_email || null,
           });
     } catch (error) {
       toast.error(`Failed to ${student ? "update" : "create"} student`, {
@@ -197,6 +213,35 @@ export default function CreateStudent({ student }: { student?: Student }) {
               </FormItem>
             )}
           />
+          <FormField
+            control={form.control}
+            name="email"
+            render={({ field }) => (
+              <FormItem>
+                <FormLabel>Email</FormLabel>
+                <FormControl>
+                  <Input placeholder="student@school.rw" {...field} />
+                </FormControl>
+                <FormMessage />
+              </FormItem>
+            )}
+          />
+          <FormField
+            control={form.control}
+            name="guardian_email"
+            render={({ field }) => (
+              <FormItem>
+                <FormLabel>Guardian email</FormLabel>
+                <FormControl>
+                  <Input placeholder="parent@example.com" {...field} />
+                </FormControl>
+                <FormDescription>
+                  Due-soon and overdue reminders go to both addresses
+                </FormDescription>
+                <FormMessage />
+              </FormItem>
+            )}
+          />
 
           <DialogFooter>
             <Button type="button" variant="outline">
@@ -0,0 +1,259 @@
+import { useCallback, useEffect, useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { format } from "date-fns";
+import { BookOpen, LogOut, Lock } from "lucide-react";
+import { toast } from "sonner";
+import { cn } from "@/lib/utils";
+import { Badge } from "@/components/ui/badge";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from "@/components/ui/card";
+import {
+  exitKioskMode,
+  getKioskLoans,
+  getKioskStatus,
+  kioskCheckout,
+  kioskLogin,
+  kioskLogout,
+  kioskRenew,
+  kioskReturn,
+} from "@/utils/api";
+import type { KioskLoan, KioskStudent } from "@/types";
+
+const errorMessage = (error: unknown) =>
+  typeof error === "string" ? error : "Something went wrong";
+
+export function KioskScreen() {
+  const [student, setStudent] = useState<KioskStudent | null>(null);
+  const [loans, setLoans] = useState<KioskLoan[]>([]);
+  const [showExit, setShowExit] = useState(false);
+
+  const signOut = useCallback(() => {
+    setStudent(null);
+    setLoans([]);
+  }, []);
+
+  const refreshLoans = useCallback(async () => {
+    try {
+      setLoans(await getKioskLoans());
+    } catch (error) {
+      toast.error(errorMessage(error));
+      signOut();
+    }
+  }, [signOut]);
+
+  useEffect(() => {
+    getKioskStatus().then((status) => {
+      if (status.student) {
+        setStudent(status.student);
+        refreshLoans();
+      }
+    });
+
+    const unlisten = listen("kiosk_logged_out", signOut);
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [refreshLoans, signOut]);
+
+  const run = async (action: () => Promise<unknown>, success: string) => {
+    try {
+      await action();
+      toast.success(success);
+      await refreshLoans();
+    } catch (error) {
+      toast.error(errorMessage(error));
+    }
+  };
+
+  return (
+    <div className="min-h-screen flex flex-col items-center justify-center gap-6 p-8">
+      {student ? (
+        <StudentView
+          student={student}
+          loans={loans}
+          onCheckout={(code) =>
+            run(() => kioskCheckout(code), "Enjoy your book!")
+          }
+          onReturn={(id) => run(() => kioskReturn(id), "Book returned")}
+          onRenew={(id) =>
+            run(
+              async () => {
+                const due = await kioskRenew(id);
+                toast.info(`Now due ${format(new Date(due), "MMM dd, yyyy")}`);
+              },
+              "Loan renewed"
+            )
+          }
+          onSignOut={() => kioskLogout().then(signOut)}
+        />
+      ) : (
+        <ScanForm
+          label="Scan your student card"
+          placeholder="Student ID"
+          onSubmit={async (card) => {
+            try {
+              setStudent(await kioskLogin(card));
+              await refreshLoans();
+            } catch (error) {
+              toast.error(errorMessage(error));
+            }
+          }}
+        />
+      )}
+
+      <div className="fixed bottom-4 right-4">
+        {showExit ? (
+          <ScanForm
+            label="Librarian PIN"
+            placeholder="PIN"
+            secret
+            onSubmit={async (pin) => {
+              try {
+                await exitKioskMode(pin);
+              } catch (error) {
+                toast.error(errorMessage(error));
+              }
+            }}
+            onCancel={() => setShowExit(false)}
+          />
+        ) : (
+          <Button variant="ghost" size="icon" onClick={() => setShowExit(true)}>
+            <Lock className="size-4" />
+          </Button>
+        )}
+      </div>
+    </div>
+  );
+}
+
+function ScanForm({
+  label,
+  placeholder,
+  secret = false,
+  onSubmit,
+  onCancel,
+}: {
+  label: string;
+  placeholder: string;
+  secret?: boolean;
+  onSubmit: (value: string) => void;
+  onCancel?: () => void;
+}) {
+  const [value, setValue] = useState("");
+
+  return (
+    <form
+      className="flex flex-col items-center gap-3"
+      onSubmit={(event) => {
+        event.preventDefault();
+        if (value.trim()) {
+          onSubmit(value.trim());
+          setValue("");
+        }
+      }}
+    >
+      <p className="text-lg font-medium">{label}</p>
+      <div className="flex gap-2">
+        <Input
+          autoFocus
+          type={secret ? "password" : "text"}
+          placeholder={placeholder}
+          value={value}
+          onChange={(event) => setValue(event.target.value)}
+        />
+        <Button type="submit">OK</Button>
+        {onCancel && (
+          <Button type="button" variant="outline" onClick={onCancel}>
+            Cancel
+          </Button>
+        )}
+      </div>
+    </form>
+  );
+}
+
+function StudentView({
+  student,
+  loans,
+  onCheckout,
+  onReturn,
+  onRenew,
+  onSignOut,
+}: {
+  student: KioskStudent;
+  loans: KioskLoan[];
+  onCheckout: (code: string) => void;
+  onReturn: (id: string) => void;
+  onRenew: (id: string) => void;
+  onSignOut: () => void;
+}) {
+  return (
+    <Card className="w-full max-w-2xl">
+      <CardHeader className="flex flex-row items-start justify-between">
+        <div className="space-y-1">
+          <CardTitle>Hello, {student.name}</CardTitle>
+          <CardDescription>
+            {student.grade} · {student.student_id}
+          </CardDescription>
+        </div>
+        <Button variant="outline" onClick={onSignOut}>
+          <LogOut className="size-4" />
+          Done
+        </Button>
+      </CardHeader>
+      <CardContent className="space-y-6">
+        <ScanForm
+          label="Borrow a book"
+          placeholder="Scan the ISBN"
+          onSubmit={onCheckout}
+        />
+
+        {!loans.length ? (
+          <div className="text-center py-8 text-muted-foreground">
+            <BookOpen className="size-8 mx-auto mb-4 opacity-50" />
+            <p>You have no books on loan.</p>
+          </div>
+        ) : (
+          <div className="space-y-4">
+            {loans.map((loan) => (
+              <div
+                key={loan.id}
+                className={cn(
+                  "p-4 rounded-lg border flex items-center justify-between gap-4",
+                  loan.overdue && "bg-destructive/5 border-destructive/20"
+                )}
+              >
+                <div className="space-y-1">
+                  <h4 className="font-medium">{loan.book_title}</h4>
+                  <p className="text-sm text-muted-foreground">
+                    by {loan.book_author}
+                  </p>
+                  <Badge variant={loan.overdue ? "destructive" : "secondary"}>
+                    Due {format(new Date(loan.due_date), "MMM dd, yyyy")}
+                  </Badge>
+                </div>
+                <div className="flex gap-2 shrink-0">
+                  <Button
+                    variant="outline"
+                    disabled={!loan.renewable}
+                    onClick={() => onRenew(loan.id)}
+                  >
+                    Renew
+                  </Button>
+                  <Button onClick={() => onReturn(loan.id)}>Return</Button>
+                </div>
+              </div>
+            ))}
+          </div>
+        )}
+      </CardContent>
+    </Card>
+  );
+}
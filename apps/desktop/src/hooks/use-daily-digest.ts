@@ -0,0 +1,34 @@
+import { useEffect } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { useNavigate } from "@tanstack/react-router";
+import { toast } from "sonner";
+import type { DailyDigest } from "@/types/api";
+
+/** Shows the morning digest sent by the backend as a toast. */
+export function useDailyDigest() {
+  const navigate = useNavigate();
+
+  useEffect(() => {
+    const unlisten = listen<DailyDigest>("daily_digest", ({ payload }) => {
+      const lines = [
+        `${payload.due_today.length} due today`,
+        `${payload.newly_overdue.length} newly overdue ` +
+          `(${payload.overdue_total} in all)`,
+      ];
+      if (payload.backup_missing) {
+        lines.push("No recent backup");
+      }
+      toast.info("Today's summary", {
+        description: lines.join(" · "),
+        duration: 15000,
+        action: {
+          label: "Dashboard",
+          onClick: () => navigate({ to: "/" }),
+        },
+      });
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [navigate]);
+}
@@ -0,0 +1,23 @@
+import { useState, useEffect } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { getKioskStatus } from "@/utils/api";
+
+/** Whether the app is locked to the student kiosk screen. */
+export function useKioskMode() {
+  const [active, setActive] = useState(false);
+
+  useEffect(() => {
+    getKioskStatus()
+      .then((status) => setActive(status.active))
+      .catch((error) => console.error("Failed to get kiosk status:", error));
+
+    const unlisten = listen<boolean>("kiosk_mode_changed", (event) =>
+      setActive(event.payload)
+    );
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  return active;
+}
@@ -13,6 +13,9 @@ import { AppSidebar } from "@/components/sidebar/app-sidebar";
 import ThemeModeToggler from "@/components/mode-toggle";
 import "../styles/index.css";
 import { useMenuHandler } from "@/utils/menu-api";
+import { useKioskMode } from "@/hooks/use-kiosk";
+import { useDailyDigest } from "@/hooks/use-daily-digest";
+import { KioskScreen } from "@/components/kiosk-screen";
 
 export const Route = createRootRoute({
   component: Root,
@@ -20,6 +23,8 @@ export const Route = createRootRoute({
 
 function Root() {
   useMenuHandler();
+  const kioskMode = useKioskMode();
+  useDailyDigest();
   const queryClient = new QueryClient({
     defaultOptions: {
       queries: {
@@ -44,22 +49,26 @@ function Root() {
       ]}
     >
       <QueryClientProvider client={queryClient}>
-        <SidebarProvider>
-          <AppSidebar />
-          <SidebarInset>
-            <header className="flex h-16 shrink-0 justify-between items-center gap-2 px-4">
-              <div className="flex items-center gap-2">
-                <SidebarTrigger className="-ml-1" />
-              </div>
-              <div className="flex gap-2">
-                <ThemeModeToggler />
-              </div>
-            </header>
-            <main className="my-4">
-              <Outlet />
-            </main>
-          </SidebarInset>
-        </SidebarProvider>
+        {kioskMode ? (
+          <KioskScreen />
+        ) : (
+          <SidebarProvider>
+            <AppSidebar />
+            <SidebarInset>
+              <header className="flex h-16 shrink-0 justify-between items-center gap-2 px-4">
+                <div className="flex items-center gap-2">
+                  <SidebarTrigger className="-ml-1" />
+                </div>
+                <div className="flex gap-2">
+                  <ThemeModeToggler />
+                </div>
+              </header>
+              <main className="my-4">
+                <Outlet />
+              </main>
+            </SidebarInset>
+          </SidebarProvider>
+        )}
         <ReactQueryDevtools />
         <TanStackRouterDevtools />
       </QueryClientProvider>
@@ -1,11 +1,23 @@
 export interface DashboardStats {
+  current: CurrentStats;
+  historical: HistoricalStats;
+}
+
+export interface CurrentStats {
   total_students: number;
   total_books: number;
   available_books: number;
   books_on_loan: number;
   overdue_books: number;
   utilization_rate: number;
-  popular_categories: CategoryStats[];
+  categories_on_loan: CategoryStats[];
+}
+
+export interface HistoricalStats {
+  from?: string;
+  to?: string;
+  total_loans: number;
+  loans_by_category: CategoryStats[];
 }
 
 export interface CategoryStats {
@@ -45,3 +57,50 @@ export interface RecentActivity {
   due_date?: string;
   created_at: string;
 }
+
+export interface KioskStudent {
+  id: string;
+  name: string;
+  grade: string;
+  student_id: string;
+}
+
+export interface KioskLoan {
+  id: string;
+  book_title: string;
+  book_author: string;
+  lent_at: string;
+  due_date: string;
+  renewal_count: number;
+  overdue: boolean;
+  renewable: boolean;
+}
+
+export interface KioskStatus {
+  active: boolean;
+  student?: KioskStudent;
+}
+
+export interface NotificationRun {
+  sent: number;
+  skipped: number;
+  failed: number;
+  invalid: number;
+}
+
+export interface DigestLoan {
+  id: string;
+  book_title: string;
+  student_name: string;
+  grade: string;
+  due_date: string;
+}
+
+export interface DailyDigest {
+  date: string;
+  due_today: DigestLoan[];
+  newly_overdue: DigestLoan[];
+  overdue_total: number;
+  last_backup: string | null;
+  backup_missing: boolean;
+}
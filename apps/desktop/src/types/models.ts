@@ -16,6 +16,9 @@ export interface Student {
   name: string;
   grade: string;
   phone_number: string | null;
+  email: string | null;
+  guardian_email: string | null;
+  sms_opt_out: boolean;
   student_id: string;
   status: "Active" | "Inactive";
   created_at: string;
@@ -4,6 +4,9 @@ import type {
   PopularBook,
   OverdueBook,
   RecentActivity,
+  KioskStatus,
+  KioskStudent,
+  KioskLoan,
 } from "@/types/api";
 import type {
   Book,
@@ -33,14 +36,24 @@ export const deleteBook = (id: string) => invoke<void>("delete_book", { id });
 export const getAllStudents = () => invoke<Student[]>("get_all_students");
 export const getStudentById = (id: string) =>
   invoke<Student>("get_student_by_id", { id });
-export const createStudent = (student: Omit<Student, "id" | "created_at">) => {
+export const createStudent = (
+  student: Omit<Student, "id" | "created_at" | "sms_opt_out">
+) => {
   return invoke<void>("create_student", {
     ...student,
     studentId: student.student_id,
+    guardianEmail: student.guardian_email,
   });
 };
-export const updateStudent = (student: Omit<Student, "created_at">) =>
-  invoke<void>("update_student", { ...student });
+export const updateStudent = (
+  student: Omit<Student, "created_at" | "sms_opt_out">
+) =>
+  invoke<void>("update_student", {
+    ...student,
+    guardianEmail: student.guardian_email,
+  });
+export const setSmsOptOut = (id: string, optOut: boolean) =>
+  invoke<void>("set_sms_opt_out", { id, optOut });
 export const deleteStudent = (id: string) =>
   invoke<void>("delete_student", { id });
 
@@ -63,5 +76,22 @@ export const updateLending = (lending: Lending) =>
   invoke<void>("update_lending", { ...lending });
 export const returnBook = (id: string) =>
   invoke<void>("return_lending", { id });
+export const renewLending = (id: string) =>
+  invoke<string>("renew_lending", { id });
 export const deleteLending = (id: string) =>
   invoke<void>("delete_lending", { id });
+
+// Kiosk functions
+export const getKioskStatus = () => invoke<KioskStatus>("kiosk_status");
+export const enterKioskMode = (pin: string) =>
+  invoke<void>("enter_kiosk_mode", { pin });
+export const exitKioskMode = (pin: string) =>
+  invoke<void>("exit_kiosk_mode", { pin });
+export const kioskLogin = (card: string) =>
+  invoke<KioskStudent>("kiosk_login", { card });
+export const kioskLogout = () => invoke<void>("kiosk_logout");
+export const getKioskLoans = () => invoke<KioskLoan[]>("kiosk_loans");
+export const kioskCheckout = (code: string) =>
+  invoke<void>("kiosk_checkout", { code });
+export const kioskReturn = (id: string) => invoke<void>("kiosk_return", { id });
+export const kioskRenew = (id: string) => invoke<string>("kiosk_renew", { id });
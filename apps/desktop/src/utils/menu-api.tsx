@@ -6,6 +6,8 @@ import { listen } from "@tauri-apps/api/event";
 import { makeGmailLink } from "@/lib/utils";
 import { schoolName } from "@/utils/constants";
 import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Label } from "@/components/ui/label";
 import {
   Dialog,
   DialogContent,
@@ -32,6 +34,7 @@ import {
   type NavigationAction,
 } from "./menu-config";
 import { toast } from "sonner";
+import type { NotificationRun } from "@/types";
 
 interface DialogState {
   isOpen: boolean;
@@ -54,6 +57,8 @@ interface BackupResult {
 interface RestoreResult {
   success: boolean;
   message: string;
+  passphrase_required: boolean;
+  path?: string;
 }
 
 export function useMenuHandler() {
@@ -130,9 +135,11 @@ export function useMenuHandler() {
   );
 
   // Tauri command handlers
-  const handleBackup = useCallback(async () => {
+  const handleBackup = useCallback(async (passphrase?: string) => {
     try {
-      const result = await invoke<BackupResult>("backup_database");
+      const result = await invoke<BackupResult>("backup_database", {
+        passphrase,
+      });
 
       if (result.success) {
         toast.success("Backup Successful", {
@@ -151,28 +158,96 @@ export function useMenuHandler() {
     }
   }, [toast]);
 
-  const handleRestore = useCallback(async () => {
+  const handleRestore = useCallback(
+    async (path?: string, passphrase?: string) => {
+      try {
+        const result = await invoke<RestoreResult>("restore_database", {
+          path,
+          passphrase,
+        });
+
+        if (result.passphrase_required) {
+          setDialogContent({
+            isOpen: true,
+            title: "Encrypted Backup",
+            content: (
+              <PassphraseContent
+                description={result.message}
+                submitLabel="Restore"
+                onSubmit={(passphrase) => {
+                  closeDialog();
+                  handleRestore(result.path, passphrase);
+                }}
+                onClose={closeDialog}
+              />
+            ),
+          });
+        } else if (result.success) {
+          toast.success("Restore Successful", {
+            description: result.message,
+          });
+
+          // Refresh the app after successful restore
+          setTimeout(() => {
+            window.location.reload();
+          }, 1500);
+        } else {
+          toast.error("Restore Failed", {
+            description: result.message,
+          });
+        }
+      } catch (error) {
+        console.error("Restore error:", error);
+        toast("Restore Error", {
+          description: "An unexpected error occurred during restore.",
+        });
+      }
+    },
+    [toast, closeDialog]
+  );
+
+  const handleArchiveExport = useCallback(async () => {
     try {
-      const result = await invoke<RestoreResult>("restore_database");
+      const result = await invoke<BackupResult>("export_library_archive");
 
       if (result.success) {
-        toast.success("Restore Successful", {
+        toast.success("Export Successful", {
+          description: result.message,
+        });
+      } else {
+        toast.error("Export Failed", {
+          description: result.message,
+        });
+      }
+    } catch (error) {
+      console.error("Archive export error:", error);
+      toast.error("Export Error", {
+        description: "An unexpected error occurred during export.",
+      });
+    }
+  }, [toast]);
+
+  const handleArchiveOpen = useCallback(async () => {
+    try {
+      const result = await invoke<RestoreResult>("open_library_archive");
+
+      if (result.success) {
+        toast.success("Library Opened", {
           description: result.message,
         });
 
-        // Refresh the app after successful restore
         setTimeout(() => {
           window.location.reload();
         }, 1500);
       } else {
-        toast.error("Restore Failed", {
+        toast.error("Open Failed", {
           description: result.message,
         });
       }
     } catch (error) {
-      console.error("Restore error:", error);
-      toast("Restore Error", {
-        description: "An unexpected error occurred during restore.",
+      console.error("Archive open error:", error);
+      toast.error("Open Error", {
+        description: "An unexpected error occurred while opening the archive.",
       });
     }
   }, [toast]);
@@ -265,16 +340,102 @@ export function useMenuHandler() {
 
     export: handleExport,
 
-    backup: handleBackup,
+    archive_export: handleArchiveExport,
+
+    archive_open: () => {
+      showConfirmDialog(
+        "Open Library Archive",
+        "This will replace your current library with the one in the selected archive. Your current data will be backed up automatically. Are you sure you want to continue?",
+        handleArchiveOpen
+      );
+    },
+
+    backup: () => handleBackup(),
+
+    backup_encrypted: () => {
+      setDialogContent({
+        isOpen: true,
+        title: "Encrypted Backup",
+        content: (
+          <PassphraseContent
+            description="The backup can only be restored with this passphrase. Keep it somewhere safe; it cannot be recovered."
+            submitLabel="Backup"
+            confirm
+            onSubmit={(passphrase) => {
+              closeDialog();
+              handleBackup(passphrase);
+            }}
+            onClose={closeDialog}
+          />
+        ),
+      });
+    },
 
     restore: () => {
       showConfirmDialog(
         "Restore Database",
         "This will replace your current database with the selected backup. Your current data will be backed up automatically. Are you sure you want to continue?",
-        handleRestore
+        () => handleRestore()
       );
     },
 
+    email_reminders: async () => {
+      try {
+        const run = await invoke<NotificationRun>("send_email_reminders");
+        const description =
+          `${run.sent} sent, ${run.skipped} already sent, ` +
+          `${run.failed} failed`;
+        if (run.failed > 0) {
+          toast.error("Some reminders failed", { description });
+        } else {
+          toast.success("Email reminders sent", { description });
+        }
+      } catch (error) {
+        toast.error("Email Reminders", { description: String(error) });
+      }
+    },
+
+    sms_reminders: async () => {
+      try {
+        const run = await invoke<NotificationRun>("send_sms_reminders");
+        const description =
+          `${run.sent} sent, ${run.skipped} already sent, ` +
+          `${run.failed} failed, ${run.invalid} invalid numbers`;
+        if (run.failed > 0) {
+          toast.error("Some reminders failed", { description });
+        } else {
+          toast.success("SMS reminders sent", { description });
+        }
+      } catch (error) {
+        toast.error("SMS Reminders", { description: String(error) });
+      }
+    },
+
+    kiosk: () => {
+      setDialogContent({
+        isOpen: true,
+        title: "Student Kiosk Mode",
+        content: (
+          <PassphraseContent
+            description="Students will only be able to see their loans, borrow, return and renew books. Choose a PIN of at least 4 characters; it is needed to leave kiosk mode."
+            submitLabel="Start Kiosk"
+            label="PIN"
+            confirmLabel="Confirm PIN"
+            confirm
+            onSubmit={async (pin) => {
+              closeDialog();
+              try {
+                await invoke("enter_kiosk_mode", { pin });
+              } catch (error) {
+                toast.error("Kiosk Mode", { description: String(error) });
+              }
+            }}
+            onClose={closeDialog}
+          />
+        ),
+      });
+    },
+
     about: () => {
       setDialogContent({
         isOpen: true,
@@ -346,6 +507,29 @@ export function useMenuHandler() {
     [menuActionHandlers]
   );
 
+  // Offer to bring an existing library over on a fresh installation
+  useEffect(() => {
+    invoke<boolean>("is_new_library")
+      .then((isNew) => {
+        if (isNew) {
+          setDialogContent({
+            isOpen: true,
+            title: `Welcome to ${schoolName}`,
+            content: (
+              <FirstRunContent
+                onOpenArchive={() => {
+                  closeDialog();
+                  handleArchiveOpen();
+                }}
+                onClose={closeDialog}
+              />
+            ),
+          });
+        }
+      })
+      .catch(console.error);
+  }, []);
+
   // Effect to setup menu and event listeners
   useEffect(() => {
     // Initialize the menu
@@ -477,6 +661,97 @@ export function useMenuHandler() {
 }
 
 // Dialog Content Components remain the same...
+const FirstRunContent = ({
+  onOpenArchive,
+  onClose,
+}: {
+  onOpenArchive: () => void;
+  onClose: () => void;
+}) => (
+  <div className="space-y-4">
+    <p className="text-sm text-muted-foreground">
+      Moving from another computer? Open a library archive (.tiza) exported
+      there to bring over all books, students, loans and settings.
+    </p>
+
+    <div className="flex justify-end gap-2 pt-4">
+      <Button variant="outline" onClick={onClose}>
+        Start Empty
+      </Button>
+      <Button onClick={onOpenArchive}>Open Library Archive</Button>
+    </div>
+  </div>
+);
+
+const PassphraseContent = ({
+  description,
+  submitLabel,
+  label = "Passphrase",
+  confirmLabel = "Confirm passphrase",
+  confirm = false,
+  onSubmit,
+  onClose,
+}: {
+  description: string;
+  submitLabel: string;
+  label?: string;
+  confirmLabel?: string;
+  confirm?: boolean;
+  onSubmit: (passphrase: string) => void;
+  onClose: () => void;
+}) => {
+  const [passphrase, setPassphrase] = useState("");
+  const [confirmation, setConfirmation] = useState("");
+  const mismatch = confirm && confirmation !== passphrase;
+
+  return (
+    <form
+      className="space-y-4"
+      onSubmit={(event) => {
+        event.preventDefault();
+        if (passphrase && !mismatch) {
+          onSubmit(passphrase);
+        }
+      }}
+    >
+      <p className="text-sm text-muted-foreground">{description}</p>
+
+      <div className="space-y-2">
+        <Label htmlFor="backup-passphrase">{label}</Label>
+        <Input
+          id="backup-passphrase"
+          type="password"
+          autoFocus
+          value={passphrase}
+          onChange={(event) => setPassphrase(event.target.value)}
+        />
+      </div>
+
+      {confirm && (
+        <div className="space-y-2">
+          <Label htmlFor="backup-passphrase-confirm">{confirmLabel}</Label>
+          <Input
+            id="backup-passphrase-confirm"
+            type="password"
+            value={confirmation}
+            aria-invalid={mismatch && confirmation.length > 0}
+            onChange={(event) => setConfirmation(event.target.value)}
+          />
+        </div>
+      )}
+
+      <div className="flex justify-end gap-2 pt-4">
+        <Button type="button" variant="outline" onClick={onClose}>
+          Cancel
+        </Button>
+        <Button type="submit" disabled={!passphrase || mismatch}>
+          {submitLabel}
+        </Button>
+      </div>
+    </form>
+  );
+};
+
 const AboutContent = ({ onClose }: { onClose: () => void }) => (
   <div className="space-y-4">
     <div className="text-center">
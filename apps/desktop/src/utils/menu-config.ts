@@ -12,9 +12,15 @@ export type MenuAction =
   | "new"
   | "import"
   | "export"
+  | "archive_export"
+  | "archive_open"
   | "refresh"
   | "backup"
+  | "backup_encrypted"
   | "restore"
+  | "kiosk"
+  | "email_reminders"
+  | "sms_reminders"
   | "settings"
   | "about"
   | "help"
@@ -74,6 +80,17 @@ export async function createAppMenu() {
         await createMenuItem("import", "Import Data", "import"),
         await createMenuItem("export", "Export Data", "export"),
         await PredefinedMenuItem.new({ text: "", item: "Separator" }),
+        await createMenuItem(
+          "archive_open",
+          "Open Library Archive...",
+          "archive_open"
+        ),
+        await createMenuItem(
+          "archive_export",
+          "Export Library Archive...",
+          "archive_export"
+        ),
+        await PredefinedMenuItem.new({ text: "", item: "Separator" }),
         await createMenuItem("settings", "Settings", "settings"),
         await PredefinedMenuItem.new({ text: "", item: "Separator" }),
         await PredefinedMenuItem.new({ text: "Quit", item: "Quit" }),
@@ -118,8 +135,25 @@ export async function createAppMenu() {
       text: "Tools",
       items: [
         await createMenuItem("backup", "Backup Database", "backup"),
+        await createMenuItem(
+          "backup_encrypted",
+          "Encrypted Backup...",
+          "backup_encrypted"
+        ),
         await createMenuItem("restore", "Restore Database", "restore"),
         await PredefinedMenuItem.new({ text: "", item: "Separator" }),
+        await createMenuItem("kiosk", "Student Kiosk Mode...", "kiosk"),
+        await createMenuItem(
+          "email_reminders",
+          "Send Email Reminders",
+          "email_reminders"
+        ),
+        await createMenuItem(
+          "sms_reminders",
+          "Send SMS Reminders",
+          "sms_reminders"
+        ),
+        await PredefinedMenuItem.new({ text: "", item: "Separator" }),
         await MenuItem.new({
           id: "reports",
           text: "Generate Reports",